@@ -0,0 +1,40 @@
+//! Benchmark of the opcode handlers on a synthetic trace made of `ADD`s.
+
+use bus_mapping::mock::BlockData;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use eth_types::{evm_types::OpcodeId, geth_types::GethData, Bytecode};
+use mock::TestContext;
+
+const NUM_ADDS: usize = 1000;
+
+fn add_heavy_block() -> GethData {
+    let mut code = Bytecode::default();
+    code.push(1, 1u64);
+    for _ in 0..NUM_ADDS {
+        code.push(1, 1u64).write_op(OpcodeId::ADD);
+    }
+    code.write_op(OpcodeId::STOP);
+    TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+        .unwrap()
+        .into()
+}
+
+fn bench_add_heavy(c: &mut Criterion) {
+    let block = add_heavy_block();
+
+    // The trace is generated once, only the circuit input building is measured.
+    c.bench_function("gen_associated_ops ADD", |b| {
+        b.iter_batched(
+            || BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder(),
+            |mut builder| {
+                builder
+                    .handle_block(&block.eth_block, &block.geth_traces)
+                    .unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, bench_add_heavy);
+criterion_main!(benches);
@@ -2,12 +2,20 @@
 //! types from geth / web3 and outputs the circuit inputs.
 
 mod access;
+mod access_list;
 mod block;
 mod call;
+mod call_reversion;
+mod copy_validation;
+mod diagnostics;
 mod execution;
+mod frame_gas;
+mod gas_validation;
 mod input_state_ref;
 #[cfg(feature = "scroll")]
 mod l2;
+mod stack_depth;
+mod trace_memory;
 #[cfg(all(feature = "tracer-tests", feature = "enable-memory", test))]
 mod tracer_tests;
 mod transaction;
@@ -16,18 +24,23 @@ pub use self::block::BlockHead;
 use crate::{
     error::Error,
     evm::opcodes::{gen_associated_ops, gen_associated_steps},
+    l2_predeployed::l1_gas_price_oracle,
     operation::{self, CallContextField, Operation, RWCounter, StartOp, StorageOp, RW},
     rpc::GethClient,
-    state_db::{self, CodeDB, StateDB},
+    state_db::{self, CodeDB, StateDB, StateProvider},
     util::{hash_code_keccak, KECCAK_CODE_HASH_EMPTY},
 };
 pub use access::{Access, AccessSet, AccessValue, CodeSource};
+pub use access_list::{access_list_trace, accessed_storage_slots, AccessEvent};
 pub use block::{Block, BlockContext};
-pub use call::{Call, CallContext, CallKind};
+pub use call::{Call, CallContext, CallId, CallKind};
+pub use call_reversion::{call_reversion_info, reversible_op_count};
+pub use copy_validation::validate_copy_events;
 use core::fmt::Debug;
+pub use diagnostics::{Diagnostic, Diagnostics, Severity};
 use eth_types::{
     self,
-    evm_types::GasCost,
+    evm_types::{GasCost, MAX_CALL_DEPTH},
     geth_types,
     sign_types::{pk_bytes_le, pk_bytes_swap_endianness, SignData},
     Address, GethExecTrace, ToBigEndian, ToWord, Word, H256,
@@ -41,18 +54,24 @@ pub use execution::{
 use hex::decode_to_slice;
 
 use eth_types::sign_types::get_dummy_tx;
-use ethers_core::utils::keccak256;
+use ethers_core::utils::{get_contract_address, keccak256};
+pub use frame_gas::{frame_gas_trace, FrameGas};
+use gas_validation::check_step_gas;
+pub use gas_validation::GasMismatch;
 pub use input_state_ref::CircuitInputStateRef;
 use itertools::Itertools;
 use log::warn;
 #[cfg(feature = "scroll")]
 use mpt_zktrie::state::ZktrieState;
+pub use stack_depth::max_stack_depth;
 use std::{
     collections::{BTreeMap, HashMap},
     iter,
+    sync::Arc,
 };
+pub use trace_memory::fill_omitted_memory;
 pub use transaction::{
-    Transaction, TransactionContext, TxL1Fee, TX_L1_COMMIT_EXTRA_COST, TX_L1_FEE_PRECISION,
+    tx_hash, Transaction, TransactionContext, TxL1Fee, TX_L1_COMMIT_EXTRA_COST, TX_L1_FEE_PRECISION,
 };
 
 #[cfg(feature = "enable-stack")]
@@ -179,6 +198,25 @@ pub struct CircuitInputBuilder {
     pub block: Block,
     /// Block Context
     pub block_ctx: BlockContext,
+    /// Source of the state missing from `sdb`, fetched on demand
+    pub state_provider: Option<Arc<dyn StateProvider>>,
+    /// Compare the gas cost of every step with the gas consumed in the trace
+    pub validate_gas: bool,
+    /// Steps whose gas cost differs from the trace, when `validate_gas` is set
+    pub gas_mismatches: Vec<GasMismatch>,
+    /// Transfer the fee reward to the coinbase at the end of each tx
+    pub enable_coinbase_reward: bool,
+    /// Blocks are after Berlin, so the access list of EIP-2929 is warmed at the
+    /// beginning of each tx
+    pub berlin: bool,
+    /// Maximum depth of nested calls, deeper calls and creates fail with
+    /// `ErrDepth`
+    pub max_call_depth: usize,
+    /// Fill the memory omitted from the steps of the traces before handling
+    /// them
+    pub fill_omitted_memory: bool,
+    /// Warnings and errors raised while handling the block
+    pub diagnostics: Diagnostics,
     #[cfg(feature = "scroll")]
     /// Initial Zktrie Status for a incremental updating
     pub mpt_init_state: Option<ZktrieState>,
@@ -193,10 +231,112 @@ impl<'a> CircuitInputBuilder {
             code_db,
             block: block.clone(),
             block_ctx: BlockContext::new(),
+            state_provider: None,
+            validate_gas: false,
+            gas_mismatches: Vec::new(),
+            enable_coinbase_reward: true,
+            berlin: true,
+            max_call_depth: MAX_CALL_DEPTH,
+            fill_omitted_memory: false,
+            diagnostics: Diagnostics::default(),
             #[cfg(feature = "scroll")]
             mpt_init_state: Default::default(),
         }
     }
+
+    /// Fetch the accounts and storage slots missing from the [`StateDB`] from
+    /// `state_provider` on demand.
+    pub fn with_state_provider(mut self, state_provider: Arc<dyn StateProvider>) -> Self {
+        self.state_provider = Some(state_provider);
+        self
+    }
+
+    /// Load the accounts and storage slots read by the begin and the end of
+    /// `eth_tx` from the state provider: the caller, the callee or the created
+    /// account, the coinbase, and the slots of the L1 gas price oracle the L1
+    /// fee is computed from.
+    fn load_tx_state(&mut self, eth_tx: &eth_types::Transaction) {
+        let Some(provider) = self.state_provider.as_deref() else {
+            return;
+        };
+        let callee = eth_tx
+            .to
+            .unwrap_or_else(|| get_contract_address(eth_tx.from, eth_tx.nonce));
+        let mut accounts = vec![eth_tx.from, callee];
+        if let Some(block_num) = eth_tx.block_number.map(|n| n.as_u64()) {
+            if self.block.headers.contains_key(&block_num) {
+                accounts.push(self.block.coinbase(block_num));
+            }
+        }
+        for addr in &accounts {
+            self.sdb.load_account(provider, addr);
+        }
+        for slot in [
+            &l1_gas_price_oracle::BASE_FEE_SLOT,
+            &l1_gas_price_oracle::OVERHEAD_SLOT,
+            &l1_gas_price_oracle::SCALAR_SLOT,
+        ] {
+            self.sdb
+                .load_storage(provider, &l1_gas_price_oracle::ADDRESS, slot);
+        }
+    }
+
+    /// Collect the steps whose gas cost differs from the gas consumed in the
+    /// trace into `gas_mismatches`, instead of trusting the handlers.
+    pub fn with_validate_gas(mut self, validate_gas: bool) -> Self {
+        self.validate_gas = validate_gas;
+        self
+    }
+
+    /// Skip the reward of the coinbase at the end of each tx, together with
+    /// its code hash read, when `enable_coinbase_reward` is false. This is
+    /// meant for testing the EVM circuit in isolation from the transfer logic,
+    /// as the EndTx gadget still expects the reward.
+    pub fn with_enable_coinbase_reward(mut self, enable_coinbase_reward: bool) -> Self {
+        self.enable_coinbase_reward = enable_coinbase_reward;
+        self
+    }
+
+    /// Skip the warming of the precompiles, the caller and the callee at the
+    /// beginning of each tx when `berlin` is false, so that their first access
+    /// is cold as in pre-Berlin simulations. The traces and the BeginTx gadget
+    /// still assume Berlin.
+    pub fn with_berlin(mut self, berlin: bool) -> Self {
+        self.berlin = berlin;
+        self
+    }
+
+    /// Fail the calls and creates deeper than `max_call_depth` with `ErrDepth`
+    /// instead of the ones deeper than [`MAX_CALL_DEPTH`]. This is meant for
+    /// simulations only, as the traces and the EVM circuit still enforce
+    /// [`MAX_CALL_DEPTH`].
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// Reconstruct the memory of the trace steps whose memory was omitted by
+    /// the tracer because it didn't change, see [`fill_omitted_memory`].
+    pub fn with_fill_omitted_memory(mut self, fill_omitted_memory: bool) -> Self {
+        self.fill_omitted_memory = fill_omitted_memory;
+        self
+    }
+
+    /// Record the warnings and errors raised while handling the block into
+    /// `diagnostics`, in addition to logging them.
+    pub fn with_diagnostics(mut self, enabled: bool) -> Self {
+        self.diagnostics = Diagnostics::new(enabled);
+        self
+    }
+
+    /// Start the rw counter at `rwc_start` instead of 1, so that the operations
+    /// of this builder follow the ones of a previous chunk ending at
+    /// `rwc_start - 1`. The skipped counters still count as used rws when
+    /// padding the state circuit at the end of the block.
+    pub fn with_rwc_start(mut self, rwc_start: usize) -> Self {
+        self.block_ctx.rwc = RWCounter(rwc_start);
+        self
+    }
     /// Create a new CircuitInputBuilder from the given `eth_block` and
     /// `constants`.
     pub fn new_from_headers(
@@ -225,6 +365,11 @@ impl<'a> CircuitInputBuilder {
             code_db: &mut self.code_db,
             block: &mut self.block,
             block_ctx: &mut self.block_ctx,
+            state_provider: self.state_provider.as_deref(),
+            enable_coinbase_reward: self.enable_coinbase_reward,
+            berlin: self.berlin,
+            max_call_depth: self.max_call_depth,
+            diagnostics: &mut self.diagnostics,
             tx,
             tx_ctx,
         }
@@ -249,7 +394,32 @@ impl<'a> CircuitInputBuilder {
             ),
         );
 
-        Transaction::new(call_id, &self.sdb, &mut self.code_db, eth_tx, is_success)
+        let mut tx = Transaction::new(call_id, &self.sdb, &mut self.code_db, eth_tx, is_success)?;
+        let Some(header) = self.block.headers.get(&tx.block_num) else {
+            return Ok(tx);
+        };
+        // The gas price of an EIP-1559 tx is its effective gas price, which is
+        // what GASPRICE returns and what the fees are charged with.
+        if tx.tx_type.is_eip1559() {
+            let gas_price = tx.effective_gas_price(header.base_fee)?;
+            if !tx.gas_price.is_zero() && tx.gas_price != gas_price {
+                self.diagnostics.warn(
+                    self.block.txs.len() + 1,
+                    format!(
+                        "gas price {} of tx {:?} differs from its effective gas price {}",
+                        tx.gas_price, eth_tx.hash, gas_price
+                    ),
+                );
+            }
+            tx.gas_price = gas_price;
+        }
+        // An underpriced tx can't be included in a valid block, but a malformed
+        // one mustn't make the tip of the coinbase wrap around. It's rejected
+        // before any of its operations is generated.
+        if !tx.tx_type.is_l1_msg() && tx.gas_price < header.base_fee {
+            return Err(Error::EffectiveTipUnderflow);
+        }
+        Ok(tx)
     }
 
     /// Iterate over all generated CallContext RwCounterEndOfReversion
@@ -301,21 +471,27 @@ impl<'a> CircuitInputBuilder {
             let chunk_tx_idx = self.block.txs.len();
             if self.block.txs.len() >= self.block.circuits_params.max_txs {
                 if self.block.is_relaxed() {
-                    log::warn!(
-                        "tx num overflow, MAX_TX limit {}, {}th tx(inner idx: {}) {:?}, would process for partial block",
-                        self.block.circuits_params.max_txs,
-                        chunk_tx_idx,
-                        tx.transaction_index.unwrap_or_default(),
-                        tx.hash
+                    self.diagnostics.warn(
+                        chunk_tx_idx + 1,
+                        format!(
+                            "tx num overflow, MAX_TX limit {}, {}th tx(inner idx: {}) {:?}, would process for partial block",
+                            self.block.circuits_params.max_txs,
+                            chunk_tx_idx,
+                            tx.transaction_index.unwrap_or_default(),
+                            tx.hash
+                        ),
                     );
                     break;
                 } else {
-                    log::error!(
-                        "tx num overflow, MAX_TX limit {}, {}th tx(inner idx: {}) {:?}",
-                        self.block.circuits_params.max_txs,
-                        chunk_tx_idx,
-                        tx.transaction_index.unwrap_or_default(),
-                        tx.hash
+                    self.diagnostics.error(
+                        chunk_tx_idx + 1,
+                        format!(
+                            "tx num overflow, MAX_TX limit {}, {}th tx(inner idx: {}) {:?}",
+                            self.block.circuits_params.max_txs,
+                            chunk_tx_idx,
+                            tx.transaction_index.unwrap_or_default(),
+                            tx.hash
+                        ),
                     );
                     return Err(Error::InternalError("tx num overflow"));
                 }
@@ -348,39 +524,53 @@ impl<'a> CircuitInputBuilder {
                 let account_post_state: eth_types::l2_types::AccountProofWrapper =
                     account_post_state.clone();
                 if let Some(address) = account_post_state.address {
+                    let tx_id = self.block.txs.len();
                     let local_acc = self.sdb.get_account(&address).1;
                     log::trace!("local acc {local_acc:?}, trace acc {account_post_state:?}");
                     if local_acc.balance != account_post_state.balance.unwrap() {
-                        log::error!("incorrect balance")
+                        self.diagnostics
+                            .error(tx_id, format!("incorrect balance of {address:?}"))
                     }
                     if local_acc.nonce != account_post_state.nonce.unwrap().into() {
-                        log::error!("incorrect nonce")
+                        self.diagnostics
+                            .error(tx_id, format!("incorrect nonce of {address:?}"))
                     }
                     let p_hash = account_post_state.poseidon_code_hash.unwrap();
                     if p_hash.is_zero() {
                         if !local_acc.is_empty() {
-                            log::error!("incorrect poseidon_code_hash")
+                            self.diagnostics.error(
+                                tx_id,
+                                format!("incorrect poseidon_code_hash of {address:?}"),
+                            )
                         }
                     } else {
                         if local_acc.code_hash != p_hash {
-                            log::error!("incorrect poseidon_code_hash")
+                            self.diagnostics.error(
+                                tx_id,
+                                format!("incorrect poseidon_code_hash of {address:?}"),
+                            )
                         }
                     }
                     let k_hash = account_post_state.keccak_code_hash.unwrap();
                     if k_hash.is_zero() {
                         if !local_acc.is_empty() {
-                            log::error!("incorrect keccak_code_hash")
+                            self.diagnostics
+                                .error(tx_id, format!("incorrect keccak_code_hash of {address:?}"))
                         }
                     } else {
                         if local_acc.keccak_code_hash != k_hash {
-                            log::error!("incorrect keccak_code_hash")
+                            self.diagnostics
+                                .error(tx_id, format!("incorrect keccak_code_hash of {address:?}"))
                         }
                     }
                     if let Some(storage) = account_post_state.storage {
                         let k = storage.key.unwrap();
                         let local_v = self.sdb.get_storage(&address, &k).1;
                         if *local_v != storage.value.unwrap() {
-                            log::error!("incorrect storage for k = {k}");
+                            self.diagnostics.error(
+                                tx_id,
+                                format!("incorrect storage of {address:?} for k = {k}"),
+                            );
                         }
                     }
                 }
@@ -517,10 +707,12 @@ impl<'a> CircuitInputBuilder {
         #[allow(clippy::int_plus_one)]
         {
             if total_rws + 1 > max_rws {
-                log::error!(
-                    "total_rws + 1 > max_rws, total_rws={}, max_rws={}",
-                    total_rws,
-                    max_rws
+                state.diagnostics.error(
+                    dummy_tx_id,
+                    format!(
+                        "total_rws + 1 > max_rws, total_rws={}, max_rws={}",
+                        total_rws, max_rws
+                    ),
                 );
                 return Err(Error::InternalError("rws not enough"));
             };
@@ -551,6 +743,16 @@ impl<'a> CircuitInputBuilder {
         geth_trace: &GethExecTrace,
         is_last_tx: bool,
     ) -> Result<(), Error> {
+        let filled_trace;
+        let geth_trace = if self.fill_omitted_memory {
+            let mut trace = geth_trace.clone();
+            fill_omitted_memory(&mut trace.struct_logs);
+            filled_trace = trace;
+            &filled_trace
+        } else {
+            geth_trace
+        };
+        self.load_tx_state(eth_tx);
         let mut tx = self.new_tx(eth_tx, !geth_trace.failed)?;
 
         // Sanity check for transaction L1 fee.
@@ -560,10 +762,12 @@ impl<'a> CircuitInputBuilder {
             tx.l1_fee()
         };
         if tx_l1_fee != geth_trace.l1_fee {
-            log::error!(
-                "Mismatch tx_l1_fee: calculated = {}, real = {}",
-                tx_l1_fee,
-                geth_trace.l1_fee
+            self.diagnostics.error(
+                self.block.txs.len() + 1,
+                format!(
+                    "Mismatch tx_l1_fee: calculated = {}, real = {}",
+                    tx_l1_fee, geth_trace.l1_fee
+                ),
             );
         }
 
@@ -685,6 +889,18 @@ impl<'a> CircuitInputBuilder {
                 &mut state_ref,
                 &geth_trace.struct_logs[index..],
             )?;
+            if self.validate_gas {
+                if let Some(mismatch) = check_step_gas(
+                    self.block.txs.len(),
+                    index,
+                    &geth_trace.struct_logs[index..],
+                    &exec_steps,
+                ) {
+                    self.diagnostics
+                        .warn(mismatch.tx_index + 1, format!("gas mismatch: {mismatch:?}"));
+                    self.gas_mismatches.push(mismatch);
+                }
+            }
             tx.steps_mut().extend(exec_steps);
         }
 
@@ -1363,3 +1579,43 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         Ok(builder)
     }
 }
+
+#[cfg(test)]
+mod circuit_input_builder_tests {
+    use super::*;
+    use crate::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData};
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
+
+    #[test]
+    fn rwc_start() {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! { STOP }),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone())
+            .new_circuit_input_builder()
+            .with_rwc_start(1000);
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let begin_tx = &builder.block.txs()[0].steps()[0];
+        assert_eq!(begin_tx.exec_state, ExecState::BeginTx);
+        assert_eq!(begin_tx.rwc, RWCounter(1000));
+        // the first op of the tx writes the tx id in the call context
+        let first_op = begin_tx.bus_mapping_instance[0];
+        assert_eq!(first_op.target(), operation::Target::CallContext);
+        assert_eq!(
+            builder.block.container.call_context[first_op.as_usize()].rwc(),
+            RWCounter(1000)
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Account and storage accesses of a transaction, as seen by the EIP-2929
+//! access list.
+
+use super::{Block, ExecState};
+use crate::operation::Target;
+use eth_types::{evm_types::OpcodeId, Address, Word};
+use std::collections::{BTreeSet, HashMap};
+
+/// An access to an account recorded in the transaction access list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessEvent {
+    /// Address of the accessed account.
+    pub address: Address,
+    /// Whether the account was already warm, i.e. the access was charged as
+    /// a warm access instead of a cold one.
+    pub is_warm_prev: bool,
+    /// Opcode of the step accessing the account, or `None` when the account
+    /// is warmed up at the beginning of the transaction (sender, callee,
+    /// precompiles and the access list of the transaction).
+    pub opcode: Option<OpcodeId>,
+}
+
+/// Report the account accesses of the transaction at `tx_index`, in execution
+/// order, from its `TxAccessListAccountOp`s.
+///
+/// The writes undoing an access on reversion are not reported.
+pub fn access_list_trace(block: &Block, tx_index: usize) -> Vec<AccessEvent> {
+    block.txs()[tx_index]
+        .steps()
+        .iter()
+        .flat_map(|step| {
+            let opcode = match step.exec_state {
+                ExecState::Op(op) => Some(op),
+                _ => None,
+            };
+            step.bus_mapping_instance
+                .iter()
+                .filter(|op_ref| op_ref.target() == Target::TxAccessListAccount)
+                .map(move |op_ref| (opcode, op_ref))
+        })
+        .filter_map(|(opcode, op_ref)| {
+            let op = block.container.tx_access_list_account[op_ref.as_usize()].op();
+            op.is_warm.then_some(AccessEvent {
+                address: op.address,
+                is_warm_prev: op.is_warm_prev,
+                opcode,
+            })
+        })
+        .collect()
+}
+
+/// Report the storage slots accessed by the transaction at `tx_index`, by
+/// contract address, from its `StorageOp`s (reads and writes) and its
+/// `TxAccessListAccountStorageOp`s. The storage keys of the access list of the
+/// transaction are thus included, even when they are never read or written.
+pub fn accessed_storage_slots(block: &Block, tx_index: usize) -> HashMap<Address, BTreeSet<Word>> {
+    let mut slots: HashMap<Address, BTreeSet<Word>> = HashMap::new();
+    for op_ref in block.txs()[tx_index]
+        .steps()
+        .iter()
+        .flat_map(|step| step.bus_mapping_instance.iter())
+    {
+        let (address, key) = match op_ref.target() {
+            Target::Storage => {
+                let op = block.container.storage[op_ref.as_usize()].op();
+                (op.address, op.key)
+            }
+            Target::TxAccessListAccountStorage => {
+                let op = block.container.tx_access_list_account_storage[op_ref.as_usize()].op();
+                (op.address, op.key)
+            }
+            _ => continue,
+        };
+        slots.entry(address).or_default().insert(key);
+    }
+    slots
+}
+
+#[cfg(test)]
+mod access_list_tests {
+    use super::*;
+    use crate::mock::BlockData;
+    use eth_types::{address, bytecode, geth_types::GethData, ToWord};
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
+
+    #[test]
+    fn balance_cold_then_warm() {
+        let accessed = address!("0x0000000000000000000000000000000000000123");
+        let code = bytecode! {
+            PUSH20(accessed.to_word())
+            BALANCE
+            POP
+            PUSH20(accessed.to_word())
+            BALANCE
+            POP
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let balance_accesses: Vec<_> = access_list_trace(&builder.block, 0)
+            .into_iter()
+            .filter(|event| event.opcode == Some(OpcodeId::BALANCE))
+            .map(|event| (event.address, event.is_warm_prev))
+            .collect();
+        assert_eq!(balance_accesses, vec![(accessed, false), (accessed, true)]);
+    }
+
+    #[test]
+    fn storage_read_and_write() {
+        let code = bytecode! {
+            PUSH1(0)
+            SLOAD
+            POP
+            PUSH1(0x2a)
+            PUSH1(1)
+            SSTORE
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let contract = block.eth_block.transactions[0].to.unwrap();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let slots = accessed_storage_slots(&builder.block, 0);
+        assert_eq!(slots.len(), 1);
+        assert_eq!(
+            slots[&contract],
+            BTreeSet::from([Word::zero(), Word::one()])
+        );
+    }
+}
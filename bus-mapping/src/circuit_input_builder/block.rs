@@ -10,6 +10,7 @@ use crate::{
     Error,
 };
 use eth_types::{Address, Hash, ToWord, Word};
+use ethers_core::utils::keccak256;
 use std::collections::{BTreeMap, HashMap};
 
 /// Context of a [`Block`] which can mutate in a [`Transaction`].
@@ -217,6 +218,8 @@ pub struct Block {
     /// relax mode indicate builder and circuit would skip
     /// some sanity check, used by testing and debugging
     relax_mode: bool,
+    /// coinbase used instead of the one in the header, keyed by block number
+    coinbase_overrides: BTreeMap<u64, Address>,
 }
 
 impl Block {
@@ -319,6 +322,21 @@ impl Block {
         self.relax_mode
     }
 
+    /// Override the coinbase of block `block_num`, which takes precedence over
+    /// the header value when warming and rewarding the coinbase.
+    pub fn set_coinbase_override(&mut self, block_num: u64, coinbase: Address) {
+        self.coinbase_overrides.insert(block_num, coinbase);
+    }
+
+    /// Return the coinbase of block `block_num`, i.e. the overridden one if
+    /// any, or the one in the header.
+    pub fn coinbase(&self, block_num: u64) -> Address {
+        self.coinbase_overrides
+            .get(&block_num)
+            .copied()
+            .unwrap_or_else(|| self.headers[&block_num].coinbase)
+    }
+
     /// ..
     pub fn end_state_root(&self) -> Word {
         self.headers
@@ -367,4 +385,61 @@ impl Block {
     pub fn add_precompile_event(&mut self, event: PrecompileEvent) {
         self.precompile_events.events.push(event);
     }
+    /// Preimages hashed by the block steps (SHA3, CREATE, BLOCKHASH and
+    /// contract deployment), in the order they were pushed to the Keccak
+    /// table inputs.
+    pub fn keccak_preimages(&self) -> &[Vec<u8>] {
+        &self.sha3_inputs
+    }
+    /// [`Block::keccak_preimages`] along with their keccak hash.
+    pub fn keccak_preimages_with_hashes(&self) -> Vec<(Vec<u8>, Hash)> {
+        self.sha3_inputs
+            .iter()
+            .map(|preimage| (preimage.clone(), Hash::from(keccak256(preimage))))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod block_tests {
+    use crate::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData, Word};
+    use ethers_core::utils::keccak256;
+    use mock::{TestContext, MOCK_ACCOUNTS};
+
+    #[test]
+    fn keccak_preimages_of_deployment() {
+        let init_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            RETURN
+        }
+        .code();
+        let block: GethData = TestContext::<1, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(MOCK_ACCOUNTS[0])
+                    .balance(Word::from(1u64 << 30));
+            },
+            |mut txs, accs| {
+                txs[0].from(accs[0].address).input(init_code.clone().into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        assert!(builder.block.keccak_preimages().contains(&init_code));
+        let init_code_hash = keccak256(&init_code);
+        assert!(builder
+            .block
+            .keccak_preimages_with_hashes()
+            .iter()
+            .any(|(preimage, hash)| *preimage == init_code && hash.0 == init_code_hash));
+    }
 }
@@ -50,6 +50,9 @@ impl TryFrom<OpcodeId> for CallKind {
     }
 }
 
+/// Unique identifier of a [`Call`] within the Block, as in [`Call::call_id`].
+pub type CallId = usize;
+
 /// Circuit Input related to an Ethereum Call
 #[derive(Clone, Debug, Default)]
 pub struct Call {
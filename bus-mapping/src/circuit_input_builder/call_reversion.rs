@@ -0,0 +1,167 @@
+//! Reversion bookkeeping of the calls of a transaction.
+
+use super::{Block, CallId};
+use crate::operation::{OperationRef, Target};
+
+/// Report, for each call of the transaction at `tx_index` in the order of
+/// [`Transaction::calls`](super::Transaction::calls), its id, its
+/// `rw_counter_end_of_reversion` and whether it is persistent.
+///
+/// The reversion counter is only set for calls which are not persistent, and
+/// is zero otherwise.
+pub fn call_reversion_info(block: &Block, tx_index: usize) -> Vec<(CallId, u64, bool)> {
+    block.txs()[tx_index]
+        .calls()
+        .iter()
+        .map(|call| {
+            (
+                call.call_id,
+                call.rw_counter_end_of_reversion as u64,
+                call.is_persistent,
+            )
+        })
+        .collect()
+}
+
+/// Count the operations of the transaction at `tx_index` which are reversible,
+/// i.e. pushed with `push_op_reversible` and undone if their call reverts,
+/// and the ones which are not, returned as `(reversible, irreversible)`.
+pub fn reversible_op_count(block: &Block, tx_index: usize) -> (usize, usize) {
+    let op_refs: Vec<_> = block.txs()[tx_index]
+        .steps()
+        .iter()
+        .flat_map(|step| step.bus_mapping_instance.iter())
+        .collect();
+    let reversible = op_refs
+        .iter()
+        .filter(|op_ref| is_reversible(block, op_ref))
+        .count();
+    (reversible, op_refs.len() - reversible)
+}
+
+fn is_reversible(block: &Block, op_ref: &OperationRef) -> bool {
+    let container = &block.container;
+    let idx = op_ref.as_usize();
+    match op_ref.target() {
+        Target::Storage => container.storage[idx].reversible(),
+        Target::TxAccessListAccount => container.tx_access_list_account[idx].reversible(),
+        Target::TxAccessListAccountStorage => {
+            container.tx_access_list_account_storage[idx].reversible()
+        }
+        Target::TxRefund => container.tx_refund[idx].reversible(),
+        Target::Account => container.account[idx].reversible(),
+        // only the above targets are inserted as reversible
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod call_reversion_tests {
+    use super::*;
+    use crate::{
+        mock::BlockData,
+        operation::{AccountField, RW},
+    };
+    use eth_types::{bytecode, geth_types::GethData, ToWord, Word};
+    use mock::{
+        test_ctx::helpers::{caller_and_callee, tx_from_2_to_0},
+        TestContext, MOCK_ACCOUNTS, MOCK_CALLEE,
+    };
+
+    #[test]
+    fn reverted_inner_call() {
+        let callee_code = bytecode! {
+            PUSH1(1)
+            PUSH1(0)
+            SSTORE
+            PUSH1(0)
+            PUSH1(0)
+            REVERT
+        };
+        let caller_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH2(30000)
+            CALL
+            POP
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, callee_code),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let calls = call_reversion_info(&builder.block, 0);
+        assert_eq!(calls.len(), 2);
+        let (outer, inner) = (calls[0], calls[1]);
+        assert_ne!(outer.0, inner.0);
+        assert_eq!((outer.1, outer.2), (0, true));
+        assert_ne!(inner.1, 0);
+        assert!(!inner.2);
+    }
+
+    #[test]
+    fn nonce_writes_of_creation_tx() {
+        let init_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            RETURN
+        };
+        let block: GethData = TestContext::<1, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(MOCK_ACCOUNTS[0])
+                    .balance(Word::from(1u64 << 30));
+            },
+            |mut txs, accs| {
+                txs[0].from(accs[0].address).input(init_code.code().into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        // the nonce increment of the sender is irreversible, while the nonce of
+        // the created contract is only set if the creation succeeds
+        let nonce_writes: Vec<_> = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .flat_map(|step| step.bus_mapping_instance.iter())
+            .filter(|op_ref| op_ref.target() == Target::Account)
+            .map(|op_ref| &builder.block.container.account[op_ref.as_usize()])
+            .filter(|op| op.rw() == RW::WRITE && op.op().field == AccountField::Nonce)
+            .map(|op| (op.op().address, op.reversible()))
+            .collect();
+        let contract = builder.block.txs()[0].calls()[0].address;
+        assert_eq!(
+            nonce_writes,
+            vec![(MOCK_ACCOUNTS[0], false), (contract, true)]
+        );
+
+        let (reversible, irreversible) = reversible_op_count(&builder.block, 0);
+        let num_ops: usize = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .map(|step| step.bus_mapping_instance.len())
+            .sum();
+        assert!(reversible > 0);
+        assert_eq!(reversible + irreversible, num_ops);
+    }
+}
@@ -0,0 +1,117 @@
+//! Consistency checks of the copy events of a block.
+
+use super::{Block, CopyDataType, CopyEvent};
+use crate::Error;
+
+/// Check that the byte lengths of each copy event of the block are consistent
+/// with the ranges it declares, returning the index of the first invalid event
+/// in [`Error::InvalidCopyEvent`].
+///
+/// The checks are:
+/// - the source range is well formed, i.e. `src_addr <= src_addr_end`.
+/// - the read bytes (`aux_bytes`) and the previous bytes of the destination (`bytes_write_prev`)
+///   have as many entries as `copy_bytes.bytes`.
+/// - an access list event copies exactly its source range and access list.
+/// - a memory destination is written by whole words, the copied bytes fitting in the words starting
+///   at the slot of `dst_addr`.
+pub fn validate_copy_events(block: &Block) -> Result<(), Error> {
+    block
+        .copy_events
+        .iter()
+        .enumerate()
+        .try_for_each(|(idx, event)| {
+            validate_copy_event(event).map_err(|reason| Error::InvalidCopyEvent(idx, reason))
+        })
+}
+
+fn validate_copy_event(event: &CopyEvent) -> Result<(), &'static str> {
+    if event.src_addr > event.src_addr_end {
+        return Err("source range ends before its start");
+    }
+
+    let full_length = event.full_length();
+    let bytes = &event.copy_bytes;
+    if bytes
+        .aux_bytes
+        .as_ref()
+        .is_some_and(|aux_bytes| aux_bytes.len() as u64 != full_length)
+    {
+        return Err("length of the read bytes differs from the copy bytes");
+    }
+    if bytes
+        .bytes_write_prev
+        .as_ref()
+        .is_some_and(|prev_bytes| prev_bytes.len() as u64 != full_length)
+    {
+        return Err("length of the previous bytes differs from the copy bytes");
+    }
+
+    if matches!(
+        event.src_type,
+        CopyDataType::AccessListAddresses | CopyDataType::AccessListStorageKeys
+    ) && (event.src_addr_end - event.src_addr != full_length
+        || event.access_list.len() as u64 != full_length)
+    {
+        return Err("length of the access list differs from the copy bytes");
+    }
+
+    if event.dst_type == CopyDataType::Memory && full_length > 0 {
+        if full_length % 32 != 0 {
+            return Err("memory destination is not written by whole words");
+        }
+        if event.dst_addr.checked_add(full_length).is_none() {
+            return Err("destination range overflows");
+        }
+        if event.dst_addr % 32 + event.copy_length() > full_length {
+            return Err("copied bytes exceed the destination words");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod copy_validation_tests {
+    use super::*;
+    use crate::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData};
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
+
+    #[test]
+    fn length_mismatch_is_reported() {
+        let code = bytecode! {
+            PUSH1(0x28) // length
+            PUSH1(0x02) // code offset
+            PUSH1(0x05) // memory offset
+            CODECOPY
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        assert_eq!(builder.block.copy_events.len(), 1);
+        validate_copy_events(&builder.block).unwrap();
+
+        // an extra byte without its previous memory content
+        builder.block.copy_events[0]
+            .copy_bytes
+            .bytes
+            .push((0, false, true));
+        assert!(matches!(
+            validate_copy_events(&builder.block),
+            Err(Error::InvalidCopyEvent(0, _))
+        ));
+    }
+}
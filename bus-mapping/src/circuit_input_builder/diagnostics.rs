@@ -0,0 +1,172 @@
+//! Structured record of the warnings and errors logged while the
+//! [`CircuitInputBuilder`](super::CircuitInputBuilder) handles blocks, from its
+//! own checks to the ones of the opcode handlers and of the precompiles.
+//!
+//! Messages logged outside of a builder are not recorded, e.g. by the
+//! [`BuilderClient`](super::BuilderClient) before a builder exists, by the
+//! keccak input helpers run on a built block, or right before a panic.
+
+/// Severity of a [`Diagnostic`], i.e. the level it is logged at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Logged with `log::warn!`.
+    Warning,
+    /// Logged with `log::error!`.
+    Error,
+}
+
+/// A warning or an error raised while building the circuit input, which
+/// didn't abort the building.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Severity of the diagnostic.
+    pub severity: Severity,
+    /// Id of the transaction being handled, i.e. its index in the block plus
+    /// one.
+    pub tx_id: usize,
+    /// Logged message.
+    pub message: String,
+}
+
+/// Collector of the [`Diagnostic`]s of a builder. The diagnostics are always
+/// logged, and only recorded when the collector is enabled.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    enabled: bool,
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// Create a collector, recording the diagnostics if `enabled`.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Return whether the diagnostics are recorded.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Return the recorded diagnostics, in the order they were raised.
+    pub fn entries(&self) -> &[Diagnostic] {
+        &self.entries
+    }
+
+    /// Return whether no diagnostic was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Log `message` as a warning raised while handling the transaction
+    /// `tx_id`, and record it if enabled.
+    pub fn warn(&mut self, tx_id: usize, message: String) {
+        self.record(Severity::Warning, tx_id, message);
+    }
+
+    /// Log `message` as an error raised while handling the transaction
+    /// `tx_id`, and record it if enabled.
+    pub fn error(&mut self, tx_id: usize, message: String) {
+        self.record(Severity::Error, tx_id, message);
+    }
+
+    fn record(&mut self, severity: Severity, tx_id: usize, message: String) {
+        match severity {
+            Severity::Warning => log::warn!("{message}"),
+            Severity::Error => log::error!("{message}"),
+        }
+        if self.enabled {
+            self.entries.push(Diagnostic {
+                severity,
+                tx_id,
+                message,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use super::*;
+    use crate::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData, Word};
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
+
+    // Handle a tx calling the RIPEMD-160 precompile, which the circuits don't
+    // support, and return the recorded diagnostics.
+    fn diagnostics_of_ripemd160_call(enabled: bool) -> Diagnostics {
+        let code = bytecode! {
+            PUSH1(0) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(0) // argsLength
+            PUSH1(0) // argsOffset
+            PUSH1(3) // address
+            PUSH2(10000) // gas
+            STATICCALL
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone())
+            .new_circuit_input_builder()
+            .with_diagnostics(enabled);
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        builder.diagnostics
+    }
+
+    #[test]
+    fn unsupported_precompile_is_recorded() {
+        let diagnostics = diagnostics_of_ripemd160_call(true);
+        assert!(diagnostics.entries().contains(&Diagnostic {
+            severity: Severity::Warning,
+            tx_id: 1,
+            message: "precompile Ripemd160 unsupported in circuits".to_string(),
+        }));
+
+        // only logged when disabled
+        assert!(diagnostics_of_ripemd160_call(false).is_empty());
+    }
+
+    #[test]
+    fn tx_num_overflow_is_recorded() {
+        let block: GethData = TestContext::<2, 2>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! { STOP }),
+            |mut txs, accs| {
+                txs[0].from(accs[1].address).to(accs[0].address);
+                txs[1]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .nonce(Word::one());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        // the default circuit params only allow one tx
+        let mut builder = BlockData::new_from_geth_data(block.clone())
+            .new_circuit_input_builder()
+            .with_diagnostics(true);
+        assert!(builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .is_err());
+
+        assert!(builder.diagnostics.entries().iter().any(|diagnostic| {
+            (diagnostic.severity, diagnostic.tx_id) == (Severity::Error, 2)
+                && diagnostic.message.starts_with("tx num overflow")
+        }));
+    }
+}
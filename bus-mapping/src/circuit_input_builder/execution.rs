@@ -1,6 +1,7 @@
 //! Execution step related module.
 
 use std::{
+    fmt,
     marker::PhantomData,
     ops::{Add, Mul, Neg},
 };
@@ -67,6 +68,9 @@ pub struct ExecStep {
     pub error: Option<ExecError>,
     /// Optional auxiliary data that is attached to precompile call internal states.
     pub aux_data: Option<PrecompileAuxData>,
+    /// The `GethExecStep` this step is generated from, if any.
+    #[cfg(feature = "debug-trace")]
+    pub geth_step: Option<GethExecStep>,
 }
 
 impl ExecStep {
@@ -95,6 +99,8 @@ impl ExecStep {
             copy_rw_counter_delta: 0,
             error: None,
             aux_data: None,
+            #[cfg(feature = "debug-trace")]
+            geth_step: Some(step.clone()),
         }
     }
 
@@ -117,6 +123,33 @@ impl ExecStep {
     }
 }
 
+/// Compact rendering of the step for debugging, e.g.
+/// `SSTORE pc: 5 gas_left: 78 gas_cost: 22100 rws: [Stack#4, Storage#0]`.
+impl fmt::Display for ExecStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.exec_state {
+            ExecState::Op(op) => write!(f, "{op:?}")?,
+            exec_state => write!(f, "{exec_state:?}")?,
+        }
+        write!(
+            f,
+            " pc: {} gas_left: {} gas_cost: {}",
+            self.pc.0, self.gas_left.0, self.gas_cost.0
+        )?;
+        if let Some(error) = &self.error {
+            write!(f, " error: {error:?}")?;
+        }
+        write!(f, " rws: [")?;
+        for (idx, op_ref) in self.bus_mapping_instance.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?}#{}", op_ref.target(), op_ref.as_usize())?;
+        }
+        write!(f, "]")
+    }
+}
+
 impl Default for ExecStep {
     fn default() -> Self {
         Self {
@@ -136,6 +169,8 @@ impl Default for ExecStep {
             copy_rw_counter_delta: 0,
             error: None,
             aux_data: None,
+            #[cfg(feature = "debug-trace")]
+            geth_step: None,
         }
     }
 }
@@ -1458,3 +1493,91 @@ pub struct SHA256 {
     /// digest
     pub digest: [u8; 32],
 }
+
+#[cfg(test)]
+mod execution_tests {
+    use super::*;
+    use crate::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData};
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
+
+    #[test]
+    fn display_sstore_step() {
+        let code = bytecode! {
+            PUSH1(1)
+            PUSH1(0)
+            SSTORE
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::SSTORE))
+            .unwrap();
+        let pretty = step.to_string();
+        assert!(pretty.starts_with("SSTORE pc: 4 "), "{pretty}");
+        assert!(
+            pretty.contains(&format!(
+                "gas_left: {} gas_cost: {}",
+                step.gas_left.0, step.gas_cost.0
+            )),
+            "{pretty}"
+        );
+        assert!(pretty.contains("Storage#"), "{pretty}");
+    }
+
+    #[cfg(feature = "debug-trace")]
+    #[test]
+    fn geth_step_of_add() {
+        let code = bytecode! {
+            PUSH1(1)
+            PUSH1(2)
+            ADD
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let geth_add = block.geth_traces[0]
+            .struct_logs
+            .iter()
+            .find(|step| step.op == OpcodeId::ADD)
+            .unwrap();
+        let steps = builder.block.txs()[0].steps();
+        let add = steps
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::ADD))
+            .unwrap();
+        assert_eq!(add.geth_step.as_ref(), Some(geth_add));
+        assert_eq!(add.pc, geth_add.pc);
+        // virtual steps are not generated from a geth step
+        assert_eq!(steps[0].exec_state, ExecState::BeginTx);
+        assert!(steps[0].geth_step.is_none());
+    }
+}
@@ -0,0 +1,164 @@
+//! Per call frame gas accounting of a transaction.
+
+use super::{Block, ExecState, ExecStep};
+use crate::operation::{CallContextField, Target};
+
+/// Gas accounting of a single call frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameGas {
+    /// Index of the call in [`Transaction::calls`](super::Transaction::calls).
+    pub call_index: usize,
+    /// Depth of the call.
+    pub depth: usize,
+    /// Gas available when the frame starts executing.
+    pub gas_at_entry: u64,
+    /// Gas passed to the callees by the call and create steps of the frame,
+    /// i.e. without the cost of these steps themselves.
+    pub gas_forwarded: u64,
+    /// Gas given back to the frame by its callees on return.
+    pub gas_returned: u64,
+    /// Gas consumed by the frame, i.e. `gas_at_entry` minus the gas left when
+    /// the frame ends.
+    pub gas_consumed: u64,
+}
+
+/// Report the gas usage of each call frame of the transaction at `tx_index`,
+/// in the order of [`Transaction::calls`](super::Transaction::calls).
+///
+/// The gas forwarded by a call or create step is the gas the callee starts
+/// with, or the gas given back right away when the callee runs no step. The
+/// gas returned is derived from the caller's `GasLeft` written in the call
+/// context. Frames without any step (e.g. a call to an account without code)
+/// report zero gas.
+pub fn frame_gas_trace(block: &Block, tx_index: usize) -> Vec<FrameGas> {
+    let tx = &block.txs()[tx_index];
+
+    tx.calls()
+        .iter()
+        .enumerate()
+        .map(|(call_index, call)| {
+            // the steps of the frame with their index in the tx
+            let steps: Vec<(usize, &ExecStep)> = tx
+                .steps()
+                .iter()
+                .enumerate()
+                .filter(|(_, step)| {
+                    step.call_index == call_index
+                        && !matches!(step.exec_state, ExecState::BeginTx | ExecState::EndTx)
+                })
+                .collect();
+
+            let mut frame = FrameGas {
+                call_index,
+                depth: call.depth,
+                gas_at_entry: 0,
+                gas_forwarded: 0,
+                gas_returned: 0,
+                gas_consumed: 0,
+            };
+            let (Some((_, first)), Some((_, last))) = (steps.first(), steps.last()) else {
+                return frame;
+            };
+            frame.gas_at_entry = first.gas_left.0;
+            frame.gas_consumed = first
+                .gas_left
+                .0
+                .saturating_sub(last.gas_left.0.saturating_sub(last.gas_cost.0));
+
+            for (idx, &(tx_step_idx, step)) in steps.iter().enumerate() {
+                if !matches!(step.exec_state, ExecState::Op(op) if op.is_call_or_create()) {
+                    continue;
+                }
+                let caller_gas_left = step.bus_mapping_instance.iter().find_map(|op_ref| {
+                    if op_ref.target() != Target::CallContext {
+                        return None;
+                    }
+                    let op = block.container.call_context[op_ref.as_usize()].op();
+                    (op.call_id == call.call_id && op.field == CallContextField::GasLeft)
+                        .then(|| op.value.as_u64())
+                });
+                let Some(caller_gas_left) = caller_gas_left else {
+                    continue;
+                };
+                let returned = steps.get(idx + 1).map_or(0, |(_, next_step)| {
+                    next_step.gas_left.0.saturating_sub(caller_gas_left)
+                });
+                frame.gas_returned += returned;
+                frame.gas_forwarded += match tx.steps().get(tx_step_idx + 1) {
+                    Some(callee_step) if callee_step.call_index != call_index => {
+                        callee_step.gas_left.0
+                    }
+                    _ => returned,
+                };
+            }
+
+            frame
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod frame_gas_tests {
+    use super::*;
+    use crate::mock::{step_of, BlockData};
+    use eth_types::{bytecode, evm_types::OpcodeId, geth_types::GethData, ToWord};
+    use mock::{
+        test_ctx::helpers::{caller_and_callee, tx_from_2_to_0},
+        TestContext, MOCK_CALLEE,
+    };
+
+    #[test]
+    fn frame_gas_of_nested_call() {
+        // 3 + 3 + 3 + 2 + 0 gas
+        let callee_code = bytecode! {
+            PUSH1(1)
+            PUSH1(2)
+            ADD
+            POP
+            STOP
+        };
+        let caller_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH2(10000)
+            CALL
+            POP
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, callee_code),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let frames = frame_gas_trace(&builder.block, 0);
+        assert_eq!(frames.len(), 2);
+        let (caller, callee) = (frames[0], frames[1]);
+
+        assert_eq!((caller.depth, callee.depth), (1, 2));
+        assert_eq!(callee.gas_at_entry, 10000);
+        assert_eq!(callee.gas_consumed, 11);
+        assert_eq!((callee.gas_forwarded, callee.gas_returned), (0, 0));
+
+        assert_eq!(caller.gas_forwarded, callee.gas_at_entry);
+        assert_eq!(caller.gas_returned, 10000 - 11);
+
+        // the cost of the CALL itself is the cold account access
+        let call_step = step_of(&builder, OpcodeId::CALL);
+        assert_eq!(call_step.gas_cost.0 - caller.gas_forwarded, 2600);
+        let end_tx = builder.block.txs()[0].steps().last().unwrap();
+        assert_eq!(end_tx.exec_state, ExecState::EndTx);
+        assert_eq!(caller.gas_at_entry - caller.gas_consumed, end_tx.gas_left.0);
+    }
+}
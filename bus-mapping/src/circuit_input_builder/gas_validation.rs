@@ -0,0 +1,115 @@
+//! Per step comparison of the gas cost computed by the opcode handlers with the
+//! gas consumed in the geth trace.
+
+use super::ExecStep;
+use eth_types::{
+    evm_types::{OpcodeId, ProgramCounter},
+    GethExecStep,
+};
+
+/// A step whose gas cost differs from the gas consumed by geth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasMismatch {
+    /// Index of the transaction in the block.
+    pub tx_index: usize,
+    /// Index of the step in the `struct_logs` of the transaction.
+    pub step_index: usize,
+    /// Opcode of the step.
+    pub opcode: OpcodeId,
+    /// Program counter of the step.
+    pub pc: ProgramCounter,
+    /// Gas cost of the step generated by the handler.
+    pub gas_cost: u64,
+    /// Gas consumed by the step in the trace, i.e. the gas left of the step
+    /// minus the gas left of the next one.
+    pub geth_gas_cost: u64,
+}
+
+/// Compare the gas cost of the first step in `exec_steps`, generated from
+/// `geth_steps[0]`, with the gas consumed in the trace.
+///
+/// Like [`diff_step_against_geth`](crate::evm::diff_step_against_geth), the
+/// comparison is skipped when the next step is in another call frame, and for
+/// call and create whose gas cost includes the gas passed to the callee.
+pub(crate) fn check_step_gas(
+    tx_index: usize,
+    step_index: usize,
+    geth_steps: &[GethExecStep],
+    exec_steps: &[ExecStep],
+) -> Option<GasMismatch> {
+    let geth_step = &geth_steps[0];
+    let next_step = geth_steps.get(1)?;
+    if next_step.depth != geth_step.depth || geth_step.op.is_call_or_create() {
+        return None;
+    }
+    let gas_cost = exec_steps.first()?.gas_cost.0;
+    let geth_gas_cost = geth_step.gas.0.saturating_sub(next_step.gas.0);
+    (gas_cost != geth_gas_cost).then_some(GasMismatch {
+        tx_index,
+        step_index,
+        opcode: geth_step.op,
+        pc: geth_step.pc,
+        gas_cost,
+        geth_gas_cost,
+    })
+}
+
+#[cfg(test)]
+mod gas_validation_tests {
+    use super::*;
+    use crate::mock::BlockData;
+    use eth_types::{bytecode, evm_types::GasCost, geth_types::GethData};
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
+
+    #[test]
+    fn wrong_gas_cost_is_reported() {
+        let code = bytecode! {
+            PUSH1(1)
+            PUSH1(2)
+            ADD
+            POP
+            STOP
+        };
+        let mut block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let run = |block: &GethData| {
+            let mut builder = BlockData::new_from_geth_data(block.clone())
+                .new_circuit_input_builder()
+                .with_validate_gas(true);
+            builder
+                .handle_block(&block.eth_block, &block.geth_traces)
+                .unwrap();
+            builder.gas_mismatches
+        };
+        assert_eq!(run(&block), vec![]);
+
+        // the ADD step takes its gas cost from a doctored trace
+        let struct_logs = &mut block.geth_traces[0].struct_logs;
+        let add_idx = struct_logs
+            .iter()
+            .position(|step| step.op == OpcodeId::ADD)
+            .unwrap();
+        struct_logs[add_idx].gas_cost = GasCost(GasCost::FASTEST.0 + 1);
+        assert_eq!(
+            run(&block),
+            vec![GasMismatch {
+                tx_index: 0,
+                step_index: add_idx,
+                opcode: OpcodeId::ADD,
+                pc: ProgramCounter(4),
+                gas_cost: GasCost::FASTEST.0 + 1,
+                geth_gas_cost: GasCost::FASTEST.0,
+            }]
+        );
+    }
+}
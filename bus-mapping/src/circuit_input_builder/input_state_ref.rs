@@ -2,8 +2,8 @@
 
 use super::{
     get_call_memory_offset_length, get_create_init_code, Block, BlockContext, Call, CallContext,
-    CallKind, CodeSource, CopyEvent, ExecState, ExecStep, ExpEvent, PrecompileEvent, Transaction,
-    TransactionContext,
+    CallKind, CodeSource, CopyEvent, Diagnostics, ExecState, ExecStep, ExpEvent, PrecompileEvent,
+    Transaction, TransactionContext,
 };
 #[cfg(feature = "scroll")]
 use crate::util::KECCAK_CODE_HASH_EMPTY;
@@ -20,7 +20,7 @@ use crate::{
         TxReceiptField, TxReceiptOp, RW,
     },
     precompile::{is_precompiled, PrecompileCalls},
-    state_db::{CodeDB, StateDB},
+    state_db::{CodeDB, StateDB, StateProvider},
     Error,
 };
 use eth_types::{
@@ -47,6 +47,16 @@ pub struct CircuitInputStateRef<'a> {
     pub block: &'a mut Block,
     /// Block Context
     pub block_ctx: &'a mut BlockContext,
+    /// Source of the state missing from `sdb`
+    pub state_provider: Option<&'a dyn StateProvider>,
+    /// Reward the coinbase at the end of each tx
+    pub enable_coinbase_reward: bool,
+    /// Warm the access list at the beginning of each tx
+    pub berlin: bool,
+    /// Maximum depth of nested calls
+    pub max_call_depth: usize,
+    /// Warnings and errors raised while handling the block
+    pub diagnostics: &'a mut Diagnostics,
     /// Transaction
     pub tx: &'a mut Transaction,
     /// Transaction Context
@@ -54,6 +64,69 @@ pub struct CircuitInputStateRef<'a> {
 }
 
 impl<'a> CircuitInputStateRef<'a> {
+    /// Make sure the [`Account`](crate::state_db::Account) at `addr` is in the
+    /// [`StateDB`], fetching it from the state provider if it's missing.
+    pub fn load_account(&mut self, addr: &Address) {
+        if let Some(provider) = self.state_provider {
+            self.sdb.load_account(provider, addr);
+        }
+    }
+
+    /// Make sure the storage value at `addr` and `key` is in the [`StateDB`],
+    /// fetching it from the state provider if it's missing.
+    pub fn load_storage(&mut self, addr: &Address, key: &Word) {
+        if let Some(provider) = self.state_provider {
+            self.sdb.load_storage(provider, addr, key);
+        }
+    }
+
+    /// Load the accounts and storage slots read by the step of `geth_step`
+    /// from the state provider, before any of its operations is generated:
+    /// the current account, the storage slot of SLOAD and SSTORE, the account
+    /// of the address operand of BALANCE, EXTCODE*, SELFDESTRUCT and the calls,
+    /// and the account created by a CREATE or CREATE2 that doesn't fail in
+    /// geth.
+    pub(crate) fn load_step_state(&mut self, geth_step: &GethExecStep) -> Result<(), Error> {
+        if self.state_provider.is_none() {
+            return Ok(());
+        }
+        let address = self.call()?.address;
+        self.load_account(&address);
+
+        let stack = &self.call_ctx()?.stack;
+        match geth_step.op {
+            OpcodeId::SLOAD | OpcodeId::SSTORE => {
+                if let Ok(key) = stack.last() {
+                    self.load_storage(&address, &key);
+                }
+            }
+            OpcodeId::BALANCE
+            | OpcodeId::EXTCODESIZE
+            | OpcodeId::EXTCODECOPY
+            | OpcodeId::EXTCODEHASH
+            | OpcodeId::SELFDESTRUCT => {
+                if let Ok(operand) = stack.last() {
+                    self.load_account(&operand.to_address());
+                }
+            }
+            OpcodeId::CALL | OpcodeId::CALLCODE | OpcodeId::DELEGATECALL | OpcodeId::STATICCALL => {
+                if let Ok(operand) = stack.nth_last(1) {
+                    self.load_account(&operand.to_address());
+                }
+            }
+            OpcodeId::CREATE if geth_step.error.is_none() => {
+                let created = self.create_address()?;
+                self.load_account(&created);
+            }
+            OpcodeId::CREATE2 if geth_step.error.is_none() => {
+                let created = self.create2_address(geth_step)?;
+                self.load_account(&created);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
     /// Create a new step from a `GethExecStep`
     pub fn new_step(&self, geth_step: &GethExecStep) -> Result<ExecStep, Error> {
         let call_ctx = self.tx_ctx.call_ctx()?;
@@ -166,7 +239,7 @@ impl<'a> CircuitInputStateRef<'a> {
     }
 
     /// Check whether rws will overflow circuit limit.
-    pub fn check_rw_num_limit(&self) -> Result<(), Error> {
+    pub fn check_rw_num_limit(&mut self) -> Result<(), Error> {
         let max_rws = self.block.circuits_params.max_rws;
         let effective_limit = if max_rws == 0 {
             // even for dynamic case, we don't want to handle > 1M rows.
@@ -176,7 +249,10 @@ impl<'a> CircuitInputStateRef<'a> {
         };
         let rwc = self.block_ctx.rwc.0;
         if rwc > effective_limit {
-            log::error!("rwc > max_rws, rwc={}, max_rws={}", rwc, max_rws);
+            self.diagnostics.error(
+                self.tx_ctx.id(),
+                format!("rwc > max_rws, rwc={}, max_rws={}", rwc, max_rws),
+            );
             return Err(Error::InternalError("rws not enough"));
         };
         Ok(())
@@ -708,6 +784,8 @@ impl<'a> CircuitInputStateRef<'a> {
     /// Push 1 reversible [`AccountOp`] to update `sender`'s balance by
     /// `value`. If `fee` is existing (not None), also need to push 1
     /// non-reversible [`AccountOp`] to update `sender` balance by `fee`.
+    /// The `sender` is kept in the state even if its balance drops to zero,
+    /// since accounts are only removed by `SELFDESTRUCT`.
     pub fn transfer_from_with_fee(
         &mut self,
         step: &mut ExecStep,
@@ -1661,6 +1739,15 @@ impl<'a> CircuitInputStateRef<'a> {
         let call_ctx = self.call_ctx()?;
         #[cfg(feature = "enable-stack")]
         assert_eq!(call_ctx.stack, step.stack);
+        // DUPn reads the n-th item of the stack and SWAPn the (n+1)-th one
+        let min_stack_len = match step.op {
+            op if op.is_dup() => op.postfix(),
+            op if op.is_swap() => op.postfix().map(|n| n + 1),
+            _ => None,
+        };
+        if min_stack_len.is_some_and(|len| call_ctx.stack.len() < len as usize) {
+            return Ok(Some(ExecError::StackUnderflow));
+        }
         // get value first if call/create
         let value = match step.op {
             OpcodeId::CALL | OpcodeId::CALLCODE => call_ctx.stack.nth_last(2)?,
@@ -1748,10 +1835,7 @@ impl<'a> CircuitInputStateRef<'a> {
             ));
         }
 
-        // The *CALL*/CREATE* code was not executed
-
-        let next_pc = next_step.map(|s| s.pc.0).unwrap_or(1);
-        if matches!(
+        let is_call_or_create = matches!(
             step.op,
             OpcodeId::CALL
                 | OpcodeId::CALLCODE
@@ -1759,21 +1843,25 @@ impl<'a> CircuitInputStateRef<'a> {
                 | OpcodeId::STATICCALL
                 | OpcodeId::CREATE
                 | OpcodeId::CREATE2
-        ) && !next_success
-            && next_pc != 0
-        {
-            if step.depth == 1025 {
-                return Ok(Some(ExecError::Depth(match step.op {
-                    OpcodeId::CALL
-                    | OpcodeId::CALLCODE
-                    | OpcodeId::DELEGATECALL
-                    | OpcodeId::STATICCALL => DepthError::Call,
-                    OpcodeId::CREATE => DepthError::Create,
-                    OpcodeId::CREATE2 => DepthError::Create2,
-                    op => unreachable!("ErrDepth cannot occur in {op}"),
-                })));
-            }
+        );
+        // Checked regardless of the trace, which follows the protocol limit
+        // instead of `max_call_depth`.
+        if is_call_or_create && usize::from(step.depth) > self.max_call_depth {
+            return Ok(Some(ExecError::Depth(match step.op {
+                OpcodeId::CALL
+                | OpcodeId::CALLCODE
+                | OpcodeId::DELEGATECALL
+                | OpcodeId::STATICCALL => DepthError::Call,
+                OpcodeId::CREATE => DepthError::Create,
+                OpcodeId::CREATE2 => DepthError::Create2,
+                op => unreachable!("ErrDepth cannot occur in {op}"),
+            })));
+        }
+
+        // The *CALL*/CREATE* code was not executed
 
+        let next_pc = next_step.map(|s| s.pc.0).unwrap_or(1);
+        if is_call_or_create && !next_success && next_pc != 0 {
             let sender = self.call()?.address;
             let (found, account) = self.sdb.get_account(&sender);
             if !found {
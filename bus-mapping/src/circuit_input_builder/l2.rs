@@ -1,13 +1,13 @@
 pub use super::block::{Block, BlockContext};
 use crate::{
-    circuit_input_builder::{self, BlockHead, CircuitInputBuilder, CircuitsParams},
+    circuit_input_builder::{self, BlockHead, CircuitInputBuilder, CircuitsParams, Diagnostics},
     error::Error,
     precompile::is_precompiled,
     state_db::{self, CodeDB, StateDB},
 };
 use eth_types::{
     self,
-    evm_types::OpcodeId,
+    evm_types::{OpcodeId, MAX_CALL_DEPTH},
     l2_types::{BlockTrace, EthBlock, ExecStep, StorageTrace},
     Address, ToWord, Word, H256,
 };
@@ -119,7 +119,12 @@ fn trace_code(
     });
 }
 
-fn update_codedb(cdb: &mut CodeDB, sdb: &StateDB, block: &BlockTrace) -> Result<(), Error> {
+fn update_codedb(
+    cdb: &mut CodeDB,
+    sdb: &StateDB,
+    block: &BlockTrace,
+    diagnostics: &mut Diagnostics,
+) -> Result<(), Error> {
     log::debug!("build_codedb for block {:?}", block.header.number);
     for (er_idx, execution_result) in block.execution_results.iter().enumerate() {
         if let Some(bytecode) = &execution_result.byte_code {
@@ -228,7 +233,10 @@ fn update_codedb(cdb: &mut CodeDB, sdb: &StateDB, block: &BlockTrace) -> Result<
                     OpcodeId::EXTCODESIZE | OpcodeId::EXTCODECOPY => {
                         let code = data.get_code_at(0);
                         if code.is_none() {
-                            log::warn!("unable to fetch code from step. {step:?}");
+                            diagnostics.warn(
+                                er_idx + 1,
+                                format!("unable to fetch code from step. {step:?}"),
+                            );
                             continue;
                         }
                         trace_code(cdb, None, code.unwrap(), step, None, sdb);
@@ -337,6 +345,14 @@ impl CircuitInputBuilder {
             code_db,
             block: block.clone(),
             block_ctx: BlockContext::new(),
+            state_provider: None,
+            validate_gas: false,
+            gas_mismatches: Vec::new(),
+            enable_coinbase_reward: true,
+            berlin: true,
+            max_call_depth: MAX_CALL_DEPTH,
+            fill_omitted_memory: false,
+            diagnostics: Diagnostics::default(),
             mpt_init_state: Some(mpt_init_state),
         }
     }
@@ -404,7 +420,8 @@ impl CircuitInputBuilder {
 
         let mut code_db = CodeDB::new();
         code_db.insert(Vec::new());
-        update_codedb(&mut code_db, &sdb, &l2_trace)?;
+        let mut diagnostics = Diagnostics::default();
+        update_codedb(&mut code_db, &sdb, &l2_trace, &mut diagnostics)?;
 
         let mut builder_block = circuit_input_builder::Block::from_headers(&[], circuits_params);
         builder_block.chain_id = chain_id;
@@ -415,6 +432,14 @@ impl CircuitInputBuilder {
             code_db,
             block: builder_block,
             block_ctx: BlockContext::new(),
+            state_provider: None,
+            validate_gas: false,
+            gas_mismatches: Vec::new(),
+            enable_coinbase_reward: true,
+            berlin: true,
+            max_call_depth: MAX_CALL_DEPTH,
+            fill_omitted_memory: false,
+            diagnostics,
             mpt_init_state,
         };
 
@@ -475,7 +500,12 @@ impl CircuitInputBuilder {
             *self.sdb.get_storage_mut(&addr, &key).1 = val;
         }
 
-        update_codedb(&mut self.code_db, &self.sdb, &l2_trace)?;
+        update_codedb(
+            &mut self.code_db,
+            &self.sdb,
+            &l2_trace,
+            &mut self.diagnostics,
+        )?;
 
         self.apply_l2_trace(l2_trace, !more)?;
         Ok(())
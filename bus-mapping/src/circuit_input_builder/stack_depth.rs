@@ -0,0 +1,55 @@
+//! Stack usage of a transaction.
+
+use super::Block;
+
+/// Report the maximum stack size reached by the call frames of the
+/// transaction at `tx_index`, from the stack size of its steps.
+///
+/// The stack size of a step is the one before its execution, so the maximum
+/// is reached by a step following the pushes, e.g. the final `STOP`.
+pub fn max_stack_depth(block: &Block, tx_index: usize) -> usize {
+    block.txs()[tx_index]
+        .steps()
+        .iter()
+        .map(|step| step.stack_size)
+        .max()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod stack_depth_tests {
+    use super::*;
+    use crate::mock::BlockData;
+    use eth_types::{evm_types::OpcodeId, geth_types::GethData, Bytecode};
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
+
+    #[test]
+    fn max_depth_of_pushes() {
+        let mut code = Bytecode::default();
+        for i in 0..300 {
+            code.push(1, i % 0x100);
+        }
+        // pop a few items before stopping
+        for _ in 0..10 {
+            code.write_op(OpcodeId::POP);
+        }
+        code.write_op(OpcodeId::STOP);
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        assert_eq!(max_stack_depth(&builder.block, 0), 300);
+    }
+}
@@ -0,0 +1,123 @@
+//! Reconstruction of the memory omitted from the steps of a geth trace.
+
+use eth_types::{evm_types::Memory, GethExecStep};
+
+/// Fill the memory of the steps whose memory was omitted by the tracer
+/// because it didn't change, i.e. the steps with an empty memory, with the
+/// memory of the previous step of the same call frame.
+///
+/// A call frame starts with an empty memory, and its memory is restored when
+/// its callee returns. A trace recorded without memory is left untouched.
+pub fn fill_omitted_memory(struct_logs: &mut [GethExecStep]) {
+    // Last memory seen in each frame of the call stack, by depth.
+    let mut frames: Vec<Memory> = Vec::new();
+    for step in struct_logs.iter_mut() {
+        let depth = (step.depth as usize).max(1);
+        // Drop the frames which returned, and start the new ones empty.
+        frames.truncate(depth);
+        frames.resize_with(depth, Memory::default);
+
+        let memory = &mut frames[depth - 1];
+        if step.memory.is_empty() {
+            step.memory = memory.clone();
+        } else {
+            *memory = step.memory.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod trace_memory_tests {
+    use super::*;
+    use crate::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData, ToWord};
+    use mock::{
+        test_ctx::{
+            helpers::{caller_and_callee, tx_from_2_to_0},
+            LoggerConfig,
+        },
+        TestContext, MOCK_CALLEE,
+    };
+
+    // Omit the memory of the steps whose memory is the one of the previous
+    // step in the same frame.
+    fn omit_unchanged_memory(struct_logs: &mut [GethExecStep]) {
+        let mut frames: Vec<Memory> = Vec::new();
+        for step in struct_logs.iter_mut() {
+            let depth = step.depth as usize;
+            frames.truncate(depth);
+            frames.resize_with(depth, Memory::default);
+            if step.memory == frames[depth - 1] {
+                step.memory = Memory::default();
+            } else {
+                frames[depth - 1] = step.memory.clone();
+            }
+        }
+    }
+
+    #[test]
+    fn fill_memory_of_nested_call() {
+        let callee_code = bytecode! {
+            PUSH1(0xbb)
+            PUSH1(0x20)
+            MSTORE
+            PUSH1(0)
+            MLOAD
+            POP
+            STOP
+        };
+        let caller_code = bytecode! {
+            PUSH1(0xaa)
+            PUSH1(0)
+            MSTORE
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH2(10000)
+            CALL
+            POP
+            PUSH1(0)
+            MLOAD
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new_with_logger_config(
+            None,
+            caller_and_callee(caller_code, callee_code),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+            LoggerConfig {
+                enable_memory: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .into();
+
+        let full = block.geth_traces[0].struct_logs.clone();
+        let mut delta_block = block.clone();
+        let struct_logs = &mut delta_block.geth_traces[0].struct_logs;
+        omit_unchanged_memory(struct_logs);
+        assert!(struct_logs
+            .iter()
+            .zip(full.iter())
+            .any(|(delta, full)| delta.memory != full.memory));
+
+        fill_omitted_memory(struct_logs);
+        assert!(struct_logs
+            .iter()
+            .zip(full.iter())
+            .all(|(filled, full)| filled.memory == full.memory));
+
+        // the builder fills the memory of the delta encoded trace itself
+        omit_unchanged_memory(&mut delta_block.geth_traces[0].struct_logs);
+        let mut builder = BlockData::new_from_geth_data(delta_block.clone())
+            .new_circuit_input_builder()
+            .with_fill_omitted_memory(true);
+        builder
+            .handle_block(&delta_block.eth_block, &delta_block.geth_traces)
+            .unwrap();
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
 };
 use eth_types::{
     address, bytecode,
-    evm_types::{stack::Stack, Gas, Memory, OpcodeId},
+    evm_types::{stack::Stack, Gas, Memory, OpcodeId, MAX_CALL_DEPTH},
     geth_types::GethData,
     word, Bytecode, GethExecError, GethExecStep, Hash, ToAddress, ToWord, Word,
 };
@@ -148,12 +148,11 @@ fn check_err_depth(step: &GethExecStep, next_step: Option<&GethExecStep>) -> boo
             | OpcodeId::CREATE2
     ) && step.error.is_none()
         && result(next_step).is_zero()
-        && step.depth == 1025
+        && usize::from(step.depth) > MAX_CALL_DEPTH
 }
 
-#[test]
-fn tracer_err_depth() {
-    // Recursive CALL will exaust the call depth
+// A recursive CALL which exhausts the call depth.
+fn recursive_call_block() -> GethData {
     let code = bytecode! {
              PUSH1(0x0) // retLength
              PUSH1(0x0) // retOffset
@@ -168,7 +167,7 @@ fn tracer_err_depth() {
     };
 
     // Create a custom tx setting Gas to
-    let block: GethData = TestContext::<2, 1>::new_with_logger_config(
+    TestContext::<2, 1>::new_with_logger_config(
         None,
         |accs| {
             accs[0]
@@ -189,8 +188,12 @@ fn tracer_err_depth() {
         LoggerConfig::enable_memory(),
     )
     .unwrap()
-    .into();
+    .into()
+}
 
+#[test]
+fn tracer_err_depth() {
+    let block = recursive_call_block();
     let struct_logs = &block.geth_traces[0].struct_logs;
 
     // get last CALL
@@ -221,6 +224,77 @@ fn tracer_err_depth() {
     );
 }
 
+#[test]
+fn tracer_no_err_depth_at_max_call_depth() {
+    let block = recursive_call_block();
+
+    // the CALL from the deepest allowed call succeeds
+    let (index, step) = block.geth_traces[0]
+        .struct_logs
+        .iter()
+        .enumerate()
+        .find(|(_, s)| s.op == OpcodeId::CALL && usize::from(s.depth) == MAX_CALL_DEPTH)
+        .unwrap();
+    let next_step = block.geth_traces[0].struct_logs.get(index + 1);
+    assert_eq!(next_step.unwrap().depth, step.depth + 1);
+    assert!(!check_err_depth(step, next_step));
+
+    let mut builder = CircuitInputBuilderTx::new(&block, step);
+    assert_eq!(
+        builder.state_ref().get_step_err(step, next_step).unwrap(),
+        None
+    );
+}
+
+// The error of the CALL at `depth` of the recursive call block, when the
+// builder limits the depth to `max_call_depth`.
+fn call_err_at_depth(
+    block: &GethData,
+    depth: u16,
+    max_call_depth: usize,
+) -> Result<Option<ExecError>, Error> {
+    let struct_logs = &block.geth_traces[0].struct_logs;
+    let index = struct_logs
+        .iter()
+        .position(|s| s.op == OpcodeId::CALL && s.depth == depth)
+        .unwrap();
+    let (step, next_step) = (&struct_logs[index], struct_logs.get(index + 1));
+
+    let mut builder = CircuitInputBuilderTx::new(block, step);
+    builder.builder.max_call_depth = max_call_depth;
+    builder.state_ref().get_step_err(step, next_step)
+}
+
+#[test]
+fn tracer_err_depth_with_max_call_depth_of_2() {
+    let block = recursive_call_block();
+
+    // the first two nested calls are entered, the third one is too deep
+    assert_eq!(call_err_at_depth(&block, 1, 2).unwrap(), None);
+    assert_eq!(call_err_at_depth(&block, 2, 2).unwrap(), None);
+    assert_eq!(
+        call_err_at_depth(&block, 3, 2).unwrap(),
+        Some(ExecError::Depth(DepthError::Call))
+    );
+}
+
+#[test]
+fn tracer_no_err_depth_with_raised_max_call_depth() {
+    let block = recursive_call_block();
+    let depth = MAX_CALL_DEPTH as u16 + 1;
+
+    assert_eq!(
+        call_err_at_depth(&block, depth, MAX_CALL_DEPTH).unwrap(),
+        Some(ExecError::Depth(DepthError::Call))
+    );
+    // Not too deep anymore, though geth, which follows the protocol limit,
+    // didn't enter the call.
+    assert!(!matches!(
+        call_err_at_depth(&block, depth, 2 * MAX_CALL_DEPTH),
+        Ok(Some(ExecError::Depth(_)))
+    ));
+}
+
 #[test]
 fn tracer_err_insufficient_balance() {
     let code_a = bytecode! {
@@ -1944,3 +2018,163 @@ fn create_address() {
 
     assert_eq!(addr.to_word(), addr_expect);
 }
+
+// A state provider serving a single account, and counting the account fetches.
+#[derive(Debug)]
+struct SingleAccountProvider {
+    address: Address,
+    account: Account,
+    fetches: std::sync::atomic::AtomicUsize,
+}
+
+impl StateProvider for SingleAccountProvider {
+    fn get_account(&self, addr: &Address) -> Option<Account> {
+        if *addr != self.address {
+            return None;
+        }
+        self.fetches
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Some(self.account.clone())
+    }
+
+    fn get_storage(&self, addr: &Address, key: &Word) -> Option<Word> {
+        if *addr != self.address {
+            return None;
+        }
+        self.account.storage.get(key).copied()
+    }
+}
+
+#[test]
+fn state_provider_serves_missing_account() {
+    let remote = address!("0x000000000000000000000000000000000000dead");
+    let unknown = address!("0x000000000000000000000000000000000000beef");
+    let provider = Arc::new(SingleAccountProvider {
+        address: remote,
+        account: Account {
+            balance: Word::from(0x1234),
+            storage: HashMap::from([(Word::one(), Word::from(0x5678))]),
+            ..Account::zero()
+        },
+        fetches: Default::default(),
+    });
+
+    let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode! { STOP })
+        .unwrap()
+        .into();
+    let mut builder = CircuitInputBuilderTx::new(&block, &block.geth_traces[0].struct_logs[0]);
+    builder.builder.state_provider = Some(provider.clone());
+    let mut state = builder.state_ref();
+    assert!(!state.sdb.get_account(&remote).0);
+
+    // fetched on first access, then served from the StateDB
+    state.load_account(&remote);
+    state.load_account(&remote);
+    let (found, account) = state.sdb.get_account(&remote);
+    assert!(found);
+    assert_eq!(account.balance, Word::from(0x1234));
+    assert_eq!(
+        provider.fetches.load(std::sync::atomic::Ordering::SeqCst),
+        1
+    );
+
+    state.load_storage(&remote, &Word::one());
+    assert_eq!(
+        state.sdb.get_storage(&remote, &Word::one()),
+        (true, &Word::from(0x5678))
+    );
+
+    // accounts unknown to the provider stay missing
+    state.load_account(&unknown);
+    assert!(!state.sdb.get_account(&unknown).0);
+}
+
+#[test]
+fn block_from_lazily_loaded_state() {
+    let contract = address!("0x000000000000000000000000000000000000c0de");
+    let sender = address!("0x000000000000000000000000000000000000cafe");
+    let receiver = address!("0x000000000000000000000000000000000000beef");
+    let code = bytecode! {
+        SELFBALANCE
+        POP
+        PUSH1(0x01)
+        SLOAD
+        POP
+        PUSH1(0x02)
+        PUSH1(0x01)
+        SSTORE
+        PUSH20(receiver.to_word())
+        BALANCE
+        POP
+        PUSH1(0x00) // retLength
+        PUSH1(0x00) // retOffset
+        PUSH1(0x00) // argsLength
+        PUSH1(0x00) // argsOffset
+        PUSH1(0x01) // value
+        PUSH20(receiver.to_word()) // addr
+        PUSH2(0xffff) // gas
+        CALL
+        POP
+        PUSH1(0x00) // length
+        PUSH1(0x00) // offset
+        PUSH1(0x00) // value
+        CREATE
+        POP
+        PUSH1(0x00) // salt
+        PUSH1(0x00) // length
+        PUSH1(0x00) // offset
+        PUSH1(0x00) // value
+        CREATE2
+        POP
+        STOP
+    };
+    let block: GethData = TestContext::<3, 2>::new(
+        None,
+        |accs| {
+            accs[0]
+                .address(contract)
+                .code(code)
+                .balance(Word::from(1u64 << 20))
+                .storage(vec![(Word::one(), Word::from(0x1234))].into_iter());
+            accs[1].address(sender).balance(Word::from(1u64 << 60));
+            accs[2].address(receiver).balance(Word::from(1000));
+        },
+        |mut txs, accs| {
+            txs[0]
+                .from(accs[1].address)
+                .to(accs[0].address)
+                .value(Word::from(0x100));
+            txs[1]
+                .from(accs[1].address)
+                .to(accs[2].address)
+                .value(Word::from(0x200))
+                .nonce(Word::one());
+        },
+        |block, _tx| block.number(0xcafeu64),
+    )
+    .unwrap()
+    .into();
+    let block_data = crate::mock::BlockData::new_from_geth_data(block.clone());
+
+    let mut builder = block_data.new_circuit_input_builder();
+    builder
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap();
+
+    // all the state is fetched on demand from the provider
+    let mut lazy_builder = block_data
+        .new_circuit_input_builder()
+        .with_state_provider(Arc::new(block_data.sdb.clone()));
+    lazy_builder.sdb = StateDB::new();
+    lazy_builder
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap();
+
+    assert_eq!(lazy_builder.block.container, builder.block.container);
+    for addr in [contract, sender, receiver] {
+        assert_eq!(
+            lazy_builder.sdb.get_account(&addr),
+            builder.sdb.get_account(&addr)
+        );
+    }
+}
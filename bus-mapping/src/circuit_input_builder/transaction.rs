@@ -12,7 +12,7 @@ use eth_types::{
     geth_types::{get_rlp_unsigned, TxType},
     AccessList, Address, GethExecTrace, Signature, Word, H256,
 };
-use ethers_core::utils::get_contract_address;
+use ethers_core::utils::{get_contract_address, keccak256};
 
 /// Precision of transaction L1 fee
 pub const TX_L1_FEE_PRECISION: u64 = 1_000_000_000;
@@ -24,6 +24,10 @@ pub const TX_L1_COMMIT_EXTRA_COST: u64 = 64;
 pub struct TransactionContext {
     /// L1 fee
     pub l1_fee: u64,
+    /// Gas used by the transaction, as reported by the trace
+    pub(crate) trace_gas_used: u64,
+    /// Fee debited from the caller at the beginning of the transaction
+    pub(crate) fee_debited: Word,
     /// Unique identifier of transaction of the block. The value is `index + 1`.
     id: usize,
     /// The index of logs made in the transaction.
@@ -43,6 +47,9 @@ pub struct TransactionContext {
     /// in the inner most revert (which we track with the last element in
     /// the reversion groups stack), and skip it in the outer revert.
     pub(crate) reversion_groups: Vec<ReversionGroup>,
+    /// Whether the memory was traced, i.e. some step of the trace has memory.
+    /// Always false without `enable-memory`, since the steps don't keep it.
+    pub(crate) memory_traced: bool,
 }
 
 impl TransactionContext {
@@ -67,6 +74,15 @@ impl TransactionContext {
             calls: Vec::new(),
             reversion_groups: Vec::new(),
             l1_fee: geth_trace.l1_fee,
+            trace_gas_used: geth_trace.gas.0,
+            fee_debited: Word::zero(),
+            #[cfg(feature = "enable-memory")]
+            memory_traced: geth_trace
+                .struct_logs
+                .iter()
+                .any(|step| !step.memory.is_empty()),
+            #[cfg(not(feature = "enable-memory"))]
+            memory_traced: false,
         };
         tx_ctx.push_call_ctx(
             0,
@@ -439,6 +455,25 @@ impl Transaction {
 
         self.l1_fee.tx_l1_fee(tx_data_gas_cost).0
     }
+
+    /// Effective gas price of an EIP-1559 transaction in a block with
+    /// `base_fee`, i.e. `base_fee + min(gas_tip_cap, gas_fee_cap - base_fee)`.
+    /// A fee cap below the base fee, which can't be included in the block, is
+    /// an error.
+    pub fn effective_gas_price(&self, base_fee: Word) -> Result<Word, Error> {
+        let max_tip = self
+            .gas_fee_cap
+            .checked_sub(base_fee)
+            .ok_or(Error::EffectiveTipUnderflow)?;
+        Ok(base_fee + self.gas_tip_cap.min(max_tip))
+    }
+}
+
+/// Hash of the transaction, i.e. the keccak hash of its signed encoding, which
+/// is the RLP of its fields prefixed by its type for typed transactions
+/// (EIP-2718).
+pub fn tx_hash(tx: &Transaction) -> H256 {
+    H256(keccak256(&tx.rlp_bytes))
 }
 
 #[cfg(feature = "test")]
@@ -531,3 +566,92 @@ impl TxL1Fee {
         }
     }
 }
+
+#[cfg(test)]
+mod transaction_tests {
+    use super::*;
+    use eth_types::{address, word, U64};
+    use std::str::FromStr;
+
+    // Signed transaction of the EIP-155 example, with the given type.
+    fn example_tx(transaction_type: Option<u64>) -> eth_types::Transaction {
+        eth_types::Transaction {
+            nonce: Word::from(9),
+            gas: Word::from(21000),
+            to: Some(address!("0x3535353535353535353535353535353535353535")),
+            value: Word::from(10).pow(Word::from(18)),
+            r: word!("0x28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276"),
+            s: word!("0x67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"),
+            transaction_type: transaction_type.map(U64::from),
+            ..Default::default()
+        }
+    }
+
+    fn hash_of(eth_tx: &eth_types::Transaction) -> H256 {
+        tx_hash(&Transaction {
+            rlp_bytes: eth_tx.rlp().to_vec(),
+            ..Transaction::dummy()
+        })
+    }
+
+    #[test]
+    fn legacy_tx_hash() {
+        let eth_tx = eth_types::Transaction {
+            gas_price: Some(Word::from(20_000_000_000u64)),
+            v: U64::from(37),
+            ..example_tx(None)
+        };
+        assert_eq!(
+            hash_of(&eth_tx),
+            H256::from_str("0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn eip1559_tx_hash() {
+        let eth_tx = eth_types::Transaction {
+            nonce: Word::zero(),
+            chain_id: Some(Word::one()),
+            max_priority_fee_per_gas: Some(Word::from(1_000_000_000u64)),
+            max_fee_per_gas: Some(Word::from(100_000_000_000u64)),
+            access_list: Some(AccessList::default()),
+            v: U64::zero(),
+            ..example_tx(Some(2))
+        };
+        assert_eq!(
+            hash_of(&eth_tx),
+            H256::from_str("0xd5cc66b4a63699f9fe83558cc8cf398dc46fbc4bccd95905db473ec925a1a4ec")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn effective_gas_price_of_eip1559_tx() {
+        let gwei = |n: u64| Word::from(n * 1_000_000_000);
+        let effective_gas_price = |fee_cap, tip_cap, base_fee| {
+            Transaction {
+                gas_fee_cap: fee_cap,
+                gas_tip_cap: tip_cap,
+                ..Transaction::dummy()
+            }
+            .effective_gas_price(base_fee)
+        };
+
+        // base_fee + tip_cap
+        assert_eq!(
+            effective_gas_price(gwei(100), gwei(2), gwei(10)).unwrap(),
+            gwei(12)
+        );
+        // base_fee + (fee_cap - base_fee)
+        assert_eq!(
+            effective_gas_price(gwei(11), gwei(2), gwei(10)).unwrap(),
+            gwei(11)
+        );
+        // The fee cap below the base fee can't pay for it.
+        assert!(matches!(
+            effective_gas_price(gwei(9), gwei(2), gwei(10)),
+            Err(Error::EffectiveTipUnderflow)
+        ));
+    }
+}
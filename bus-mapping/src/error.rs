@@ -39,6 +39,21 @@ pub enum Error {
     ExecutionError(ExecError),
     /// Internal Code error
     InternalError(&'static str),
+    /// The gas accounting of a tx doesn't add up: the quantity, its expected
+    /// value, and its actual value.
+    GasAccountingMismatch(&'static str, Word, Word),
+    /// The trace has no memory, although the opcode depends on the memory
+    /// content.
+    MemoryTracingDisabled(OpcodeId),
+    /// The copy event at the given index of the block has inconsistent
+    /// lengths or ranges.
+    InvalidCopyEvent(usize, &'static str),
+    /// The sender of the tx has code, which is forbidden by EIP-3607.
+    SenderHasCode(Address),
+    /// The cumulative gas used by the txs overflows a `u64`.
+    CumulativeGasOverflow,
+    /// The gas price of the tx is below the base fee of its block.
+    EffectiveTipUnderflow,
 }
 
 impl From<eth_types::Error> for Error {
@@ -3,10 +3,13 @@
 pub(crate) mod opcodes;
 
 pub use eth_types::evm_types::opcode_ids::OpcodeId;
-pub use opcodes::Opcode;
+pub use opcodes::{stack_arity, Opcode};
 
 #[cfg(any(feature = "test", test))]
 pub use opcodes::{gen_sha3_code, MemoryKind};
 
 #[cfg(any(feature = "test", test))]
 pub use opcodes::PrecompileCallArgs;
+
+#[cfg(feature = "test")]
+pub use opcodes::{diff_step_against_geth, StepDiff};
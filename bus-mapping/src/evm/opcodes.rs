@@ -40,6 +40,7 @@ mod extcodecopy;
 mod extcodehash;
 mod extcodesize;
 mod gasprice;
+mod jumpi;
 mod logs;
 mod mload;
 mod mstore;
@@ -55,6 +56,7 @@ mod selfbalance;
 mod sha3;
 mod sload;
 mod sstore;
+mod stack_arity;
 mod stackonlyop;
 mod stop;
 mod swap;
@@ -73,12 +75,19 @@ mod error_precompile_failed;
 mod error_return_data_outofbound;
 mod error_write_protection;
 
+#[cfg(test)]
+mod error_test;
 #[cfg(all(feature = "enable-memory", test))]
 mod memory_expansion_test;
 #[cfg(feature = "test")]
+mod step_diff;
+#[cfg(feature = "test")]
 pub use callop::tests::PrecompileCallArgs;
+#[cfg(feature = "test")]
+pub use step_diff::{diff_step_against_geth, StepDiff};
 
 use self::{pushn::PushN, sha3::Sha3};
+pub use stack_arity::stack_arity;
 
 use address::Address;
 use arithmetic::ArithmeticOpcode;
@@ -95,7 +104,7 @@ use codecopy::Codecopy;
 use codesize::Codesize;
 use create::Create;
 use dup::Dup;
-use environment::{Gas, GetBlockHeaderField, Msize, Pc};
+use environment::{Coinbase, Gas, GetBlockHeaderField, Msize, Pc};
 use error_codestore::ErrorCodeStore;
 use error_invalid_creation_code::ErrorCreationCode;
 use error_invalid_jump::InvalidJump;
@@ -112,6 +121,7 @@ use extcodecopy::Extcodecopy;
 use extcodehash::Extcodehash;
 use extcodesize::Extcodesize;
 use gasprice::GasPrice;
+use jumpi::Jumpi;
 use logs::Log;
 use mload::Mload;
 use mstore::Mstore;
@@ -219,7 +229,7 @@ fn fn_gen_associated_ops(opcode_id: &OpcodeId) -> FnGenAssociatedOps {
         OpcodeId::RETURNDATACOPY => Returndatacopy::gen_associated_ops,
         OpcodeId::EXTCODEHASH => Extcodehash::gen_associated_ops,
         OpcodeId::BLOCKHASH => Blockhash::gen_associated_ops,
-        OpcodeId::COINBASE => GetBlockHeaderField::<{ OpcodeId::COINBASE }>::gen_associated_ops,
+        OpcodeId::COINBASE => Coinbase::gen_associated_ops,
         OpcodeId::TIMESTAMP => GetBlockHeaderField::<{ OpcodeId::TIMESTAMP }>::gen_associated_ops,
         OpcodeId::NUMBER => GetBlockHeaderField::<{ OpcodeId::NUMBER }>::gen_associated_ops,
         OpcodeId::DIFFICULTY => GetBlockHeaderField::<{ OpcodeId::DIFFICULTY }>::gen_associated_ops,
@@ -234,7 +244,7 @@ fn fn_gen_associated_ops(opcode_id: &OpcodeId) -> FnGenAssociatedOps {
         OpcodeId::SLOAD => Sload::gen_associated_ops,
         OpcodeId::SSTORE => Sstore::gen_associated_ops,
         OpcodeId::JUMP => StackPopOnlyOpcode::<1>::gen_associated_ops,
-        OpcodeId::JUMPI => StackPopOnlyOpcode::<2>::gen_associated_ops,
+        OpcodeId::JUMPI => Jumpi::gen_associated_ops,
         OpcodeId::PC => Pc::gen_associated_ops,
         OpcodeId::MSIZE => Msize::gen_associated_ops,
         OpcodeId::GAS => Gas::gen_associated_ops,
@@ -380,6 +390,19 @@ fn fn_gen_error_state_associated_ops(
     }
 }
 
+// Opcodes whose result depends on the content of the memory.
+fn reads_memory(opcode_id: &OpcodeId) -> bool {
+    matches!(
+        opcode_id,
+        OpcodeId::MLOAD
+            | OpcodeId::SHA3
+            | OpcodeId::CALLDATACOPY
+            | OpcodeId::RETURN
+            | OpcodeId::REVERT
+    ) || opcode_id.is_log()
+        || opcode_id.is_call_or_create()
+}
+
 #[allow(clippy::collapsible_else_if)]
 /// Generate the associated operations according to the particular
 /// [`OpcodeId`].
@@ -388,69 +411,58 @@ pub fn gen_associated_ops(
     state: &mut CircuitInputStateRef,
     geth_steps: &[GethExecStep],
 ) -> Result<Vec<ExecStep>, Error> {
+    let memory_enabled = state.tx_ctx.memory_traced;
+    // A trace without memory while the call has some means that memory
+    // tracing was off. Only `enable-memory` takes the memory from the trace,
+    // otherwise the memory is rebuilt from the steps and isn't needed.
+    if cfg!(feature = "enable-memory")
+        && !memory_enabled
+        && reads_memory(opcode_id)
+        && !state.call_ctx()?.memory.is_empty()
+    {
+        return Err(Error::MemoryTracingDisabled(*opcode_id));
+    }
     #[cfg(feature = "enable-memory")]
-    if GETH_TRACE_CHECK_LEVEL.should_check() {
-        let memory_enabled = !geth_steps.iter().all(|s| s.memory.is_empty());
-        assert!(memory_enabled);
-        if memory_enabled {
-            #[allow(clippy::collapsible_else_if)]
-            if state.call_ctx()?.memory != geth_steps[0].memory {
-                log::error!(
-                    "wrong mem before {:?}. len in state {}, len in step {}",
-                    opcode_id,
-                    &state.call_ctx()?.memory.len(),
-                    &geth_steps[0].memory.len(),
-                );
-                log::error!("state mem {:?}", &state.call_ctx()?.memory);
-                log::error!("step  mem {:?}", &geth_steps[0].memory);
-
-                for i in 0..std::cmp::min(
-                    state.call_ctx()?.memory.0.len(),
-                    geth_steps[0].memory.0.len(),
-                ) {
-                    let state_mem = state.call_ctx()?.memory.0[i];
-                    let step_mem = geth_steps[0].memory.0[i];
-                    if state_mem != step_mem {
-                        log::error!(
-                            "diff at {}: state {:?} != step {:?}",
-                            i,
-                            state_mem,
-                            step_mem
-                        );
-                    }
-                }
-                if GETH_TRACE_CHECK_LEVEL.should_panic() {
-                    panic!("mem wrong");
-                }
-                state.call_ctx_mut()?.memory = geth_steps[0].memory.clone();
+    if GETH_TRACE_CHECK_LEVEL.should_check() && memory_enabled {
+        #[allow(clippy::collapsible_else_if)]
+        if state.call_ctx()?.memory != geth_steps[0].memory {
+            let (state_mem, step_mem) = (&state.call_ctx()?.memory, &geth_steps[0].memory);
+            log::debug!("state mem {:?}", state_mem);
+            log::debug!("step  mem {:?}", step_mem);
+            let first_diff = (0..std::cmp::min(state_mem.0.len(), step_mem.0.len()))
+                .find(|&i| state_mem.0[i] != step_mem.0[i]);
+            let message = format!(
+                "wrong mem before {:?}. len in state {}, len in step {}, first diff at {:?}",
+                opcode_id,
+                state_mem.len(),
+                step_mem.len(),
+                first_diff,
+            );
+            let tx_id = state.tx_ctx.id();
+            state.diagnostics.error(tx_id, message);
+            if GETH_TRACE_CHECK_LEVEL.should_panic() {
+                panic!("mem wrong");
             }
+            state.call_ctx_mut()?.memory = geth_steps[0].memory.clone();
         }
     }
     #[cfg(feature = "enable-stack")]
     if GETH_TRACE_CHECK_LEVEL.should_check() {
         if state.call_ctx()?.stack != geth_steps[0].stack {
-            log::error!(
-                "wrong stack before {:?}. len in state {}, len in step {}",
+            let (state_stack, step_stack) = (&state.call_ctx()?.stack, &geth_steps[0].stack);
+            log::debug!("state stack {:?}", state_stack);
+            log::debug!("step  stack {:?}", step_stack);
+            let first_diff = (0..std::cmp::min(state_stack.0.len(), step_stack.0.len()))
+                .find(|&i| state_stack.0[i] != step_stack.0[i]);
+            let message = format!(
+                "wrong stack before {:?}. len in state {}, len in step {}, first diff at {:?}",
                 opcode_id,
-                &state.call_ctx()?.stack.len(),
-                &geth_steps[0].stack.len(),
+                state_stack.len(),
+                step_stack.len(),
+                first_diff,
             );
-            log::error!("state stack {:?}", &state.call_ctx()?.stack);
-            log::error!("step  stack {:?}", &geth_steps[0].stack);
-
-            for i in 0..std::cmp::min(state.call_ctx()?.stack.0.len(), geth_steps[0].stack.0.len())
-            {
-                let state_stack = state.call_ctx()?.stack.0[i];
-                let step_stack = geth_steps[0].stack.0[i];
-                if state_stack != step_stack {
-                    log::error!(
-                        "diff at {}: state {:?} != step {:?}",
-                        i,
-                        state_stack,
-                        step_stack
-                    );
-                }
-            }
+            let tx_id = state.tx_ctx.id();
+            state.diagnostics.error(tx_id, message);
             if GETH_TRACE_CHECK_LEVEL.should_panic() {
                 panic!("stack wrong");
             }
@@ -460,6 +472,10 @@ pub fn gen_associated_ops(
         }
     }
 
+    // the state read by the step, including its error checks, must be loaded
+    // before any of them
+    state.load_step_state(&geth_steps[0])?;
+
     // check if have error
     let geth_step = &geth_steps[0];
     let mut exec_step = state.new_step(geth_step)?;
@@ -19,7 +19,12 @@ impl Opcode for Address {
 
         let address = state.call()?.address.to_word();
         #[cfg(feature = "enable-stack")]
-        assert_eq!(address, geth_steps[1].stack.last()?);
+        if address != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
 
         // Read the callee address in call context.
         state.call_context_read(
@@ -41,7 +46,7 @@ mod address_tests {
     use super::*;
     use crate::{
         circuit_input_builder::ExecState,
-        mock::BlockData,
+        mock::{stack_push_of, BlockData},
         operation::{CallContextOp, StackOp, RW},
     };
     use eth_types::{
@@ -50,7 +55,10 @@ mod address_tests {
         geth_types::GethData,
         ToWord,
     };
-    use mock::test_ctx::{helpers::*, TestContext};
+    use mock::{
+        test_ctx::{helpers::*, TestContext},
+        MOCK_CALLEE, MOCK_CALLER,
+    };
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -110,4 +118,43 @@ mod address_tests {
             )
         );
     }
+
+    #[test]
+    fn address_in_delegatecall() {
+        let caller_code = bytecode! {
+            PUSH1(0) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(0) // argsLength
+            PUSH1(0) // argsOffset
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH2(10000) // gas
+            DELEGATECALL
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(
+                caller_code,
+                bytecode! {
+                    ADDRESS
+                    STOP
+                },
+            ),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafe_u64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        // the library code runs in the context of the caller
+        assert_eq!(
+            stack_push_of(&builder, OpcodeId::ADDRESS),
+            MOCK_CALLER.to_word()
+        );
+    }
 }
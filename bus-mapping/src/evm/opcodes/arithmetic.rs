@@ -349,15 +349,23 @@ where
         state.stack_push(&mut exec_step, output)?;
 
         #[cfg(feature = "enable-stack")]
-        assert_eq!(
-            output,
-            geth_steps[1].stack.nth_last(0)?,
-            "stack mismatch, opcode: {}, inputs: {}, actual: {:x}, expected: {:x}",
-            OP,
-            stack_inputs.iter().map(|w| format!("{w:x}")).join(", "),
-            output,
-            geth_steps[1].stack.nth_last(0)?
-        );
+        if output != geth_steps[1].stack.nth_last(0)? {
+            let tx_id = state.tx_ctx.id();
+            state.diagnostics.error(
+                tx_id,
+                format!(
+                    "stack mismatch, opcode: {}, inputs: {}, actual: {:x}, expected: {:x}",
+                    OP,
+                    stack_inputs.iter().map(|w| format!("{w:x}")).join(", "),
+                    output,
+                    geth_steps[1].stack.nth_last(0)?
+                ),
+            );
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
 
         Ok(vec![exec_step])
     }
@@ -451,6 +459,31 @@ mod tests {
         test_random::<2, { OpcodeId::SAR }>();
     }
 
+    // The result pushed by geth is checked against the one computed from the
+    // inputs when the stack is traced.
+    #[cfg(feature = "enable-stack")]
+    #[test]
+    fn test_doctored_lt_result() {
+        let mut code = Bytecode::default();
+        code.push(1, 0x02).push(1, 0x01);
+        code.write_op(OpcodeId::LT).write_op(OpcodeId::STOP);
+        let mut block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+            .unwrap()
+            .into();
+        let struct_logs = &mut block.geth_traces[0].struct_logs;
+        let lt_idx = struct_logs
+            .iter()
+            .position(|step| step.op == OpcodeId::LT)
+            .unwrap();
+        let stack = &mut struct_logs[lt_idx + 1].stack;
+        assert_eq!(stack.pop().unwrap(), Word::one());
+        stack.push(Word::zero()).unwrap();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        let result = builder.handle_block(&block.eth_block, &block.geth_traces);
+        assert!(matches!(result, Err(Error::InvalidGethExecStep(..))));
+    }
+
     #[test]
     fn test_sdiv() {
         test_both::<2, { OpcodeId::SDIV }>([0x60u64.into(), 0x80u64.into()], 0u64.into());
@@ -537,6 +570,19 @@ mod tests {
         test_both::<2, { OpcodeId::XOR }>([0x00u64.into(), 0x00u64.into()], 0x00u64.into());
     }
 
+    #[test]
+    fn test_byte() {
+        let word = word!("0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+        // the most significant byte
+        test_both::<2, { OpcodeId::BYTE }>([0x00u64.into(), word], 0x00u64.into());
+        test_both::<2, { OpcodeId::BYTE }>([0x01u64.into(), word], 0x01u64.into());
+        // the least significant byte
+        test_both::<2, { OpcodeId::BYTE }>([0x1fu64.into(), word], 0x1fu64.into());
+        // out of range
+        test_both::<2, { OpcodeId::BYTE }>([0x20u64.into(), Word::MAX], 0x00u64.into());
+        test_both::<2, { OpcodeId::BYTE }>([Word::MAX, Word::MAX], 0x00u64.into());
+    }
+
     #[test]
     fn test_not() {
         test_both::<1, { OpcodeId::NOT }>(
@@ -77,7 +77,12 @@ impl Opcode for Balance {
 
         // Write the BALANCE result to stack.
         #[cfg(feature = "enable-stack")]
-        assert_eq!(geth_steps[1].stack.last()?, balance);
+        if balance != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, balance)?;
 
         Ok(vec![exec_step])
@@ -20,7 +20,7 @@ use eth_types::{
         gas_utils::{tx_access_list_gas_cost, tx_data_gas_cost},
         GasCost, MAX_REFUND_QUOTIENT_OF_GAS_USED,
     },
-    Bytecode, ToWord, Word,
+    Address, Bytecode, ToWord, Word,
 };
 use ethers_core::utils::get_contract_address;
 
@@ -46,6 +46,24 @@ pub fn gen_begin_tx_steps(state: &mut CircuitInputStateRef) -> Result<Vec<ExecSt
     let mut exec_step = state.new_begin_tx_step();
     let call = state.call()?.clone();
 
+    // Unlike the caller of an l1 message, which is created if missing, the
+    // caller of a non-l1 tx must exist since its nonce and balance are read
+    // from sdb. EIP-3607: reject a tx sent from an account with code. An
+    // EIP-7702 delegation designator doesn't count as code. Both are checked
+    // before any operation of the tx is generated.
+    if !state.tx.tx_type.is_l1_msg() {
+        let (caller_exists, caller_acc) = state.sdb.get_account(&call.caller_address);
+        if !caller_exists {
+            return Err(Error::AccountNotFound(call.caller_address));
+        }
+        let code_hash = caller_acc.code_hash;
+        if code_hash != CodeDB::empty_code_hash()
+            && !is_delegation_designator(&state.code(code_hash)?)
+        {
+            return Err(Error::SenderHasCode(call.caller_address));
+        }
+    }
+
     // write tx_id
     begin_tx(state, &mut exec_step, &call)?;
 
@@ -146,42 +164,42 @@ pub fn gen_begin_tx_steps(state: &mut CircuitInputStateRef) -> Result<Vec<ExecSt
         nonce_prev.into(),
     )?;
 
-    // Add precompile contract address to access list
-    for address in 1..=9 {
-        let address = eth_types::Address::from_low_u64_be(address);
-        let is_warm_prev = !state.sdb.add_account_to_access_list(address);
-        state.tx_access_list_account_write(
-            &mut exec_step,
-            state.tx_ctx.id(),
-            address,
-            true,
-            is_warm_prev,
-        )?;
+    // Add precompile contract address to access list, only after Berlin.
+    if state.berlin {
+        for address in 1..=9 {
+            let address = eth_types::Address::from_low_u64_be(address);
+            let is_warm_prev = !state.sdb.add_account_to_access_list(address);
+            state.tx_access_list_account_write(
+                &mut exec_step,
+                state.tx_ctx.id(),
+                address,
+                true,
+                is_warm_prev,
+            )?;
+        }
     }
 
-    // Add caller, callee and coinbase (only for Shanghai) to access list.
+    // Add caller, callee and coinbase (only for Shanghai) to access list, only
+    // after Berlin.
     #[cfg(feature = "shanghai")]
     let accessed_addresses = [
         call.caller_address,
         call.address,
-        state
-            .block
-            .headers
-            .get(&state.tx.block_num)
-            .unwrap()
-            .coinbase,
+        state.block.coinbase(state.tx.block_num),
     ];
     #[cfg(not(feature = "shanghai"))]
     let accessed_addresses = [call.caller_address, call.address];
-    for address in accessed_addresses {
-        let is_warm_prev = !state.sdb.add_account_to_access_list(address);
-        state.tx_access_list_account_write(
-            &mut exec_step,
-            state.tx_ctx.id(),
-            address,
-            true,
-            is_warm_prev,
-        )?;
+    if state.berlin {
+        for address in accessed_addresses {
+            let is_warm_prev = !state.sdb.add_account_to_access_list(address);
+            state.tx_access_list_account_write(
+                &mut exec_step,
+                state.tx_ctx.id(),
+                address,
+                true,
+                is_warm_prev,
+            )?;
+        }
     }
 
     // Calculate gas cost of init code only for EIP-3860 of Shanghai.
@@ -210,18 +228,14 @@ pub fn gen_begin_tx_steps(state: &mut CircuitInputStateRef) -> Result<Vec<ExecSt
     // Get code_hash of callee account
     let callee_account = &state.sdb.get_account(&call.address).1.clone();
     let is_precompile = is_precompiled(&call.address);
-    let callee_exists = !callee_account.is_empty();
-    if !callee_exists && call.value.is_zero() {
+    if callee_account.is_empty() && call.value.is_zero() {
         // The account is empty (codehash and nonce be 0) while storage is non empty.
         // It is an impossible case for any real world scenario.
         // The "clear" helps with testool.
         state.sdb.get_account_mut(&call.address).1.storage.clear();
     }
-    let account_code_hash = if callee_exists {
-        callee_account.code_hash.to_word()
-    } else {
-        Word::zero()
-    };
+    let (callee_exists, account_code_hash) =
+        account_existence_read(state, &mut exec_step, call.address)?;
     // call_code is code being executed
     let call_code_hash = call.code_hash.to_word();
     if !state.tx.is_create() && !account_code_hash.is_zero() {
@@ -230,13 +244,6 @@ pub fn gen_begin_tx_steps(state: &mut CircuitInputStateRef) -> Result<Vec<ExecSt
     let account_code_hash_is_empty_or_zero =
         account_code_hash.is_zero() || account_code_hash == CodeDB::empty_code_hash().to_word();
 
-    state.account_read(
-        &mut exec_step,
-        call.address,
-        AccountField::CodeHash,
-        account_code_hash,
-    )?;
-
     if state.tx.is_create()
         && ((!account_code_hash_is_empty_or_zero) || !callee_account.nonce.is_zero())
     {
@@ -245,9 +252,12 @@ pub fn gen_begin_tx_steps(state: &mut CircuitInputStateRef) -> Result<Vec<ExecSt
         // which may also act as the data source for our statedb,
         // we have to relax the constarint a bit and fix it silently
         if account_code_hash_is_empty_or_zero && callee_account.nonce == 1.into() {
-            log::warn!(
-                "fix deployment nonce for {:?} silently for the prestate tracer",
-                call.address,
+            state.diagnostics.warn(
+                state.tx_ctx.id(),
+                format!(
+                    "fix deployment nonce for {:?} silently for the prestate tracer",
+                    call.address
+                ),
             );
             let mut fixed_account = callee_account.clone();
             fixed_account.nonce = Word::zero();
@@ -267,6 +277,7 @@ pub fn gen_begin_tx_steps(state: &mut CircuitInputStateRef) -> Result<Vec<ExecSt
     } else {
         state.tx.gas_price * state.tx.gas + state.tx_ctx.l1_fee
     };
+    state.tx_ctx.fee_debited = fee;
     state.transfer_with_fee(
         &mut exec_step,
         call.caller_address,
@@ -422,10 +433,13 @@ pub fn gen_begin_tx_steps(state: &mut CircuitInputStateRef) -> Result<Vec<ExecSt
             }
 
             let precompile_call: PrecompileCalls = call.address.0[19].into();
+            let tx_id = state.tx_ctx.id();
             let (result, precompile_call_gas_cost, has_oog_err) = execute_precompiled(
                 &precompile_call.into(),
                 &state.tx.input,
                 exec_step.gas_left.0 - exec_step.gas_cost.as_u64(),
+                state.diagnostics,
+                tx_id,
             );
 
             // insert a copy event (input) generate word memory read for input.
@@ -539,11 +553,20 @@ pub fn gen_begin_tx_steps(state: &mut CircuitInputStateRef) -> Result<Vec<ExecSt
             }
         }
     }
-    log::trace!("begin_tx_step: {:?}, {:?}", exec_step, precompile_step);
+    log::trace!("begin_tx_step: {exec_step}");
+    if let Some(precompile_step) = &precompile_step {
+        log::trace!("begin_tx precompile step: {precompile_step}");
+    }
 
     Ok(std::iter::once(exec_step).chain(precompile_step).collect())
 }
 
+// The code `0xef0100 || address` set by EIP-7702 to delegate an EOA to a
+// contract.
+fn is_delegation_designator(code: &[u8]) -> bool {
+    code.len() == 23 && code.starts_with(&[0xef, 0x01, 0x00])
+}
+
 pub fn gen_end_tx_steps(state: &mut CircuitInputStateRef) -> Result<ExecStep, Error> {
     let mut exec_step = state.new_end_tx_step();
     let call = state.tx.calls()[0].clone();
@@ -588,6 +611,7 @@ pub fn gen_end_tx_steps(state: &mut CircuitInputStateRef) -> Result<ExecStep, Er
     let effective_refund_balance = state.tx.gas_price * (exec_step.gas_left.0 + effective_refund);
     let caller_balance = caller_balance_prev + effective_refund_balance;
 
+    let mut refund_credited = Word::zero();
     if !state.tx.tx_type.is_l1_msg() {
         log::trace!(
             "call balance refund {:?}, now {:?}",
@@ -601,6 +625,7 @@ pub fn gen_end_tx_steps(state: &mut CircuitInputStateRef) -> Result<ExecStep, Er
             caller_balance,
             caller_balance_prev,
         )?;
+        refund_credited = caller_balance - caller_balance_prev;
     } else {
         log::trace!("l1 tx, no refund");
     }
@@ -611,7 +636,12 @@ pub fn gen_end_tx_steps(state: &mut CircuitInputStateRef) -> Result<ExecStep, Er
         .get(&state.tx.block_num)
         .unwrap()
         .clone();
-    let effective_tip = state.tx.gas_price - block_info.base_fee;
+    // An underpriced tx is rejected when it's created.
+    let effective_tip = if state.tx.tx_type.is_l1_msg() {
+        Word::zero()
+    } else {
+        state.tx.gas_price - block_info.base_fee
+    };
     let gas_cost = state.tx.gas - exec_step.gas_left.0 - effective_refund;
     let coinbase_reward = if state.tx.tx_type.is_l1_msg() {
         Word::zero()
@@ -628,33 +658,45 @@ pub fn gen_end_tx_steps(state: &mut CircuitInputStateRef) -> Result<ExecStep, Er
         coinbase_reward
     );
 
-    let (found, coinbase_account) = state.sdb.get_account_mut(&block_info.coinbase);
-    if !found {
-        log::error!("coinbase account not found: {}", block_info.coinbase);
-        return Err(Error::AccountNotFound(block_info.coinbase));
+    let mut coinbase_credited = None;
+    if state.enable_coinbase_reward {
+        let coinbase = state.block.coinbase(state.tx.block_num);
+        let (found, _) = state.sdb.get_account(&coinbase);
+        if !found {
+            state.diagnostics.error(
+                state.tx_ctx.id(),
+                format!("coinbase account not found: {coinbase}"),
+            );
+            return Err(Error::AccountNotFound(coinbase));
+        }
+        let (coinbase_exists, _) = account_existence_read(state, &mut exec_step, coinbase)?;
+
+        if !state.tx.tx_type.is_l1_msg() {
+            let coinbase_balance_prev = state.sdb.get_balance(&coinbase);
+            state.transfer_to(
+                &mut exec_step,
+                coinbase,
+                coinbase_exists,
+                false,
+                coinbase_reward,
+                false,
+            )?;
+            coinbase_credited = Some(state.sdb.get_balance(&coinbase) - coinbase_balance_prev);
+        }
     }
-    let coinbase_account = coinbase_account.clone();
-    state.account_read(
-        &mut exec_step,
-        block_info.coinbase,
-        AccountField::CodeHash,
-        if coinbase_account.is_empty() {
-            Word::zero()
-        } else {
-            coinbase_account.code_hash.to_word()
-        },
-    )?;
 
-    if !state.tx.tx_type.is_l1_msg() {
-        state.transfer_to(
-            &mut exec_step,
-            block_info.coinbase,
-            !coinbase_account.is_empty(),
-            false,
-            coinbase_reward,
-            false,
-        )?;
+    GasAccounting {
+        gas: state.tx.gas,
+        gas_left: exec_step.gas_left.0,
+        effective_refund,
+        trace_gas_used: state.tx_ctx.trace_gas_used,
+        base_fee: block_info.base_fee,
+        fee_debited: state.tx_ctx.fee_debited,
+        refund_credited,
+        coinbase_credited,
+        is_l1_msg: state.tx.tx_type.is_l1_msg(),
     }
+    .reconcile()?;
 
     end_tx(state, &mut exec_step, &call)?;
 
@@ -676,6 +718,81 @@ pub(crate) fn begin_tx(
     Ok(())
 }
 
+/// Read the code hash of the account at `address`, which is zero if and only if
+/// the account doesn't exist, so that the read proves either the existence or
+/// the non-existence of the account. Return the existence and the code hash.
+pub(crate) fn account_existence_read(
+    state: &mut CircuitInputStateRef,
+    exec_step: &mut ExecStep,
+    address: Address,
+) -> Result<(bool, Word), Error> {
+    let account = state.sdb.get_account(&address).1;
+    let exists = !account.is_empty();
+    let code_hash = if exists {
+        account.code_hash.to_word()
+    } else {
+        Word::zero()
+    };
+    state.account_read(exec_step, address, AccountField::CodeHash, code_hash)?;
+    Ok((exists, code_hash))
+}
+
+/// Gas quantities of a finished tx, each taken from where it is applied: the
+/// trace, or the balance changes of the caller and the coinbase.
+#[derive(Debug, Clone, Copy)]
+struct GasAccounting {
+    /// Gas limit of the tx
+    gas: u64,
+    /// Gas left at the end of the execution
+    gas_left: u64,
+    /// Gas refunded, capped by EIP-3529
+    effective_refund: u64,
+    /// Gas used by the tx, refund excluded, as reported by the trace
+    trace_gas_used: u64,
+    base_fee: Word,
+    /// Fee debited from the caller at the beginning of the tx
+    fee_debited: Word,
+    /// Balance given back to the caller for the gas left and refunded
+    refund_credited: Word,
+    /// Balance rewarded to the coinbase, if the reward is enabled
+    coinbase_credited: Option<Word>,
+    /// L1 messages pay no fee
+    is_l1_msg: bool,
+}
+
+impl GasAccounting {
+    /// Check that the gas of the tx is either used as reported by the trace,
+    /// refunded or left, and that the fee debited from the caller is given
+    /// back to it, rewarded to the coinbase or burnt.
+    fn reconcile(&self) -> Result<(), Error> {
+        let gas_spent =
+            self.trace_gas_used as u128 + self.effective_refund as u128 + self.gas_left as u128;
+        if gas_spent != self.gas as u128 {
+            return Err(Error::GasAccountingMismatch(
+                "gas",
+                self.gas.into(),
+                Word::from(gas_spent),
+            ));
+        }
+        if self.is_l1_msg {
+            return Ok(());
+        }
+        let Some(coinbase_credited) = self.coinbase_credited else {
+            return Ok(());
+        };
+        let fee_distributed =
+            self.refund_credited + coinbase_credited + self.base_fee * self.trace_gas_used;
+        if self.fee_debited != fee_distributed {
+            return Err(Error::GasAccountingMismatch(
+                "fee",
+                self.fee_debited,
+                fee_distributed,
+            ));
+        }
+        Ok(())
+    }
+}
+
 pub(crate) fn end_tx(
     state: &mut CircuitInputStateRef,
     exec_step: &mut ExecStep,
@@ -718,7 +835,11 @@ fn write_tx_receipt(
         )?;
     }
 
-    state.block_ctx.cumulative_gas_used += state.tx.gas - exec_step.gas_left.0;
+    state.block_ctx.cumulative_gas_used = state
+        .block_ctx
+        .cumulative_gas_used
+        .checked_add(state.tx.gas - exec_step.gas_left.0)
+        .ok_or(Error::CumulativeGasOverflow)?;
     state.tx_receipt_write(
         exec_step,
         state.tx_ctx.id(),
@@ -948,3 +1069,482 @@ fn add_access_list_storage_key_copy_event(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{gen_begin_tx_steps, Error};
+    use crate::{
+        circuit_input_builder::{CircuitInputBuilder, ExecState, TransactionContext},
+        mock::BlockData,
+        operation::{AccountField, Target, RW},
+        state_db::{CodeDB, StateDB},
+    };
+    use eth_types::{
+        address, bytecode, evm_types::OpcodeId, geth_types::GethData, Address, ToWord, Word,
+    };
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext, MOCK_COINBASE,
+    };
+
+    #[test]
+    fn test_begin_tx_missing_caller() {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! { STOP }),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        let eth_tx = &block.eth_block.transactions[0];
+        let geth_trace = &block.geth_traces[0];
+        let mut tx = builder.new_tx(eth_tx, !geth_trace.failed).unwrap();
+        let mut tx_ctx = TransactionContext::new(eth_tx, geth_trace, true).unwrap();
+
+        // drop the caller from the state once the tx is created
+        let callee = eth_tx.to.unwrap();
+        let mut sdb = StateDB::new();
+        sdb.set_account(&callee, builder.sdb.get_account(&callee).1.clone());
+        builder.sdb = sdb;
+
+        let rwc = builder.block_ctx.rwc;
+        let mut state = builder.state_ref(&mut tx, &mut tx_ctx);
+        assert!(matches!(
+            gen_begin_tx_steps(&mut state),
+            Err(Error::AccountNotFound(caller)) if caller == eth_tx.from
+        ));
+        // no operation of the tx is generated
+        assert_eq!(builder.block_ctx.rwc, rwc);
+    }
+
+    #[test]
+    fn test_begin_tx_sender_with_code() {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! { STOP }),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let eth_tx = &block.eth_block.transactions[0];
+        let geth_trace = &block.geth_traces[0];
+
+        // Run the begin tx step after setting `code` as the code of the sender.
+        let begin_tx_with_sender_code = |code: Vec<u8>| {
+            let mut builder =
+                BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+            let mut tx = builder.new_tx(eth_tx, !geth_trace.failed).unwrap();
+            let mut tx_ctx = TransactionContext::new(eth_tx, geth_trace, true).unwrap();
+            let code_hash = builder.code_db.insert(code);
+            builder.sdb.get_account_mut(&eth_tx.from).1.code_hash = code_hash;
+
+            let mut state = builder.state_ref(&mut tx, &mut tx_ctx);
+            gen_begin_tx_steps(&mut state).map(|_| ())
+        };
+
+        assert!(matches!(
+            begin_tx_with_sender_code(bytecode! { STOP }.code()),
+            Err(Error::SenderHasCode(sender)) if sender == eth_tx.from
+        ));
+        // an EIP-7702 delegation is accepted
+        let mut delegation = vec![0xef, 0x01, 0x00];
+        delegation.extend_from_slice(eth_tx.to.unwrap().as_bytes());
+        assert!(begin_tx_with_sender_code(delegation).is_ok());
+    }
+
+    #[test]
+    fn test_cumulative_gas_overflow() {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! { STOP }),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        // as if previous txs had used almost all the gas a u64 can count
+        builder.block_ctx.cumulative_gas_used = u64::MAX - 20_000;
+        assert!(matches!(
+            builder.handle_block(&block.eth_block, &block.geth_traces),
+            Err(Error::CumulativeGasOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_effective_tip_underflow() {
+        let handle_underpriced_block = |legacy: bool| {
+            let block: GethData = TestContext::<2, 1>::new(
+                None,
+                account_0_code_account_1_no_code(bytecode! { STOP }),
+                |mut txs, accs| {
+                    txs[0].from(accs[1].address).to(accs[0].address);
+                    if !legacy {
+                        txs[0]
+                            .max_fee_per_gas(Word::from(1_000_000_000u64))
+                            .max_priority_fee_per_gas(Word::one())
+                            .transaction_type(2);
+                    }
+                },
+                |block, _tx| block.number(0xcafeu64),
+            )
+            .unwrap()
+            .into();
+            let mut builder =
+                BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+            // a base fee above the gas price, or the fee cap, of the tx
+            builder.block.headers.get_mut(&0xcafe).unwrap().base_fee = Word::from(u64::MAX);
+            let result = builder.handle_block(&block.eth_block, &block.geth_traces);
+            // the tx is rejected before any of its operations is generated
+            assert!(builder.block.container.account.is_empty());
+            result
+        };
+
+        assert!(matches!(
+            handle_underpriced_block(true),
+            Err(Error::EffectiveTipUnderflow)
+        ));
+        assert!(matches!(
+            handle_underpriced_block(false),
+            Err(Error::EffectiveTipUnderflow)
+        ));
+    }
+
+    #[test]
+    fn test_coinbase_override() {
+        let coinbase = address!("0x0000000000000000000000000000000000c0ffee");
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            |accs| {
+                let [acc0, acc1, acc2] = accs;
+                account_0_code_account_1_no_code(bytecode! { STOP })([acc0, acc1]);
+                acc2.address(coinbase).balance(Word::one());
+            },
+            |mut txs, accs| {
+                txs[0].from(accs[1].address).to(accs[0].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder.block.set_coinbase_override(0xcafe, coinbase);
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let balance_writes = |address| {
+            builder
+                .block
+                .container
+                .account
+                .iter()
+                .filter(|op| {
+                    op.rw() == RW::WRITE
+                        && op.op().address == address
+                        && op.op().field == AccountField::Balance
+                })
+                .map(|op| (op.op().value_prev, op.op().value))
+                .collect::<Vec<_>>()
+        };
+        // the reward lands on the overridden coinbase, not the header one
+        let rewards = balance_writes(coinbase);
+        assert_eq!(rewards.len(), 1);
+        assert_eq!(rewards[0].0, Word::one());
+        assert!(rewards[0].1 > rewards[0].0);
+        assert!(balance_writes(*MOCK_COINBASE).is_empty());
+    }
+
+    #[test]
+    fn test_disabled_coinbase_reward() {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! { STOP }),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let handle_block = |enable_coinbase_reward| {
+            let mut builder = BlockData::new_from_geth_data(block.clone())
+                .new_circuit_input_builder()
+                .with_enable_coinbase_reward(enable_coinbase_reward);
+            builder
+                .handle_block(&block.eth_block, &block.geth_traces)
+                .unwrap();
+            builder
+        };
+        let coinbase_ops = |builder: &CircuitInputBuilder| {
+            builder
+                .block
+                .container
+                .account
+                .iter()
+                .filter(|op| op.op().address == *MOCK_COINBASE)
+                .count()
+        };
+
+        let rewarded = handle_block(true);
+        let unrewarded = handle_block(false);
+        // the coinbase code hash read and the reward transfer are skipped
+        assert!(coinbase_ops(&rewarded) > 0);
+        assert_eq!(coinbase_ops(&unrewarded), 0);
+        assert_eq!(
+            unrewarded.block_ctx.rwc.0 + coinbase_ops(&rewarded),
+            rewarded.block_ctx.rwc.0
+        );
+        assert_eq!(
+            unrewarded.sdb.get_balance(&MOCK_COINBASE),
+            BlockData::new_from_geth_data(block.clone())
+                .new_circuit_input_builder()
+                .sdb
+                .get_balance(&MOCK_COINBASE)
+        );
+    }
+
+    #[test]
+    fn test_pre_berlin_precompile_is_cold() {
+        let precompile = Address::from_low_u64_be(4);
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! {
+                PUSH20(precompile.to_word())
+                BALANCE
+                STOP
+            }),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let handle_block = |berlin| {
+            let mut builder = BlockData::new_from_geth_data(block.clone())
+                .new_circuit_input_builder()
+                .with_berlin(berlin);
+            builder
+                .handle_block(&block.eth_block, &block.geth_traces)
+                .unwrap();
+            builder
+        };
+        let precompile_accesses = |builder: &CircuitInputBuilder| {
+            builder
+                .block
+                .container
+                .tx_access_list_account
+                .iter()
+                .filter(|op| op.op().address == precompile)
+                .map(|op| op.op().is_warm_prev)
+                .collect::<Vec<_>>()
+        };
+
+        // warmed by the begin tx, then read as warm by BALANCE
+        assert_eq!(precompile_accesses(&handle_block(true)), [false, true]);
+        // no access list op in the begin tx, the first access is cold
+        let pre_berlin = handle_block(false);
+        let begin_tx = &pre_berlin.block.txs()[0].steps()[0];
+        assert_eq!(begin_tx.exec_state, ExecState::BeginTx);
+        assert!(begin_tx
+            .bus_mapping_instance
+            .iter()
+            .all(|op| op.target() != Target::TxAccessListAccount));
+        assert_eq!(precompile_accesses(&pre_berlin), [false]);
+    }
+
+    #[test]
+    fn test_callee_non_existence_read() {
+        let callee = address!("0x00000000000000000000000000000000000000ee");
+        let block: GethData = TestContext::<1, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(address!("0x0000000000000000000000000000000000cafe01"))
+                    .balance(Word::from(1u64 << 20));
+            },
+            |mut txs, accs| {
+                txs[0].from(accs[0].address).to(callee);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let code_hash_reads = |exec_state: ExecState| {
+            let step = builder.block.txs()[0]
+                .steps()
+                .iter()
+                .find(|step| step.exec_state == exec_state)
+                .unwrap();
+            step.bus_mapping_instance
+                .iter()
+                .filter(|op_ref| op_ref.target() == Target::Account)
+                .map(|op_ref| &builder.block.container.account[op_ref.as_usize()])
+                .filter(|op| op.op().field == AccountField::CodeHash)
+                .map(|op| (op.rw(), op.op().address, op.op().value))
+                .collect::<Vec<_>>()
+        };
+        // the zero code hash proves that the callee doesn't exist
+        assert!(code_hash_reads(ExecState::BeginTx).contains(&(RW::READ, callee, Word::zero())));
+        // the coinbase is read the same way before it's rewarded
+        let coinbase_reads = code_hash_reads(ExecState::EndTx);
+        assert_eq!(coinbase_reads.len(), 1);
+        assert_eq!(
+            (coinbase_reads[0].0, coinbase_reads[0].1),
+            (RW::READ, *MOCK_COINBASE)
+        );
+    }
+
+    #[test]
+    fn test_reverted_tx_has_no_refund() {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(address!("0x0000000000000000000000000000000000000010"))
+                    .code(bytecode! {
+                        PUSH1(0)
+                        PUSH1(0)
+                        SSTORE
+                        PUSH1(0)
+                        PUSH1(0)
+                        REVERT
+                    })
+                    .storage(vec![(Word::zero(), Word::one())].into_iter());
+                accs[1]
+                    .address(address!("0x0000000000000000000000000000000000cafe01"))
+                    .balance(Word::from(1u64 << 30));
+            },
+            |mut txs, accs| {
+                txs[0].to(accs[0].address).from(accs[1].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let tx = &builder.block.txs()[0];
+        let refunds = |exec_state: ExecState| {
+            let step = tx
+                .steps()
+                .iter()
+                .find(|step| step.exec_state == exec_state)
+                .unwrap();
+            step.bus_mapping_instance
+                .iter()
+                .filter(|op_ref| op_ref.target() == Target::TxRefund)
+                .map(|op_ref| {
+                    let op = &builder.block.container.tx_refund[op_ref.as_usize()];
+                    (op.rw(), op.op().value)
+                })
+                .collect::<Vec<_>>()
+        };
+        // clearing the slot is refunded, until the revert undoes it
+        assert!(refunds(ExecState::Op(OpcodeId::SSTORE)).contains(&(RW::WRITE, 4800)));
+        assert_eq!(refunds(ExecState::EndTx), vec![(RW::READ, 0)]);
+
+        // only the gas left is given back to the caller
+        let end_tx = tx.steps().last().unwrap();
+        let balance_refund = end_tx
+            .bus_mapping_instance
+            .iter()
+            .filter(|op_ref| op_ref.target() == Target::Account)
+            .map(|op_ref| builder.block.container.account[op_ref.as_usize()].op())
+            .find(|op| op.address == tx.from && op.field == AccountField::Balance)
+            .map(|op| op.value - op.value_prev)
+            .unwrap();
+        assert_eq!(balance_refund, tx.gas_price * end_tx.gas_left.0);
+    }
+
+    #[test]
+    fn gas_accounting_reconciliation() {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! {
+                PUSH1(1)
+                POP
+                STOP
+            }),
+            |mut txs, accs| {
+                txs[0].to(accs[0].address).from(accs[1].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let handle_block = |block: &GethData| {
+            BlockData::new_from_geth_data(block.clone())
+                .new_circuit_input_builder()
+                .handle_block(&block.eth_block, &block.geth_traces)
+        };
+        // the gas accounting is reconciled at the end of the tx
+        assert!(handle_block(&block).is_ok());
+
+        // a trace reporting one more gas used than the execution has spent
+        let mut doctored = block.clone();
+        doctored.geth_traces[0].gas.0 += 1;
+        assert!(matches!(
+            handle_block(&doctored),
+            Err(Error::GasAccountingMismatch("gas", gas, spent)) if spent == gas + 1
+        ));
+    }
+
+    #[test]
+    fn test_transfer_drains_account() {
+        // An EOA with zero nonce whose entire balance is transferred away.
+        let drained = address!("0x0000000000000000000000000000000000cafe02");
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            |accs| {
+                let [acc0, acc1, acc2] = accs;
+                account_0_code_account_1_no_code(bytecode! { STOP })([acc0, acc1]);
+                acc2.address(drained)
+                    .balance(Word::from(100u64))
+                    .nonce(Word::zero());
+            },
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        let eth_tx = &block.eth_block.transactions[0];
+        let geth_trace = &block.geth_traces[0];
+        let mut tx = builder.new_tx(eth_tx, !geth_trace.failed).unwrap();
+        let mut tx_ctx = TransactionContext::new(eth_tx, geth_trace, true).unwrap();
+
+        let mut state = builder.state_ref(&mut tx, &mut tx_ctx);
+        gen_begin_tx_steps(&mut state).unwrap();
+        // drain it within the call of the tx
+        let mut step = state.new_begin_tx_step();
+        state
+            .transfer(
+                &mut step,
+                drained,
+                eth_tx.to.unwrap(),
+                true,
+                false,
+                Word::from(100u64),
+            )
+            .unwrap();
+
+        // The drained account is retained, and reads as existing but empty.
+        let (found, account) = builder.sdb.get_account(&drained);
+        assert!(found);
+        assert_eq!(account.balance, Word::zero());
+        assert_eq!(account.nonce, Word::zero());
+        assert_eq!(account.code_hash, CodeDB::empty_code_hash());
+        assert!(account.is_empty());
+    }
+}
@@ -41,9 +41,114 @@ impl Opcode for Blockhash {
             0.into()
         };
         #[cfg(feature = "enable-stack")]
-        assert_eq!(block_hash, geth_steps[1].stack.last()?);
+        if block_hash != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, block_hash)?;
 
         Ok(vec![exec_step])
     }
 }
+
+#[cfg(test)]
+mod blockhash_tests {
+    use super::*;
+    use crate::{
+        circuit_input_builder::ExecState,
+        mock::BlockData,
+        operation::{StackOp, RW},
+    };
+    use eth_types::{
+        bytecode,
+        evm_types::{OpcodeId, StackAddress},
+        geth_types::GethData,
+        Word,
+    };
+    use mock::test_ctx::{helpers::*, TestContext};
+
+    const CURRENT_BLOCK_NUMBER: u64 = 0x1000;
+
+    // The hash of `block_number` expected to be pushed by BLOCKHASH, when the
+    // history hashes are `0xbeefcafe + n` for the 256 previous blocks `n`.
+    fn history_hash(block_number: u64) -> Word {
+        if cfg!(feature = "scroll") {
+            calculate_block_hash(mock::MOCK_CHAIN_ID, block_number.into()).1
+        } else {
+            Word::from(0xbeefcafeu64 + block_number)
+        }
+    }
+
+    // Return the value pushed by BLOCKHASH for `block_number`.
+    fn blockhash_result(block_number: u64) -> Word {
+        let code = bytecode! {
+            PUSH32(block_number)
+            BLOCKHASH
+            STOP
+        };
+        let history_hashes = (CURRENT_BLOCK_NUMBER - 256..CURRENT_BLOCK_NUMBER)
+            .map(|n| Word::from(0xbeefcafeu64 + n))
+            .collect();
+        let block: GethData = TestContext::<2, 1>::new(
+            Some(history_hashes),
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(CURRENT_BLOCK_NUMBER),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::BLOCKHASH))
+            .unwrap();
+        assert!(step.error.is_none());
+
+        let call_id = builder.block.txs()[0].calls()[0].call_id;
+        let stack_ops = step
+            .bus_mapping_instance
+            .iter()
+            .map(|op_ref| &builder.block.container.stack[op_ref.as_usize()])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            (stack_ops[0].rw(), stack_ops[0].op()),
+            (
+                RW::READ,
+                &StackOp::new(call_id, StackAddress::from(1023), block_number.into())
+            )
+        );
+        assert_eq!(stack_ops[1].rw(), RW::WRITE);
+        assert_eq!(stack_ops[1].op().address, StackAddress::from(1023));
+        stack_ops[1].op().value
+    }
+
+    #[test]
+    fn blockhash_of_previous_block() {
+        let block_number = CURRENT_BLOCK_NUMBER - 1;
+        assert_eq!(blockhash_result(block_number), history_hash(block_number));
+    }
+
+    #[test]
+    fn blockhash_of_oldest_block_in_window() {
+        let block_number = CURRENT_BLOCK_NUMBER - 256;
+        assert_eq!(blockhash_result(block_number), history_hash(block_number));
+    }
+
+    #[test]
+    fn blockhash_of_block_out_of_window() {
+        assert_eq!(blockhash_result(CURRENT_BLOCK_NUMBER - 257), Word::zero());
+    }
+
+    #[test]
+    fn blockhash_of_current_and_future_blocks() {
+        assert_eq!(blockhash_result(CURRENT_BLOCK_NUMBER), Word::zero());
+        assert_eq!(blockhash_result(CURRENT_BLOCK_NUMBER + 1), Word::zero());
+    }
+}
@@ -407,6 +407,26 @@ mod calldataload_tests {
         );
     }
 
+    #[test]
+    fn calldataload_opcode_root_past_end() {
+        let calldata = rand_bytes(40);
+
+        // 1. fully within call data
+        test_root_ok(
+            4u64,
+            calldata.clone(),
+            Word::from_big_endian(&calldata[4..36]),
+        );
+
+        // 2. straddling the end: right padded with zeros
+        let mut expected = calldata[20..].to_vec();
+        expected.resize(32, 0);
+        test_root_ok(20u64, calldata.clone(), Word::from_big_endian(&expected));
+
+        // 3. fully past the end: all zeros
+        test_root_ok(64u64, calldata, Word::zero());
+    }
+
     #[test]
     fn calldataload_opcode_internal() {
         let pushdata = rand_bytes(0x08);
@@ -26,7 +26,12 @@ impl Opcode for Calldatasize {
             call_data_length,
         )?;
         #[cfg(feature = "enable-stack")]
-        assert_eq!(call_data_length, geth_steps[1].stack.last()?);
+        if call_data_length != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, call_data_length)?;
         Ok(vec![exec_step])
     }
@@ -43,9 +48,13 @@ mod calldatasize_tests {
         bytecode,
         evm_types::{OpcodeId, StackAddress},
         geth_types::GethData,
+        Word,
     };
 
-    use mock::test_ctx::{helpers::*, TestContext};
+    use mock::{
+        test_ctx::{helpers::*, TestContext},
+        MOCK_ACCOUNTS,
+    };
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -105,4 +114,62 @@ mod calldatasize_tests {
             )
         );
     }
+
+    // Return the value pushed by the CALLDATASIZE of the first tx of `block`.
+    fn calldatasize_of(block: GethData) -> Word {
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::CALLDATASIZE))
+            .unwrap();
+        *builder.block.container.stack[step.bus_mapping_instance[1].as_usize()]
+            .op()
+            .value()
+    }
+
+    #[test]
+    fn calldatasize_in_constructor_vs_call() {
+        let code = bytecode! {
+            CALLDATASIZE
+            POP
+            STOP
+        };
+
+        // the init code of a deployment tx is not call data
+        let deployment: GethData = TestContext::<1, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(MOCK_ACCOUNTS[0])
+                    .balance(Word::from(1u64 << 30));
+            },
+            |mut txs, accs| {
+                txs[0].from(accs[0].address).input(code.code().into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        assert_eq!(calldatasize_of(deployment), Word::zero());
+
+        let call: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .input(vec![1, 2, 3, 4].into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        assert_eq!(calldatasize_of(call), Word::from(4));
+    }
 }
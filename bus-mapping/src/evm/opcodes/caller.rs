@@ -30,7 +30,12 @@ impl Opcode for Caller {
 
         // Stack write of the caller_address
         #[cfg(feature = "enable-stack")]
-        assert_eq!(caller_address, geth_steps[1].stack.last()?);
+        if caller_address != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, caller_address)?;
 
         Ok(vec![exec_step])
@@ -112,4 +117,101 @@ mod caller_tests {
             )
         );
     }
+
+    #[test]
+    fn caller_and_origin_in_nested_calls() {
+        use crate::operation::Target;
+        use eth_types::{address, Word};
+
+        let sender = address!("0x0000000000000000000000000000000000cafe01");
+        let (a, b, c) = (
+            address!("0x0000000000000000000000000000000000000010"),
+            address!("0x0000000000000000000000000000000000000020"),
+            address!("0x0000000000000000000000000000000000000030"),
+        );
+        // A calls B, which delegate calls C
+        let code_a = bytecode! {
+            CALLER
+            ORIGIN
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(b.to_word())
+            GAS
+            CALL
+            STOP
+        };
+        let code_b = bytecode! {
+            CALLER
+            ORIGIN
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(c.to_word())
+            GAS
+            DELEGATECALL
+            STOP
+        };
+        let code_c = bytecode! {
+            CALLER
+            ORIGIN
+            STOP
+        };
+        let block: GethData = TestContext::<4, 1>::new(
+            None,
+            |accs| {
+                accs[0].address(a).code(code_a);
+                accs[1].address(b).code(code_b);
+                accs[2].address(c).code(code_c);
+                accs[3].address(sender).balance(Word::from(1u64 << 20));
+            },
+            |mut txs, accs| {
+                txs[0].to(accs[0].address).from(accs[3].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        // (address of the frame, value pushed) of each step of `op`
+        let tx = &builder.block.txs()[0];
+        let pushed_by = |op: OpcodeId| {
+            tx.steps()
+                .iter()
+                .filter(|step| step.exec_state == ExecState::Op(op))
+                .map(|step| {
+                    let value = step
+                        .bus_mapping_instance
+                        .iter()
+                        .filter(|op_ref| op_ref.target() == Target::Stack)
+                        .map(|op_ref| &builder.block.container.stack[op_ref.as_usize()])
+                        .find(|op| op.rw() == RW::WRITE)
+                        .map(|op| *op.op().value())
+                        .unwrap();
+                    (tx.calls()[step.call_index].address, value)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // C runs in the context of B, so its caller is B's caller
+        assert_eq!(
+            pushed_by(OpcodeId::CALLER),
+            vec![(a, sender.to_word()), (b, a.to_word()), (b, a.to_word())]
+        );
+        assert_eq!(
+            pushed_by(OpcodeId::ORIGIN),
+            vec![
+                (a, sender.to_word()),
+                (b, sender.to_word()),
+                (b, sender.to_word())
+            ]
+        );
+    }
 }
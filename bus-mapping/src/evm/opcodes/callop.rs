@@ -74,8 +74,8 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
         };
         // Precheck is OK when depth is in range and caller balance is sufficient.
         let is_call_or_callcode = matches!(callee_kind, CallKind::Call | CallKind::CallCode);
-        let is_precheck_ok =
-            geth_step.depth < 1025 && (!is_call_or_callcode || caller_balance >= call_value);
+        let is_precheck_ok = usize::from(geth_step.depth) <= state.max_call_depth
+            && (!is_call_or_callcode || caller_balance >= call_value);
 
         let callee_call = if is_precheck_ok {
             state.parse_call(geth_step)?
@@ -178,8 +178,8 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
             state.call_context_write(&mut exec_step, callee_call.call_id, field, value)?;
         }
 
-        let is_precheck_ok =
-            geth_step.depth < 1025 && (!is_call_or_callcode || caller_balance >= callee_call.value);
+        let is_precheck_ok = usize::from(geth_step.depth) <= state.max_call_depth
+            && (!is_call_or_callcode || caller_balance >= callee_call.value);
 
         // read balance of caller to compare to value for insufficient_balance checking
         // in circuit, also use for callcode successful case check balance is
@@ -257,21 +257,23 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
 
         // There are 4 branches from here.
         // add failure case for insufficient balance or error depth in the future.
-        if geth_steps[0].op == OpcodeId::CALL
-            && geth_steps[1].depth == geth_steps[0].depth + 1
+        if geth_steps[1].depth == geth_steps[0].depth + 1
             && geth_steps[1].gas.0 != callee_gas_left_with_stipend
         {
             // panic with full info
             let info1 = format!("callee_gas_left {callee_gas_left} gas_specified {gas_specified} gas_cost {gas_cost} is_warm {is_warm} has_value {has_value} current_memory_word_size {curr_memory_word_size} next_memory_word_size {next_memory_word_size}, memory_expansion_gas_cost {memory_expansion_gas_cost}");
-            let info2 = format!("args gas:{:?} addr:{:?} value:{:?} cd_pos:{:?} cd_len:{:?} rd_pos:{:?} rd_len:{:?}",
-                                stack_inputs[0],
-                                stack_inputs[1],
-                                stack_inputs[2],
-                                stack_inputs[3],
-                                stack_inputs[4],
-                                stack_inputs[5],
-                                stack_inputs[6]
-                    );
+            let arg_labels: &[&str] = match geth_step.op {
+                OpcodeId::CALL | OpcodeId::CALLCODE => &[
+                    "gas", "addr", "value", "cd_pos", "cd_len", "rd_pos", "rd_len",
+                ],
+                _ => &["gas", "addr", "cd_pos", "cd_len", "rd_pos", "rd_len"],
+            };
+            let args: Vec<String> = arg_labels
+                .iter()
+                .zip(stack_inputs.iter())
+                .map(|(label, arg)| format!("{label}:{arg:?}"))
+                .collect();
+            let info2 = format!("args {}", args.join(" "));
             let full_ctx = format!(
                 "step0 {:?} step1 {:?} call {:?}, {} {}",
                 geth_steps[0], geth_steps[1], callee_call, info1, info2
@@ -290,21 +292,26 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
 
                 // get the result of the precompile call.
                 // For failed call, it will cost all gas provided.
+                let input = if args_length != 0 {
+                    let caller_memory = &state.caller_ctx()?.memory;
+                    caller_memory.0[args_offset..args_offset + args_length].to_vec()
+                } else {
+                    vec![]
+                };
                 let (result, precompile_call_gas_cost, has_oog_err) = execute_precompiled(
                     &code_address,
-                    if args_length != 0 {
-                        let caller_memory = &state.caller_ctx()?.memory;
-                        &caller_memory.0[args_offset..args_offset + args_length]
-                    } else {
-                        &[]
-                    },
+                    &input,
                     callee_gas_left_with_stipend,
+                    state.diagnostics,
+                    tx_id,
                 );
 
                 // mutate the callee memory by at least the precompile call's result that will be
                 // written from memory addr 0 to memory addr result.len()
                 state.call_ctx_mut()?.memory.extend_at_least(result.len());
 
+                // the full result is kept as return data, while only the first `ret_length`
+                // bytes of it are copied to the caller's memory.
                 state.caller_ctx_mut()?.return_data = result.clone();
                 // mutate the caller memory.
                 let length = min(result.len(), ret_length);
@@ -451,7 +458,8 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
                     None
                 };
 
-                // insert another copy event (output) for this step.
+                // insert another copy event (output) for this step, bounded by the caller's
+                // out-size.
                 let rw_counter_start = state.block_ctx.rwc;
                 let returned_bytes = if callee_call.is_success() && length > 0 {
                     let (read_steps, write_steps, prev_bytes) = state
@@ -1075,4 +1083,872 @@ pub mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_precompile_output_truncated_by_ret_size() {
+        use crate::{
+            circuit_input_builder::{CopyDataType, NumberOrHash},
+            mock::{stack_push_of, BlockData},
+        };
+        use eth_types::{bytecode, geth_types::GethData};
+        use mock::{
+            test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+            TestContext,
+        };
+
+        // sha256 returns 32 bytes, but only 16 bytes are requested.
+        let call = PrecompileCallArgs {
+            name: "SHA2-256",
+            setup_code: bytecode! {
+                PUSH1(0xFF) // data
+                PUSH1(0)
+                MSTORE
+            },
+            ret_size: Word::from(0x10),
+            ret_offset: Word::from(0x20),
+            call_data_length: Word::from(0x1),
+            call_data_offset: Word::from(0x1F),
+            address: Word::from(0x2),
+            ..Default::default()
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(call.with_call_op(OpcodeId::CALL)),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        // the copy event to the caller's memory is bounded by the out-size
+        let caller_id = builder.block.txs()[0].calls()[0].call_id;
+        let output_copy = builder
+            .block
+            .copy_events
+            .iter()
+            .find(|event| {
+                event.src_type == CopyDataType::Memory
+                    && event.dst_type == CopyDataType::Memory
+                    && event.dst_id == NumberOrHash::Number(caller_id)
+            })
+            .unwrap();
+        assert_eq!(output_copy.src_addr_end, 0x10);
+        assert_eq!(output_copy.dst_addr, 0x20);
+
+        // while the full output is kept as return data
+        assert_eq!(
+            stack_push_of(&builder, OpcodeId::RETURNDATASIZE),
+            Word::from(0x20)
+        );
+    }
+
+    #[test]
+    fn test_reverted_call_restores_access_list() {
+        use crate::{
+            circuit_input_builder::ExecState,
+            mock::BlockData,
+            operation::{Op, Target, TxAccessListAccountOp},
+        };
+        use eth_types::{address, bytecode, geth_types::GethData, ToWord};
+        use mock::{
+            test_ctx::helpers::{caller_and_callee, tx_from_2_to_0},
+            TestContext, MOCK_CALLEE,
+        };
+
+        let addr_a = address!("0x00000000000000000000000000000000000000aa");
+        let addr_b = address!("0x00000000000000000000000000000000000000bb");
+
+        // the callee warms B and then reverts
+        let callee_code = bytecode! {
+            PUSH20(addr_b.to_word())
+            BALANCE
+            POP
+            PUSH1(0)
+            PUSH1(0)
+            REVERT
+        };
+        // the caller warms A, calls the callee, then reads A and B again
+        let caller_code = bytecode! {
+            PUSH20(addr_a.to_word())
+            BALANCE
+            POP
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(MOCK_CALLEE.to_word())
+            GAS
+            CALL
+            POP
+            PUSH20(addr_a.to_word())
+            BALANCE
+            POP
+            PUSH20(addr_b.to_word())
+            BALANCE
+            POP
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, callee_code),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let access_list_ops: Vec<Vec<TxAccessListAccountOp>> = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .filter(|step| step.exec_state == ExecState::Op(OpcodeId::BALANCE))
+            .map(|step| {
+                step.bus_mapping_instance
+                    .iter()
+                    .filter(|op_ref| op_ref.target() == Target::TxAccessListAccount)
+                    .map(|op_ref| {
+                        builder.block.container.tx_access_list_account[op_ref.as_usize()]
+                            .op()
+                            .clone()
+                    })
+                    .collect()
+            })
+            .collect();
+        let warm_prev: Vec<_> = access_list_ops
+            .iter()
+            .map(|ops| (ops[0].address, ops[0].is_warm_prev))
+            .collect();
+        // A warmed by the caller, B warmed by the callee, then after the revert
+        // A is still warm while B is cold again.
+        assert_eq!(
+            warm_prev,
+            vec![
+                (addr_a, false),
+                (addr_b, false),
+                (addr_a, true),
+                (addr_b, false)
+            ]
+        );
+
+        // the callee's warming of B is reversed on the step which did it
+        let callee_ops = &access_list_ops[1];
+        assert_eq!(callee_ops.len(), 2);
+        assert_eq!(callee_ops[1], callee_ops[0].reverse());
+        assert!(!callee_ops[1].is_warm);
+    }
+
+    #[test]
+    fn test_call_with_insufficient_balance() {
+        use crate::{
+            mock::{stack_push_of, step_of, BlockData},
+            operation::{Target, RW},
+        };
+        use eth_types::{
+            bytecode,
+            evm_types::{GasCost, GAS_STIPEND_CALL_WITH_VALUE},
+            geth_types::GethData,
+            ToWord,
+        };
+        use mock::{
+            test_ctx::helpers::{caller_and_callee_with_balances, tx_from_2_to_0},
+            TestContext, MOCK_CALLEE,
+        };
+
+        // the caller only holds 100 wei but sends 1000
+        let caller_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH2(1000)
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH2(10000)
+            CALL
+            RETURNDATASIZE
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee_with_balances(
+                caller_code,
+                Word::from(100u64),
+                bytecode! { PUSH1(1) STOP },
+                Word::one(),
+            ),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        // CALL pushes 0 and leaves the return data empty
+        assert_eq!(stack_push_of(&builder, OpcodeId::CALL), Word::zero());
+        assert_eq!(
+            stack_push_of(&builder, OpcodeId::RETURNDATASIZE),
+            Word::zero()
+        );
+
+        // the callee is never entered and no value is transferred
+        let tx = &builder.block.txs()[0];
+        let callee_call = &tx.calls()[1];
+        assert!(!callee_call.is_success);
+        assert!(tx.steps().iter().all(|step| step.call_index != 1));
+        let call_step = step_of(&builder, OpcodeId::CALL);
+        assert!(call_step
+            .bus_mapping_instance
+            .iter()
+            .filter(|op_ref| op_ref.target() == Target::Account)
+            .map(|op_ref| &builder.block.container.account[op_ref.as_usize()])
+            .all(|op| op.rw() == RW::READ));
+
+        // only the cold access and value transfer cost are charged, and the
+        // stipend is given back along with the unused callee gas
+        assert_eq!(
+            call_step.gas_left.0 - step_of(&builder, OpcodeId::RETURNDATASIZE).gas_left.0,
+            GasCost::COLD_ACCOUNT_ACCESS.as_u64() + GasCost::CALL_WITH_VALUE.as_u64()
+                - GAS_STIPEND_CALL_WITH_VALUE
+        );
+    }
+
+    #[test]
+    fn test_value_call_reverted() {
+        use crate::{
+            mock::{step_of, BlockData},
+            operation::{AccountField, RW},
+        };
+        use eth_types::{
+            bytecode,
+            evm_types::{GasCost, GAS_STIPEND_CALL_WITH_VALUE},
+            geth_types::GethData,
+            ToWord,
+        };
+        use mock::{
+            test_ctx::helpers::{caller_and_callee_with_balances, tx_from_2_to_0},
+            TestContext, MOCK_CALLEE, MOCK_CALLER,
+        };
+
+        let caller_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH2(1000)
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH2(10000)
+            CALL
+            RETURNDATASIZE
+            STOP
+        };
+        // 3 + 3 gas
+        let callee_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            REVERT
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee_with_balances(
+                caller_code,
+                Word::from(10000u64),
+                callee_code,
+                Word::one(),
+            ),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let tx = &builder.block.txs()[0];
+        let callee_call = &tx.calls()[1];
+        assert!(!callee_call.is_success);
+        assert!(!callee_call.is_persistent);
+
+        // the value transfer is written on CALL and reverted on REVERT
+        let balance_writes = |address| {
+            builder
+                .block
+                .container
+                .account
+                .iter()
+                .filter(|op| {
+                    op.rw() == RW::WRITE
+                        && op.op().address == address
+                        && op.op().field == AccountField::Balance
+                })
+                .map(|op| (op.op().value_prev.as_u64(), op.op().value.as_u64()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            balance_writes(*MOCK_CALLER),
+            vec![(10000, 9000), (9000, 10000)]
+        );
+        assert_eq!(balance_writes(*MOCK_CALLEE), vec![(1, 1001), (1001, 1)]);
+        assert_eq!(builder.sdb.get_balance(&MOCK_CALLER), Word::from(10000u64));
+        assert_eq!(builder.sdb.get_balance(&MOCK_CALLEE), Word::one());
+
+        // the callee runs with the stipend, and the gas it doesn't use goes
+        // back to the caller, stipend included
+        let callee_entry = tx.steps().iter().find(|step| step.call_index == 1).unwrap();
+        assert_eq!(callee_entry.gas_left.0, 10000 + GAS_STIPEND_CALL_WITH_VALUE);
+        assert_eq!(
+            step_of(&builder, OpcodeId::CALL).gas_left.0
+                - step_of(&builder, OpcodeId::RETURNDATASIZE).gas_left.0,
+            GasCost::COLD_ACCOUNT_ACCESS.as_u64() + GasCost::CALL_WITH_VALUE.as_u64()
+                - GAS_STIPEND_CALL_WITH_VALUE
+                + 6
+        );
+    }
+
+    #[test]
+    fn test_value_call_reverted_with_all_but_one_64th() {
+        use crate::mock::{step_of, BlockData};
+        use eth_types::{
+            bytecode,
+            evm_types::{GasCost, GAS_STIPEND_CALL_WITH_VALUE},
+            geth_types::GethData,
+            ToWord,
+        };
+        use mock::{
+            test_ctx::helpers::{caller_and_callee_with_balances, tx_from_2_to_0},
+            TestContext, MOCK_CALLEE, MOCK_CALLER,
+        };
+
+        // request all the gas left, so that the callee gets all but one 64th
+        let caller_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH2(1000)
+            PUSH20(MOCK_CALLEE.to_word())
+            GAS
+            CALL
+            RETURNDATASIZE
+            STOP
+        };
+        // 3 + 3 gas
+        let callee_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            REVERT
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee_with_balances(
+                caller_code,
+                Word::from(10000u64),
+                callee_code,
+                Word::one(),
+            ),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        // the value transfer is undone
+        assert_eq!(builder.sdb.get_balance(&MOCK_CALLER), Word::from(10000u64));
+        assert_eq!(builder.sdb.get_balance(&MOCK_CALLEE), Word::one());
+
+        let call_step = step_of(&builder, OpcodeId::CALL);
+        let available = call_step.gas_left.0
+            - GasCost::COLD_ACCOUNT_ACCESS.as_u64()
+            - GasCost::CALL_WITH_VALUE.as_u64();
+        let forwarded = available - available / 64;
+        let callee_entry = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.call_index == 1)
+            .unwrap();
+        assert_eq!(
+            callee_entry.gas_left.0,
+            forwarded + GAS_STIPEND_CALL_WITH_VALUE
+        );
+        // the caller keeps its 64th and only gets back what the callee didn't
+        // use
+        assert_eq!(
+            step_of(&builder, OpcodeId::RETURNDATASIZE).gas_left.0,
+            available / 64 + callee_entry.gas_left.0 - 6
+        );
+    }
+
+    #[test]
+    fn test_delegatecall_forwards_all_but_one_64th() {
+        use crate::mock::{step_of, BlockData};
+        use eth_types::{bytecode, evm_types::GasCost, geth_types::GethData, ToWord};
+        use mock::{
+            test_ctx::helpers::{caller_and_callee, tx_from_2_to_0},
+            TestContext, MOCK_CALLEE,
+        };
+
+        let caller_code = bytecode! {
+            PUSH1(0) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(0) // argsLength
+            PUSH1(0) // argsOffset
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH32(Word::MAX) // gas
+            DELEGATECALL
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, bytecode! { STOP }),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let call_step = step_of(&builder, OpcodeId::DELEGATECALL);
+        let callee_entry = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.call_index == 1)
+            .unwrap();
+
+        // no stipend is added, and 1/64 of the gas left is kept by the caller
+        let gas_available = call_step.gas_left.0 - GasCost::COLD_ACCOUNT_ACCESS.as_u64();
+        assert_eq!(callee_entry.gas_left.0, gas_available - gas_available / 64);
+    }
+
+    // Handle a call by `call_op` with the gas of the callee's first step
+    // doctored in the trace, which is reported as a gas mismatch.
+    #[cfg(debug_assertions)]
+    fn handle_doctored_callee_gas(call_op: OpcodeId) {
+        use crate::mock::BlockData;
+        use eth_types::{bytecode, geth_types::GethData, Bytecode, ToWord};
+        use mock::{
+            test_ctx::helpers::{caller_and_callee, tx_from_2_to_0},
+            TestContext, MOCK_CALLEE,
+        };
+
+        let mut caller_code = Bytecode::default();
+        caller_code
+            .push(1, 0x20) // retLength
+            .push(1, 0) // retOffset
+            .push(1, 0x20) // argsLength
+            .push(1, 0) // argsOffset
+            .push(20, MOCK_CALLEE.to_word())
+            .push(2, 10000) // gas
+            .write_op(call_op)
+            .write_op(OpcodeId::STOP);
+        let mut block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, bytecode! { STOP }),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let struct_logs = &mut block.geth_traces[0].struct_logs;
+        let call_idx = struct_logs
+            .iter()
+            .position(|step| step.op == call_op)
+            .unwrap();
+        assert_eq!(struct_logs[call_idx + 1].depth, 2);
+        struct_logs[call_idx + 1].gas.0 += 1;
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "args gas:10000 addr:32 cd_pos:0 cd_len:32 rd_pos:0 rd_len:32")]
+    fn test_delegatecall_gas_mismatch() {
+        handle_doctored_callee_gas(OpcodeId::DELEGATECALL);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "args gas:10000 addr:32 cd_pos:0 cd_len:32 rd_pos:0 rd_len:32")]
+    fn test_staticcall_gas_mismatch() {
+        handle_doctored_callee_gas(OpcodeId::STATICCALL);
+    }
+
+    #[test]
+    fn test_call_gas_above_u64() {
+        use crate::mock::{step_of, BlockData};
+        use eth_types::{bytecode, evm_types::GasCost, geth_types::GethData, ToWord};
+        use mock::{
+            test_ctx::helpers::{caller_and_callee, tx_from_2_to_0},
+            TestContext, MOCK_CALLEE,
+        };
+
+        // the low 64 bits of the requested gas are zero
+        let caller_code = bytecode! {
+            PUSH1(0) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(0) // argsLength
+            PUSH1(0) // argsOffset
+            PUSH1(0) // value
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH32(Word::one() << 128) // gas
+            CALL
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, bytecode! { STOP }),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let call_step = step_of(&builder, OpcodeId::CALL);
+        let callee_entry = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.call_index == 1)
+            .unwrap();
+
+        // the requested gas saturates instead of being truncated to zero, so
+        // the callee gets all but one 64th of the gas available
+        let gas_available = call_step.gas_left.0 - GasCost::COLD_ACCOUNT_ACCESS.as_u64();
+        assert_eq!(callee_entry.gas_left.0, gas_available - gas_available / 64);
+    }
+
+    #[test]
+    fn test_staticcall_precompile_in_static_frame() {
+        use crate::{
+            circuit_input_builder::ExecState,
+            mock::{stack_push_of, BlockData},
+            precompile::PrecompileCalls,
+        };
+        use eth_types::{bytecode, geth_types::GethData, word, ToWord};
+        use mock::{
+            test_ctx::helpers::{caller_and_callee, tx_from_2_to_0},
+            TestContext, MOCK_CALLEE,
+        };
+
+        let recovered = word!("7156526fbd7a3c72969b54f64e42c10fbb768c8a");
+        let ecrecover = PrecompileCallArgs {
+            name: "ecRecover",
+            setup_code: bytecode! {
+                PUSH32(word!("0x456e9aea5e197a1f1af7a3e85a3212fa4049a3ba34c2289b4c860fc0b0c64ef3")) // hash
+                PUSH1(0x0)
+                MSTORE
+                PUSH1(28) // v
+                PUSH1(0x20)
+                MSTORE
+                PUSH32(word!("0x9242685bf161793cc25603c231bc2f568eb630ea16aa137d2664ac8038825608")) // r
+                PUSH1(0x40)
+                MSTORE
+                PUSH32(word!("0x4f8ae3bd7535248d0bd448298cc2e2071e56992d0774dc340c368ae950852ada")) // s
+                PUSH1(0x60)
+                MSTORE
+            },
+            ret_size: Word::from(0x20),
+            ret_offset: Word::from(0x80),
+            call_data_length: Word::from(0x80),
+            address: Word::from(0x1),
+            stack_value: vec![(Word::from(0x80), recovered)],
+            ..Default::default()
+        };
+
+        // the callee runs in a static frame and STATICCALLs ecrecover from there
+        let mut callee_code = ecrecover.with_call_op(OpcodeId::STATICCALL);
+        callee_code.op_stop();
+        let caller_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(MOCK_CALLEE.to_word())
+            GAS
+            STATICCALL
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, callee_code),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let tx = &builder.block.txs()[0];
+        assert!(tx.steps().iter().all(|step| step.error.is_none()));
+
+        // ecrecover is charged its gas and succeeds inside the static frame
+        let precompile_step = tx
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Precompile(PrecompileCalls::Ecrecover))
+            .unwrap();
+        assert_eq!(
+            precompile_step.gas_cost,
+            PrecompileCalls::Ecrecover.base_gas_cost()
+        );
+        let precompile_call = &tx.calls()[precompile_step.call_index];
+        assert!(precompile_call.is_static);
+        assert!(precompile_call.is_success);
+
+        // the recovered address is copied back to the callee's memory
+        assert_eq!(stack_push_of(&builder, OpcodeId::MLOAD), recovered);
+    }
+
+    #[test]
+    fn test_value_call_to_identity() {
+        use crate::{
+            mock::{stack_push_of, step_of, BlockData},
+            operation::{AccountField, Target, RW},
+        };
+        use eth_types::{address, bytecode, geth_types::GethData, word};
+        use mock::{
+            test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+            TestContext,
+        };
+
+        let data = word!("0x0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef");
+        let identity = PrecompileCallArgs {
+            name: "identity with value",
+            setup_code: bytecode! {
+                PUSH32(data)
+                PUSH1(0)
+                MSTORE
+            },
+            call_data_offset: Word::zero(),
+            call_data_length: Word::from(0x20),
+            ret_offset: Word::from(0x20),
+            ret_size: Word::from(0x20),
+            address: Word::from(0x4),
+            value: Word::from(1000),
+            stack_value: vec![(Word::from(0x20), data)],
+            ..Default::default()
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(identity.with_call_op(OpcodeId::CALL)),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        // the value is credited to the precompile
+        let credit = step_of(&builder, OpcodeId::CALL)
+            .bus_mapping_instance
+            .iter()
+            .filter(|op_ref| op_ref.target() == Target::Account)
+            .map(|op_ref| &builder.block.container.account[op_ref.as_usize()])
+            .find(|op| {
+                op.rw() == RW::WRITE
+                    && op.op().address == address!("0x0000000000000000000000000000000000000004")
+                    && op.op().field == AccountField::Balance
+            })
+            .unwrap();
+        assert_eq!(credit.op().value, credit.op().value_prev + Word::from(1000));
+
+        // and the identity still echoes its input
+        assert_eq!(stack_push_of(&builder, OpcodeId::MLOAD), data);
+    }
+
+    #[test]
+    fn test_call_to_self_shares_storage() {
+        use crate::{
+            mock::{step_of, BlockData},
+            operation::{StorageOp, Target, RW},
+        };
+        use eth_types::{bytecode, geth_types::GethData};
+        use mock::{
+            test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+            TestContext, MOCK_ACCOUNTS,
+        };
+
+        // The outer frame (no call data) calls itself with one byte of call data
+        // and loads slot 0, which the inner frame sets to 0x42.
+        let code = bytecode! {
+            CALLDATASIZE
+            PUSH1(22) // inner frame
+            JUMPI
+            PUSH1(0) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(1) // argsLength
+            PUSH1(0) // argsOffset
+            PUSH1(0) // value
+            ADDRESS
+            GAS
+            CALL
+            POP
+            PUSH1(0)
+            SLOAD
+            STOP
+            JUMPDEST
+            PUSH1(0x42)
+            PUSH1(0)
+            SSTORE
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let calls = builder.block.txs()[0].calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            (calls[1].caller_address, calls[1].address),
+            (MOCK_ACCOUNTS[0], MOCK_ACCOUNTS[0])
+        );
+
+        let storage_op_of = |op: OpcodeId| {
+            let step = step_of(&builder, op);
+            let op_ref = step
+                .bus_mapping_instance
+                .iter()
+                .find(|op_ref| op_ref.target() == Target::Storage)
+                .unwrap();
+            let op = &builder.block.container.storage[op_ref.as_usize()];
+            (step.call_index, op.rw(), op.op().clone())
+        };
+        assert_eq!(
+            storage_op_of(OpcodeId::SSTORE),
+            (
+                1,
+                RW::WRITE,
+                StorageOp::new(
+                    MOCK_ACCOUNTS[0],
+                    Word::zero(),
+                    0x42.into(),
+                    0.into(),
+                    1,
+                    0.into()
+                )
+            )
+        );
+        // the write of the inner frame is seen by the outer one after return
+        assert_eq!(
+            storage_op_of(OpcodeId::SLOAD),
+            (
+                0,
+                RW::READ,
+                StorageOp::new(
+                    MOCK_ACCOUNTS[0],
+                    Word::zero(),
+                    0x42.into(),
+                    0x42.into(),
+                    1,
+                    0.into()
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn test_delegatecall_to_identity() {
+        use crate::{
+            circuit_input_builder::{CallKind, ExecState},
+            mock::{stack_push_of, BlockData},
+            precompile::PrecompileCalls,
+        };
+        use eth_types::{address, bytecode, evm_types::GasCost, geth_types::GethData, word};
+        use mock::{
+            test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+            TestContext,
+        };
+
+        let data = word!("0x0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef");
+        let identity = PrecompileCallArgs {
+            name: "identity under delegatecall",
+            setup_code: bytecode! {
+                PUSH32(data)
+                PUSH1(0)
+                MSTORE
+            },
+            call_data_length: Word::from(0x20),
+            ret_offset: Word::from(0x20),
+            ret_size: Word::from(0x20),
+            address: Word::from(0x4),
+            stack_value: vec![(Word::from(0x20), data)],
+            ..Default::default()
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(identity.with_call_op(OpcodeId::DELEGATECALL)),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let tx = &builder.block.txs()[0];
+        assert!(tx.steps().iter().all(|step| step.error.is_none()));
+
+        // the identity runs in the delegation context and is charged its gas
+        let precompile_step = tx
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Precompile(PrecompileCalls::Identity))
+            .unwrap();
+        assert_eq!(
+            precompile_step.gas_cost.0,
+            GasCost::PRECOMPILE_IDENTITY_BASE.0 + GasCost::PRECOMPILE_IDENTITY_PER_WORD.0
+        );
+        let precompile_call = &tx.calls()[precompile_step.call_index];
+        assert_eq!(precompile_call.kind, CallKind::DelegateCall);
+        assert_eq!(precompile_call.address, tx.calls()[0].address);
+        assert_eq!(
+            precompile_call.code_address(),
+            Some(address!("0x0000000000000000000000000000000000000004"))
+        );
+        assert!(precompile_call.is_success);
+
+        // and its output is the input
+        assert_eq!(stack_push_of(&builder, OpcodeId::MLOAD), data);
+    }
 }
@@ -107,12 +107,20 @@ mod codecopy_tests {
         state_db::CodeDB,
     };
 
+    // The code of `test_ok` is 101 bytes long.
     #[test]
     fn codecopy_opcode_impl() {
+        // within the code
         test_ok(0x00, 0x00, 0x40);
+        // straddling the end of the code
         test_ok(0x20, 0x40, 0xA0);
     }
 
+    #[test]
+    fn codecopy_past_code_end() {
+        test_ok(0x00, 0x80, 0x40);
+    }
+
     fn test_ok(memory_offset: usize, code_offset: usize, copy_size: usize) {
         let code = bytecode! {
             PUSH32(copy_size)
@@ -210,8 +218,9 @@ mod codecopy_tests {
             copy_events[0].src_id,
             NumberOrHash::Hash(CodeDB::hash(&code.to_vec()))
         );
-        assert_eq!(copy_events[0].src_addr as usize, code_offset);
-        assert_eq!(copy_events[0].src_addr_end as usize, code.to_vec().len());
+        let code_len = code.to_vec().len();
+        assert_eq!(copy_events[0].src_addr as usize, code_offset.min(code_len));
+        assert_eq!(copy_events[0].src_addr_end as usize, code_len);
         assert_eq!(copy_events[0].src_type, CopyDataType::Bytecode);
         assert_eq!(
             copy_events[0].dst_id,
@@ -228,5 +237,18 @@ mod codecopy_tests {
                 assert_eq!(*is_code, bytecode_element.is_code);
             }
         }
+        // the bytes past the end of the code are zero-padded
+        let padding = (code_offset + copy_size).saturating_sub(code_offset.max(code_len));
+        let copied_padding = copy_events[0]
+            .copy_bytes
+            .bytes
+            .iter()
+            .filter(|(_, _, is_mask)| !is_mask)
+            .skip(copy_size - padding)
+            .collect::<Vec<_>>();
+        assert_eq!(copied_padding.len(), padding);
+        assert!(copied_padding
+            .iter()
+            .all(|(value, is_code, _)| *value == 0 && !is_code));
     }
 }
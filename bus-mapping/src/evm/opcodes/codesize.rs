@@ -23,7 +23,12 @@ impl Opcode for Codesize {
         let codesize = code.len();
 
         #[cfg(feature = "enable-stack")]
-        assert_eq!(codesize, geth_steps[1].stack.last()?.as_usize());
+        if codesize != geth_steps[1].stack.last()?.as_usize() {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, codesize.into())?;
 
         Ok(vec![exec_step])
@@ -49,8 +49,9 @@ impl<const IS_CREATE2: bool> Opcode for Create<IS_CREATE2> {
         // Check if an error of ErrDepth, ErrInsufficientBalance or
         // ErrNonceUintOverflow occurred.
         let depth = caller.depth;
-        let is_precheck_ok =
-            depth < 1025 && caller_balance >= callee_value && caller_nonce < u64::MAX;
+        let is_precheck_ok = depth <= state.max_call_depth
+            && caller_balance >= callee_value
+            && caller_nonce < u64::MAX;
         let callee = if is_precheck_ok && !is_address_collision {
             state.parse_call(geth_step)?
         } else {
@@ -111,14 +112,25 @@ impl<const IS_CREATE2: bool> Opcode for Create<IS_CREATE2> {
             assert_eq!(*value, geth_step.stack.nth_last(i)?);
         }
 
-        state.stack_push(
-            &mut exec_step,
-            if callee.is_success {
-                address.to_word()
-            } else {
-                Word::zero()
-            },
-        )?;
+        // Push the created address on success, or zero if the creation failed (precheck
+        // failure, address collision, or a reverted / failed init code).
+        let stack_output = if callee.is_success {
+            address.to_word()
+        } else {
+            Word::zero()
+        };
+        // The callee frame is not entered when the precheck fails, the address collides or
+        // the init code is empty, so the next geth step is already back in the caller.
+        #[cfg(feature = "enable-stack")]
+        if (!is_precheck_ok || is_address_collision || length == 0)
+            && stack_output != geth_steps[1].stack.last()?
+        {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
+        state.stack_push(&mut exec_step, stack_output)?;
         // stack end
 
         state.call_context_read(
@@ -406,13 +418,282 @@ fn handle_copy(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{circuit_input_builder::ExecState, mock::BlockData, operation::RW};
-    use eth_types::{bytecode, evm_types::OpcodeId, geth_types::GethData, word};
+    use crate::{
+        circuit_input_builder::{CircuitInputBuilder, ExecState},
+        mock::{stack_push_of, step_of, BlockData},
+        operation::{Target, RW},
+    };
+    use eth_types::{bytecode, evm_types::OpcodeId, geth_types::GethData, word, Address};
+    use ethers_core::utils::get_contract_address;
     use mock::{
         test_ctx::{helpers::account_0_code_account_1_no_code, LoggerConfig},
-        TestContext,
+        TestContext, MOCK_ACCOUNTS,
     };
 
+    fn build_from_code(code: Bytecode) -> CircuitInputBuilder {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            |mut txs, accs| {
+                txs[0].from(accs[1].address).to(accs[0].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        builder
+    }
+
+    #[test]
+    fn test_create_reverted_init_pushes_zero() {
+        // init code: PUSH2(0xbeef) PUSH1(0) MSTORE PUSH1(2) PUSH1(30) REVERT
+        let code = bytecode! {
+            PUSH11(word!("0x61beef6000526002601efd"))
+            PUSH1(0)
+            MSTORE
+
+            PUSH1(11) // size
+            PUSH1(21) // offset
+            PUSH1(0)  // value
+            CREATE
+
+            RETURNDATASIZE
+            PUSH1(2)  // size
+            PUSH1(0)  // offset
+            PUSH1(62) // dest_offset
+            RETURNDATACOPY
+            PUSH1(32)
+            MLOAD
+            STOP
+        };
+        let builder = build_from_code(code);
+
+        assert_eq!(step_of(&builder, OpcodeId::CREATE).error, None);
+        assert_eq!(stack_push_of(&builder, OpcodeId::CREATE), Word::zero());
+        // return data of the reverted init code is visible to the caller
+        assert_eq!(stack_push_of(&builder, OpcodeId::RETURNDATASIZE), 2.into());
+        assert_eq!(step_of(&builder, OpcodeId::RETURNDATACOPY).error, None);
+        assert_eq!(stack_push_of(&builder, OpcodeId::MLOAD), 0xbeef.into());
+    }
+
+    #[test]
+    fn test_create_success_pushes_address() {
+        // init code: PUSH1(0) PUSH1(0) RETURN
+        let code = bytecode! {
+            PUSH5(word!("0x60006000f3"))
+            PUSH1(0)
+            MSTORE
+
+            PUSH1(5)  // size
+            PUSH1(27) // offset
+            PUSH1(0)  // value
+            CREATE
+
+            RETURNDATASIZE
+            STOP
+        };
+        let builder = build_from_code(code);
+
+        assert_eq!(
+            stack_push_of(&builder, OpcodeId::CREATE),
+            get_contract_address(MOCK_ACCOUNTS[0], 0).to_word()
+        );
+        assert_eq!(stack_push_of(&builder, OpcodeId::RETURNDATASIZE), 0.into());
+    }
+
+    #[test]
+    fn test_nested_deployment() {
+        // RLP([address, nonce]), whose keccak is the created address
+        let address_preimage = |address: Address, nonce: u64| {
+            let mut stream = rlp::RlpStream::new();
+            stream.begin_list(2);
+            stream.append(&address);
+            stream.append(&nonce);
+            stream.out().to_vec()
+        };
+        let child_init_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            RETURN
+        }
+        .code();
+        // the constructor of the factory deploys the child
+        let factory_init_code = bytecode! {
+            PUSH5(Word::from_big_endian(&child_init_code))
+            PUSH1(0)
+            MSTORE
+            PUSH1(5)  // size
+            PUSH1(27) // offset
+            PUSH1(0)  // value
+            CREATE
+            STOP
+        };
+        let block: GethData = TestContext::<1, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(MOCK_ACCOUNTS[0])
+                    .balance(Word::from(1u64 << 30));
+            },
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[0].address)
+                    .input(factory_init_code.code().into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        // a contract starts with nonce 1
+        let factory = get_contract_address(MOCK_ACCOUNTS[0], 0);
+        let child = get_contract_address(factory, 1);
+        let calls = builder.block.txs()[0].calls();
+        assert_eq!((calls[0].address, calls[1].address), (factory, child));
+        assert_eq!(stack_push_of(&builder, OpcodeId::CREATE), child.to_word());
+
+        // the preimages of the factory are followed by the ones of the child
+        assert_eq!(
+            builder.block.sha3_inputs[..4],
+            [
+                address_preimage(MOCK_ACCOUNTS[0], 0),
+                factory_init_code.code(),
+                address_preimage(factory, 1),
+                child_init_code,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_calldatasize_in_constructor_is_zero() {
+        // init code: CALLDATASIZE POP STOP
+        let init_code = word!("0x365000");
+        let codes = [
+            bytecode! {
+                PUSH3(init_code)
+                PUSH1(0)
+                MSTORE
+                PUSH1(3)  // size
+                PUSH1(29) // offset
+                PUSH1(0)  // value
+                CREATE
+                STOP
+            },
+            bytecode! {
+                PUSH3(init_code)
+                PUSH1(0)
+                MSTORE
+                PUSH1(0)  // salt
+                PUSH1(3)  // size
+                PUSH1(29) // offset
+                PUSH1(0)  // value
+                CREATE2
+                STOP
+            },
+        ];
+        for code in codes {
+            // the call data of the creator must not leak into the constructor
+            let block: GethData = TestContext::<2, 1>::new(
+                None,
+                account_0_code_account_1_no_code(code),
+                |mut txs, accs| {
+                    txs[0]
+                        .from(accs[1].address)
+                        .to(accs[0].address)
+                        .input(vec![1, 2, 3, 4].into());
+                },
+                |block, _tx| block.number(0xcafeu64),
+            )
+            .unwrap()
+            .into();
+            let mut builder =
+                BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+            builder
+                .handle_block(&block.eth_block, &block.geth_traces)
+                .unwrap();
+
+            let step = step_of(&builder, OpcodeId::CALLDATASIZE);
+            let callee = &builder.block.txs()[0].calls()[step.call_index];
+            assert!(callee.is_create());
+            assert_eq!((callee.call_data_offset, callee.call_data_length), (0, 0));
+            assert_eq!(
+                stack_push_of(&builder, OpcodeId::CALLDATASIZE),
+                Word::zero()
+            );
+        }
+    }
+
+    #[test]
+    fn test_create2_salt_changes_address() {
+        // init code: PUSH1(0) PUSH1(0) RETURN
+        let init_code = word!("0x60006000f3");
+        let code = bytecode! {
+            PUSH5(init_code)
+            PUSH1(0)
+            MSTORE
+
+            PUSH1(1)  // salt
+            PUSH1(5)  // size
+            PUSH1(27) // offset
+            PUSH1(0)  // value
+            CREATE2
+
+            PUSH1(2)  // salt
+            PUSH1(5)  // size
+            PUSH1(27) // offset
+            PUSH1(0)  // value
+            CREATE2
+            STOP
+        };
+        let builder = build_from_code(code);
+
+        let addresses: Vec<Word> = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .filter(|step| step.exec_state == ExecState::Op(OpcodeId::CREATE2))
+            .map(|step| {
+                let op_ref = step
+                    .bus_mapping_instance
+                    .iter()
+                    .find(|op_ref| {
+                        op_ref.target() == Target::Stack
+                            && builder.block.container.stack[op_ref.as_usize()].rw() == RW::WRITE
+                    })
+                    .unwrap();
+                *builder.block.container.stack[op_ref.as_usize()]
+                    .op()
+                    .value()
+            })
+            .collect();
+        assert_eq!(addresses.len(), 2);
+        assert_ne!(addresses[0], addresses[1]);
+
+        let init_code = init_code.to_be_bytes()[27..].to_vec();
+        for (salt, address) in [1u64, 2].into_iter().zip(addresses) {
+            let salt = Word::from(salt).to_be_bytes();
+            assert_eq!(
+                address,
+                get_create2_address(MOCK_ACCOUNTS[0], salt, init_code.clone()).to_word()
+            );
+            // 0xff ++ sender ++ salt ++ keccak(init_code)
+            let preimage: Vec<u8> = std::iter::once(0xffu8)
+                .chain(MOCK_ACCOUNTS[0].to_fixed_bytes())
+                .chain(salt)
+                .chain(keccak256(&init_code))
+                .collect();
+            assert!(builder.block.sha3_inputs.contains(&preimage));
+        }
+    }
+
     #[test]
     fn test_create_address_collision_error() {
         let code = bytecode! {
@@ -5,6 +5,8 @@ use crate::{
 };
 use eth_types::{evm_types::OpcodeId, GethExecStep, ToWord, Word, U256};
 
+/// Block information opcodes pushing a field of the block header, which is
+/// checked against the one seen by geth when the stack is traced.
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct GetBlockHeaderField<const OP: OpcodeId>;
 
@@ -12,12 +14,6 @@ trait BlockHeaderToField {
     fn handle(block_head: &BlockHead) -> Word;
 }
 
-impl BlockHeaderToField for GetBlockHeaderField<{ OpcodeId::COINBASE }> {
-    fn handle(block_head: &BlockHead) -> Word {
-        block_head.coinbase.to_word()
-    }
-}
-
 impl BlockHeaderToField for GetBlockHeaderField<{ OpcodeId::TIMESTAMP }> {
     fn handle(block_head: &BlockHead) -> Word {
         block_head.timestamp
@@ -68,7 +64,43 @@ where
         let output = Self::handle(block_head);
 
         #[cfg(feature = "enable-stack")]
-        assert_eq!(output, geth_steps[1].stack.last()?);
+        if output != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "block header field differs from the one seen by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
+        state.stack_push(&mut exec_step, output)?;
+
+        Ok(vec![exec_step])
+    }
+}
+
+/// COINBASE pushes the coinbase of the block, i.e. the one in the header or its
+/// override, which is checked against the one seen by geth when the stack is
+/// traced.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Coinbase;
+
+impl Opcode for Coinbase {
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+    ) -> Result<Vec<ExecStep>, Error> {
+        let mut exec_step = state.new_step(&geth_steps[0])?;
+        let output = state.block.coinbase(state.tx.block_num).to_word();
+
+        // geth always pushes the header coinbase, so an override is checked
+        // against the header and only the override reaches the rw op.
+        #[cfg(feature = "enable-stack")]
+        if state.block.headers[&state.tx.block_num].coinbase.to_word()
+            != geth_steps[1].stack.last()?
+        {
+            return Err(Error::InvalidGethExecStep(
+                "COINBASE differs from the coinbase of the block header",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, output)?;
 
         Ok(vec![exec_step])
@@ -87,7 +119,12 @@ impl Opcode for Pc {
         let output: U256 = geth_steps[0].pc.0.into();
 
         #[cfg(feature = "enable-stack")]
-        assert_eq!(output, geth_steps[1].stack.last()?);
+        if output != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, output)?;
 
         Ok(vec![exec_step])
@@ -106,7 +143,12 @@ impl Opcode for Msize {
         let output: U256 = state.call_ctx()?.memory.len().into();
 
         #[cfg(feature = "enable-stack")]
-        assert_eq!(output, geth_steps[1].stack.last()?);
+        if output != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, output)?;
 
         Ok(vec![exec_step])
@@ -125,7 +167,12 @@ impl Opcode for Gas {
         let output: U256 = geth_steps[1].gas.0.into();
 
         #[cfg(feature = "enable-stack")]
-        assert_eq!(output, geth_steps[1].stack.last()?);
+        if output != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, output)?;
 
         Ok(vec![exec_step])
@@ -134,9 +181,20 @@ impl Opcode for Gas {
 
 #[cfg(test)]
 mod tests {
-    use crate::mock::BlockData;
-    use eth_types::{bytecode, geth_types::GethData, Bytecode};
-    use mock::TestContext;
+    use crate::{
+        circuit_input_builder::{BlockHead, ExecState},
+        mock::BlockData,
+        operation::{Target, RW},
+        Error,
+    };
+    use eth_types::{
+        address, bytecode, evm_types::OpcodeId, geth_types::GethData, Address, Bytecode, ToWord,
+        Word,
+    };
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
 
     fn test_trace(code: Bytecode) {
         // Get the execution steps from the external tracer
@@ -173,4 +231,139 @@ mod tests {
             STOP
         });
     }
+
+    // Return the value pushed by COINBASE in a block whose header coinbase is
+    // `coinbase`, after setting `coinbase_override` if any.
+    fn coinbase_opcode_result(
+        coinbase: Address,
+        coinbase_override: Option<Address>,
+    ) -> Result<Word, Error> {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! {
+                COINBASE
+                STOP
+            }),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64).author(coinbase),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        if let Some(coinbase_override) = coinbase_override {
+            builder
+                .block
+                .set_coinbase_override(0xcafe, coinbase_override);
+        }
+        builder.handle_block(&block.eth_block, &block.geth_traces)?;
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::COINBASE))
+            .unwrap();
+        let op_ref = step.bus_mapping_instance[0];
+        assert_eq!(op_ref.target(), Target::Stack);
+        let op = &builder.block.container.stack[op_ref.as_usize()];
+        assert_eq!(op.rw(), RW::WRITE);
+        Ok(op.op().value)
+    }
+
+    // Return the value pushed by `op` after `tamper` altered the header of the
+    // block seen by geth.
+    fn header_field_opcode_result(
+        op: OpcodeId,
+        tamper: impl FnOnce(&mut BlockHead),
+    ) -> Result<Word, Error> {
+        let mut code = Bytecode::default();
+        code.write_op(op).write_op(OpcodeId::STOP);
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        tamper(builder.block.headers.get_mut(&0xcafe).unwrap());
+        builder.handle_block(&block.eth_block, &block.geth_traces)?;
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(op))
+            .unwrap();
+        Ok(
+            builder.block.container.stack[step.bus_mapping_instance[0].as_usize()]
+                .op()
+                .value,
+        )
+    }
+
+    // Check that `op` pushes `field` of the header, and that a header
+    // disagreeing with the trace is reported when the stack is traced.
+    fn assert_header_field_checked(op: OpcodeId, field: fn(&mut BlockHead) -> &mut Word) {
+        let mut expected = Word::zero();
+        let result = header_field_opcode_result(op, |header| expected = *field(header));
+        assert_eq!(result.unwrap(), expected);
+
+        let result = header_field_opcode_result(op, |header| *field(header) += Word::one());
+        if cfg!(feature = "enable-stack") {
+            assert!(matches!(result, Err(Error::InvalidGethExecStep(..))));
+        } else {
+            assert_eq!(result.unwrap(), expected + 1);
+        }
+    }
+
+    #[test]
+    fn number_checked_against_header() {
+        assert_header_field_checked(OpcodeId::NUMBER, |header| &mut header.number);
+    }
+
+    #[test]
+    fn timestamp_checked_against_header() {
+        assert_header_field_checked(OpcodeId::TIMESTAMP, |header| &mut header.timestamp);
+    }
+
+    #[test]
+    fn basefee_checked_against_header() {
+        assert_header_field_checked(OpcodeId::BASEFEE, |header| &mut header.base_fee);
+    }
+
+    #[test]
+    fn gaslimit_checked_against_header() {
+        let mut gas_limit = 0;
+        let result =
+            header_field_opcode_result(OpcodeId::GASLIMIT, |header| gas_limit = header.gas_limit);
+        assert_eq!(result.unwrap(), Word::from(gas_limit));
+
+        let result = header_field_opcode_result(OpcodeId::GASLIMIT, |header| header.gas_limit += 1);
+        if cfg!(feature = "enable-stack") {
+            assert!(matches!(result, Err(Error::InvalidGethExecStep(..))));
+        } else {
+            assert_eq!(result.unwrap(), Word::from(gas_limit + 1));
+        }
+    }
+
+    #[test]
+    fn coinbase_opcode_impl() {
+        let coinbase = address!("0x0000000000000000000000000000000000c0ffee");
+        assert_eq!(
+            coinbase_opcode_result(coinbase, None).unwrap(),
+            coinbase.to_word()
+        );
+    }
+
+    #[test]
+    fn coinbase_opcode_with_override() {
+        let coinbase = address!("0x0000000000000000000000000000000000c0ffee");
+        let coinbase_override = address!("0x0000000000000000000000000000000000beef00");
+        assert_eq!(
+            coinbase_opcode_result(coinbase, Some(coinbase_override)).unwrap(),
+            coinbase_override.to_word()
+        );
+    }
 }
@@ -48,3 +48,21 @@ impl Opcode for ErrorCodeStore {
         Ok(vec![exec_step])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ExecError, evm::opcodes::error_test::create_with_init_code};
+    use eth_types::{bytecode, evm_types::MAX_CODE_SIZE};
+
+    #[test]
+    fn test_max_code_size_exceeded() {
+        // init code returning one byte more than the maximum code size
+        let init_code = bytecode! {
+            PUSH2(MAX_CODE_SIZE + 1)
+            PUSH1(0)
+            RETURN
+        };
+        let return_step = create_with_init_code(init_code, ExecError::MaxCodeSizeExceeded);
+        assert_eq!(return_step.pc.0, 5);
+    }
+}
@@ -55,3 +55,24 @@ impl Opcode for ErrorCreationCode {
         Ok(vec![exec_step])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ExecError, evm::opcodes::error_test::create_with_init_code};
+    use eth_types::bytecode;
+
+    #[test]
+    fn test_invalid_creation_code() {
+        // init code returning a runtime code starting with 0xef
+        let init_code = bytecode! {
+            PUSH1(0xef)
+            PUSH1(0)
+            MSTORE8
+            PUSH1(1)
+            PUSH1(0)
+            RETURN
+        };
+        let return_step = create_with_init_code(init_code, ExecError::InvalidCreationCode);
+        assert_eq!(return_step.pc.0, 9);
+    }
+}
@@ -51,3 +51,77 @@ impl Opcode for ErrorOOGLog {
         Ok(vec![exec_step])
     }
 }
+
+#[cfg(test)]
+mod error_oog_log_tests {
+    use crate::{
+        circuit_input_builder::ExecState,
+        error::{ExecError, OogError},
+        mock::BlockData,
+        operation::{StackOp, Target, RW},
+    };
+    use eth_types::{
+        bytecode,
+        evm_types::{OpcodeId, StackAddress},
+        geth_types::GethData,
+        Word,
+    };
+    use mock::{test_ctx::helpers::account_0_code_account_1_no_code, TestContext};
+
+    #[test]
+    fn log4_out_of_gas_reads_offset_and_size() {
+        let code = bytecode! {
+            PUSH1(4) // topics
+            PUSH1(3)
+            PUSH1(2)
+            PUSH1(1)
+            PUSH1(0x20) // size
+            PUSH1(0)    // offset
+            LOG4
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            |mut txs, accs| {
+                // not enough for the 375 * 5 static gas of LOG4
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .gas(Word::from(21_100));
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.error.is_some())
+            .unwrap();
+        assert_eq!(step.exec_state, ExecState::Op(OpcodeId::LOG4));
+        assert_eq!(step.error, Some(ExecError::OutOfGas(OogError::Log)));
+        // only the offset and size are read, not the topics
+        assert_eq!(
+            [0, 1]
+                .map(|idx| &builder.block.container.stack[step.bus_mapping_instance[idx].as_usize()])
+                .map(|op| (op.rw(), op.op().clone())),
+            [
+                (RW::READ, StackOp::new(1, StackAddress(1018), Word::zero())),
+                (RW::READ, StackOp::new(1, StackAddress(1019), Word::from(0x20))),
+            ]
+        );
+        assert_eq!(
+            step.bus_mapping_instance
+                .iter()
+                .filter(|op_ref| op_ref.target() == Target::Stack)
+                .count(),
+            2
+        );
+    }
+}
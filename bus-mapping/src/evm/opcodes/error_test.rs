@@ -0,0 +1,146 @@
+//! Shared harness to test the error paths of the opcode handlers.
+
+use crate::{
+    circuit_input_builder::{ExecState, ExecStep},
+    error::ExecError,
+    mock::BlockData,
+};
+use eth_types::{bytecode, evm_types::OpcodeId, geth_types::GethData, Bytecode, Word};
+use mock::{
+    test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+    TestContext,
+};
+
+/// Build the circuit input of the first tx of `block`, assert that the first
+/// step with an error carries the `expected` error, and return that step.
+pub(crate) fn run_trace_expecting_error(block: GethData, expected: ExecError) -> ExecStep {
+    let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+    builder
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap();
+
+    let step = builder.block.txs()[0]
+        .steps()
+        .iter()
+        .find(|step| step.error.is_some())
+        .unwrap_or_else(|| panic!("no step with error, expected {expected:?}"));
+    assert_eq!(step.error, Some(expected));
+    step.clone()
+}
+
+/// Build a block with a single tx calling `code`.
+pub(crate) fn code_block(code: Bytecode) -> GethData {
+    TestContext::<2, 1>::new(
+        None,
+        account_0_code_account_1_no_code(code),
+        tx_from_1_to_0,
+        |block, _tx| block.number(0xcafeu64),
+    )
+    .unwrap()
+    .into()
+}
+
+/// Run [`run_trace_expecting_error`] on a tx calling `code`.
+pub(crate) fn run_code_expecting_error(code: Bytecode, expected: ExecError) -> ExecStep {
+    run_trace_expecting_error(code_block(code), expected)
+}
+
+/// Run a tx which CREATEs a contract with `init_code` (at most 32 bytes) and
+/// then STOPs. Assert that the RETURN of the init code fails with `expected`
+/// and that the creation frame consumes all the gas passed to it, so that the
+/// caller is only left with the 1/64 it kept. Return the RETURN step.
+pub(crate) fn create_with_init_code(init_code: Bytecode, expected: ExecError) -> ExecStep {
+    let init_code = init_code.code();
+    let code = bytecode! {
+        PUSH32(Word::from_big_endian(&init_code))
+        PUSH1(0)
+        MSTORE
+        PUSH1(init_code.len()) // size
+        PUSH1(32 - init_code.len()) // offset
+        PUSH1(0) // value
+        CREATE
+        STOP
+    };
+    let block = code_block(code);
+    let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+    builder
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap();
+    let tx = &builder.block.txs()[0];
+
+    let find_step = |exec_state| {
+        tx.steps()
+            .iter()
+            .find(|step| step.exec_state == exec_state)
+            .unwrap()
+    };
+    let return_step = find_step(ExecState::Op(OpcodeId::RETURN));
+    assert_eq!(return_step.error, Some(expected));
+    assert!(!tx.calls()[return_step.call_index].is_success);
+
+    let create_step = find_step(ExecState::Op(OpcodeId::CREATE));
+    assert_eq!(
+        find_step(ExecState::Op(OpcodeId::STOP)).gas_left.0,
+        (create_step.gas_left.0 - create_step.gas_cost.0) / 64
+    );
+    return_step.clone()
+}
+
+#[test]
+fn invalid_jump() {
+    // 0x10 is out of the code
+    let step = run_code_expecting_error(
+        bytecode! {
+            PUSH1(0x10)
+            JUMP
+            STOP
+        },
+        ExecError::InvalidJump,
+    );
+    assert_eq!(step.pc.0, 2);
+}
+
+#[test]
+fn stack_underflow() {
+    let step = run_code_expecting_error(
+        bytecode! {
+            PUSH1(1)
+            ADD
+            STOP
+        },
+        ExecError::StackUnderflow,
+    );
+    assert_eq!(step.pc.0, 2);
+}
+
+// Run `op` on a stack of `depth` items, both with the error reported by geth
+// and with the error dropped from the trace, and expect a stack underflow.
+fn assert_stack_underflow(depth: usize, op: OpcodeId) {
+    let mut code = Bytecode::default();
+    for i in 0..depth {
+        code.push(1, i);
+    }
+    code.write_op(op).write_op(OpcodeId::STOP);
+    let pc = 2 * depth;
+
+    let mut block = code_block(code);
+    let step = run_trace_expecting_error(block.clone(), ExecError::StackUnderflow);
+    assert_eq!(step.pc.0, pc);
+
+    let struct_logs = &mut block.geth_traces[0].struct_logs;
+    let op_step = struct_logs.iter_mut().find(|step| step.op == op).unwrap();
+    assert!(op_step.error.is_some());
+    op_step.error = None;
+    let step = run_trace_expecting_error(block, ExecError::StackUnderflow);
+    assert_eq!(step.pc.0, pc);
+}
+
+#[test]
+fn dup16_stack_underflow() {
+    assert_stack_underflow(15, OpcodeId::DUP16);
+}
+
+#[test]
+fn swap16_stack_underflow() {
+    assert_stack_underflow(16, OpcodeId::SWAP16);
+}
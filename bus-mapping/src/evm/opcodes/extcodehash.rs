@@ -77,7 +77,12 @@ impl Opcode for Extcodehash {
             code_hash.to_word(),
         )?;
         #[cfg(feature = "enable-stack")]
-        assert_eq!(steps[1].stack.last()?, code_hash.to_word());
+        if code_hash.to_word() != steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(steps[1].clone()),
+            ));
+        }
         // Stack write of the result of EXTCODEHASH.
         state.stack_push(&mut exec_step, code_hash.to_word())?;
 
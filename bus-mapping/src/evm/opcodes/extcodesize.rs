@@ -79,7 +79,12 @@ impl Opcode for Extcodesize {
 
         // Write the EXTCODESIZE result to stack.
         #[cfg(feature = "enable-stack")]
-        assert_eq!(code_size, geth_steps[1].stack.last()?);
+        if code_size != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, code_size)?;
 
         Ok(vec![exec_step])
@@ -278,4 +283,57 @@ mod extcodesize_tests {
             }
         );
     }
+
+    #[test]
+    fn test_extcodesize_of_precompile() {
+        use eth_types::{evm_types::GasCost, Address};
+        use mock::test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0};
+
+        let ecrecover = Address::from_low_u64_be(1);
+        let code = bytecode! {
+            PUSH1(1)
+            EXTCODESIZE
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::EXTCODESIZE))
+            .unwrap();
+        // precompiles are warm from the beginning of the tx
+        assert_eq!(step.gas_cost, GasCost::WARM_ACCESS);
+
+        let container = &builder.block.container;
+        let indices = &step.bus_mapping_instance;
+        assert_eq!(
+            container.tx_access_list_account[indices[4].as_usize()].op(),
+            &TxAccessListAccountOp {
+                tx_id: 1,
+                address: ecrecover,
+                is_warm: true,
+                is_warm_prev: true,
+            }
+        );
+        // a precompile has no code
+        assert_eq!(
+            container.account[indices[5].as_usize()].op().value,
+            Word::zero()
+        );
+        let size = &container.stack[indices.last().unwrap().as_usize()];
+        assert_eq!(size.rw(), RW::WRITE);
+        assert_eq!(size.op().value, Word::zero());
+    }
 }
@@ -32,7 +32,12 @@ impl Opcode for GasPrice {
 
         // Stack write of the gasprice value
         #[cfg(feature = "enable-stack")]
-        assert_eq!(gasprice, geth_steps[1].stack.last()?);
+        if gasprice != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, gasprice)?;
 
         Ok(vec![exec_step])
@@ -49,6 +54,8 @@ mod gasprice_tests {
         Error,
     };
     use eth_types::{bytecode, evm_types::StackAddress, geth_types::GethData, Word};
+    #[cfg(not(feature = "enable-stack"))]
+    use mock::gwei;
     use mock::{
         test_ctx::{helpers::*, TestContext},
         MOCK_WALLETS,
@@ -120,4 +127,63 @@ mod gasprice_tests {
 
         Ok(())
     }
+
+    // Run GASPRICE in an EIP-1559 tx and return the pushed value. The gas price
+    // of the tx is left at zero, so the pushed value can only be derived from
+    // the fee caps and the base fee. The mock tracer executes the tx with that
+    // zero gas price, hence these tests don't run against its stack.
+    #[cfg(not(feature = "enable-stack"))]
+    fn gasprice_of_eip1559_tx(base_fee: Word, fee_cap: Word, tip_cap: Word) -> Word {
+        let code = bytecode! {
+            GASPRICE
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .gas_price(Word::zero())
+                    .max_fee_per_gas(fee_cap)
+                    .max_priority_fee_per_gas(tip_cap)
+                    .transaction_type(2);
+            },
+            |block, _tx| block.number(0xcafeu64).base_fee_per_gas(base_fee),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let tx = &builder.block.txs()[0];
+        let step = tx
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::GASPRICE))
+            .unwrap();
+        let op_gasprice = &builder.block.container.stack[step.bus_mapping_instance[1].as_usize()];
+        assert_eq!(op_gasprice.op().value, tx.gas_price);
+        op_gasprice.op().value
+    }
+
+    #[test]
+    #[cfg(not(feature = "enable-stack"))]
+    fn gasprice_of_eip1559_tx_bound_by_tip_cap() {
+        // base_fee + tip_cap
+        let gasprice = gasprice_of_eip1559_tx(gwei(10), gwei(100), gwei(2));
+        assert_eq!(gasprice, gwei(12));
+    }
+
+    #[test]
+    #[cfg(not(feature = "enable-stack"))]
+    fn gasprice_of_eip1559_tx_bound_by_fee_cap() {
+        // base_fee + (fee_cap - base_fee)
+        let gasprice = gasprice_of_eip1559_tx(gwei(10), gwei(11), gwei(2));
+        assert_eq!(gasprice, gwei(11));
+    }
 }
@@ -0,0 +1,165 @@
+use super::Opcode;
+use crate::{
+    circuit_input_builder::{CircuitInputStateRef, ExecStep},
+    Error,
+};
+use eth_types::{evm_types::OpcodeId, Bytecode, GethExecStep, Word};
+
+/// Placeholder structure used to implement [`Opcode`] trait over it
+/// corresponding to the [`OpcodeId::JUMPI`](crate::evm::OpcodeId::JUMPI)
+/// `OpcodeId`.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Jumpi;
+
+impl Opcode for Jumpi {
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+    ) -> Result<Vec<ExecStep>, Error> {
+        let geth_step = &geth_steps[0];
+        let mut exec_step = state.new_step(geth_step)?;
+        let destination = state.stack_pop(&mut exec_step)?;
+        let condition = state.stack_pop(&mut exec_step)?;
+        #[cfg(feature = "enable-stack")]
+        {
+            assert_eq!(destination, geth_step.stack.nth_last(0)?);
+            assert_eq!(condition, geth_step.stack.nth_last(1)?);
+        }
+
+        // Cross-check the jump decision with the pc of the next step, unless
+        // the call ends right after the JUMPI.
+        if let Some(next_step) = geth_steps
+            .get(1)
+            .filter(|next_step| next_step.depth == geth_step.depth)
+        {
+            let expected_pc = if condition.is_zero() {
+                geth_step.pc.0 + 1
+            } else {
+                let code: Bytecode = state.code(state.call()?.code_hash)?.into();
+                if !is_jumpdest(&code, destination) {
+                    return Err(Error::InvalidGethExecStep(
+                        "JUMPI to a destination which is not a JUMPDEST",
+                        Box::new(geth_step.clone()),
+                    ));
+                }
+                destination.as_usize()
+            };
+            if next_step.pc.0 != expected_pc {
+                return Err(Error::InvalidGethExecStep(
+                    "pc after JUMPI differs from the jump condition",
+                    Box::new(next_step.clone()),
+                ));
+            }
+        }
+
+        Ok(vec![exec_step])
+    }
+}
+
+fn is_jumpdest(code: &Bytecode, destination: Word) -> bool {
+    destination < Word::from(code.code().len())
+        && code
+            .get(destination.as_usize())
+            .is_some_and(|element| element.is_code && element.value == OpcodeId::JUMPDEST.as_u8())
+}
+
+#[cfg(test)]
+mod jumpi_tests {
+    use super::*;
+    use crate::{
+        circuit_input_builder::ExecState,
+        error::ExecError,
+        evm::opcodes::error_test::run_code_expecting_error,
+        mock::BlockData,
+        operation::{StackOp, RW},
+    };
+    use eth_types::{bytecode, evm_types::StackAddress, geth_types::GethData};
+    use mock::test_ctx::{helpers::*, TestContext};
+    use pretty_assertions::assert_eq;
+
+    // JUMPI to `destination` if `condition`, where 6 is the only JUMPDEST.
+    fn jumpi_code(condition: u64, destination: u64) -> Bytecode {
+        bytecode! {
+            PUSH1(condition)
+            PUSH1(destination)
+            JUMPI
+            STOP
+            JUMPDEST
+            STOP
+        }
+    }
+
+    fn jumpi_block(condition: u64) -> GethData {
+        TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(jumpi_code(condition, 6)),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into()
+    }
+
+    // Handle the block, check the stack reads of the JUMPI, and return the pc
+    // of the following step.
+    fn pc_after_jumpi(block: &GethData, condition: u64) -> Result<usize, Error> {
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder.handle_block(&block.eth_block, &block.geth_traces)?;
+
+        let steps = builder.block.txs()[0].steps();
+        let idx = steps
+            .iter()
+            .position(|step| step.exec_state == ExecState::Op(OpcodeId::JUMPI))
+            .unwrap();
+        assert_eq!(
+            steps[idx]
+                .bus_mapping_instance
+                .iter()
+                .map(|op_ref| {
+                    let op = &builder.block.container.stack[op_ref.as_usize()];
+                    (op.rw(), op.op().clone())
+                })
+                .collect::<Vec<_>>(),
+            vec![
+                (RW::READ, StackOp::new(1, StackAddress(1022), Word::from(6))),
+                (
+                    RW::READ,
+                    StackOp::new(1, StackAddress(1023), Word::from(condition))
+                ),
+            ]
+        );
+        Ok(steps[idx + 1].pc.0)
+    }
+
+    #[test]
+    fn jumpi_taken() {
+        assert_eq!(pc_after_jumpi(&jumpi_block(1), 1).unwrap(), 6);
+    }
+
+    #[test]
+    fn jumpi_not_taken() {
+        assert_eq!(pc_after_jumpi(&jumpi_block(0), 0).unwrap(), 5);
+    }
+
+    #[test]
+    fn jumpi_invalid_destination() {
+        // 5 is a STOP
+        run_code_expecting_error(jumpi_code(1, 5), ExecError::InvalidJump);
+    }
+
+    #[test]
+    fn jumpi_on_zero_condition_in_trace() {
+        // a malformed trace jumping although the condition is zero
+        let mut block = jumpi_block(0);
+        let struct_logs = &mut block.geth_traces[0].struct_logs;
+        let idx = struct_logs
+            .iter()
+            .position(|step| step.op == OpcodeId::JUMPI)
+            .unwrap();
+        struct_logs[idx + 1].pc.0 = 6;
+        assert!(matches!(
+            pc_after_jumpi(&block, 0),
+            Err(Error::InvalidGethExecStep(..))
+        ));
+    }
+}
@@ -152,7 +152,7 @@ mod log_tests {
     };
     use eth_types::{
         bytecode,
-        evm_types::{OpcodeId, StackAddress},
+        evm_types::{gas_utils::memory_expansion_gas_cost, GasCost, OpcodeId, StackAddress},
         geth_types::GethData,
         Bytecode, ToWord, Word,
     };
@@ -179,6 +179,66 @@ mod log_tests {
         ]);
     }
 
+    // Run LOG<topic_count> on the first `msize` bytes of a 2 words memory and
+    // return the gas cost of the step.
+    fn log_gas_cost(topic_count: usize, msize: usize) -> u64 {
+        let log_op = OpcodeId::LOG0.as_u8() + topic_count as u8;
+        let mut code = bytecode! {
+            PUSH1(1)
+            PUSH1(0x20)
+            MSTORE
+        };
+        for topic in 0..topic_count {
+            code.push(1, topic);
+        }
+        code.push(1, msize)
+            .push(1, 0)
+            .write_op(log_op.into())
+            .op_stop();
+
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(log_op.into()))
+            .unwrap()
+            .gas_cost
+            .0
+    }
+
+    #[test]
+    fn log_gas_cost_by_topics_and_data() {
+        for topic_count in [0, 4] {
+            for msize in [0, 0x20, 0x21, 0x60] {
+                let next_memory_word_size = if msize == 0 {
+                    2
+                } else {
+                    2.max((msize + 31) / 32)
+                };
+                let expected = GasCost::LOG.0 * (1 + topic_count as u64)
+                    + 8 * msize as u64
+                    + memory_expansion_gas_cost(2, next_memory_word_size as u64);
+                assert_eq!(
+                    log_gas_cost(topic_count, msize),
+                    expected,
+                    "LOG{topic_count} of {msize} bytes"
+                );
+            }
+        }
+    }
+
     fn test_logs_opcode(topics: &[Word]) {
         let log_codes = [
             OpcodeId::LOG0,
@@ -36,7 +36,12 @@ impl Opcode for Mload {
 
         // First stack write
         #[cfg(feature = "enable-stack")]
-        assert_eq!(mem_read_value, geth_steps[1].stack.last()?);
+        if mem_read_value != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, mem_read_value)?;
 
         state.memory_read_word(&mut exec_step, slot.into())?;
@@ -136,4 +141,95 @@ mod mload_tests {
             ]
         )
     }
+
+    // MLOAD at `offset` with one word stored at 0, returning the loaded value,
+    // the gas cost of the MLOAD and the memory size after it.
+    fn mload_after_one_word(offset: u64) -> (Word, u64, usize) {
+        let code = bytecode! {
+            PUSH32(Word::MAX)
+            PUSH1(0)
+            MSTORE
+            PUSH1(offset)
+            MLOAD
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let steps = builder.block.txs()[0].steps();
+        let idx = steps
+            .iter()
+            .position(|step| step.exec_state == ExecState::Op(OpcodeId::MLOAD))
+            .unwrap();
+        let value = builder.block.container.stack[steps[idx].bus_mapping_instance[1].as_usize()]
+            .op()
+            .value;
+        (value, steps[idx].gas_cost.0, steps[idx + 1].memory_size)
+    }
+
+    #[test]
+    fn mload_within_memory() {
+        assert_eq!(mload_after_one_word(0), (Word::MAX, 3, 32));
+    }
+
+    #[test]
+    fn mload_straddling_memory_end() {
+        // the 16 bytes past the first word are zeros, and memory grows by a word
+        assert_eq!(mload_after_one_word(0x10), (Word::MAX << 128, 3 + 3, 64));
+    }
+
+    #[test]
+    fn mload_past_memory_end() {
+        // memory grows from 1 to 3 words
+        assert_eq!(mload_after_one_word(0x40), (Word::zero(), 3 + 6, 96));
+    }
+
+    #[test]
+    fn mload_without_traced_memory() {
+        use crate::Error;
+        use mock::test_ctx::LoggerConfig;
+
+        let code = bytecode! {
+            .setup_state()
+
+            PUSH1(0x40u64)
+            MLOAD
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new_with_logger_config(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+            LoggerConfig {
+                enable_memory: false,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        let result = builder.handle_block(&block.eth_block, &block.geth_traces);
+        // Without `enable-memory` the memory is rebuilt from the steps, so
+        // the trace doesn't need to carry it.
+        if cfg!(feature = "enable-memory") {
+            assert!(matches!(
+                result,
+                Err(Error::MemoryTracingDisabled(OpcodeId::MLOAD))
+            ));
+        } else {
+            assert!(result.is_ok());
+        }
+    }
 }
@@ -82,9 +82,11 @@ mod mstore_tests {
     };
     use eth_types::{
         bytecode,
-        evm_types::{MemoryAddress, OpcodeId, StackAddress},
+        evm_types::{
+            gas_utils::memory_expansion_gas_cost, GasCost, MemoryAddress, OpcodeId, StackAddress,
+        },
         geth_types::GethData,
-        Word,
+        Bytecode, Word,
     };
     use itertools::Itertools;
     use mock::test_ctx::{helpers::*, LoggerConfig, TestContext};
@@ -233,4 +235,134 @@ mod mstore_tests {
             )
         )
     }
+
+    // Run MSTORE8 of `value` at `offset` after `prefix`, and return the word
+    // written, the previous word, the gas cost, and the memory size before and
+    // after the step.
+    fn mstore8_at(prefix: Bytecode, offset: usize, value: u64) -> (MemoryOp, u64, usize, usize) {
+        let mut code = prefix;
+        code.append(&bytecode! {
+            PUSH8(value)
+            PUSH32(offset)
+            MSTORE8
+            STOP
+        });
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let steps = builder.block.txs()[0].steps();
+        let idx = steps
+            .iter()
+            .position(|step| step.exec_state == ExecState::Op(OpcodeId::MSTORE8))
+            .unwrap();
+        let step = &steps[idx];
+        // a single word is written, after the 2 stack reads
+        assert_eq!(step.bus_mapping_instance.len(), 3);
+        let memory_op = &builder.block.container.memory[step.bus_mapping_instance[2].as_usize()];
+        assert_eq!(memory_op.rw(), RW::WRITE);
+        (
+            memory_op.op().clone(),
+            step.gas_cost.0,
+            step.memory_size,
+            steps[idx + 1].memory_size,
+        )
+    }
+
+    // A word with `byte` at `shift`, and `fill` elsewhere.
+    fn word_with_byte(fill: u8, shift: usize, byte: u8) -> Word {
+        let mut bytes = [fill; 32];
+        bytes[shift] = byte;
+        Word::from_big_endian(&bytes)
+    }
+
+    #[test]
+    fn mstore8_at_offset_zero() {
+        let (op, gas_cost, size_before, size_after) = mstore8_at(Bytecode::default(), 0, 0x1234);
+        assert_eq!(
+            op,
+            MemoryOp::new_write(
+                1,
+                MemoryAddress(0),
+                word_with_byte(0, 0, 0x34),
+                Word::zero()
+            )
+        );
+        assert_eq!((size_before, size_after), (0, 32));
+        assert_eq!(
+            gas_cost,
+            GasCost::FASTEST.0 + memory_expansion_gas_cost(0, 1)
+        );
+    }
+
+    #[test]
+    fn mstore8_at_word_boundary() {
+        let (op, gas_cost, size_before, size_after) = mstore8_at(Bytecode::default(), 32, 0xab);
+        assert_eq!(
+            op,
+            MemoryOp::new_write(
+                1,
+                MemoryAddress(32),
+                word_with_byte(0, 0, 0xab),
+                Word::zero()
+            )
+        );
+        assert_eq!((size_before, size_after), (0, 64));
+        assert_eq!(
+            gas_cost,
+            GasCost::FASTEST.0 + memory_expansion_gas_cost(0, 2)
+        );
+    }
+
+    #[test]
+    fn mstore8_within_memory() {
+        // only the last byte of the first word is overwritten, and no memory
+        // is expanded
+        let prefix = bytecode! {
+            PUSH32(Word::MAX)
+            PUSH1(0)
+            MSTORE
+        };
+        let (op, gas_cost, size_before, size_after) = mstore8_at(prefix, 31, 0);
+        assert_eq!(
+            op,
+            MemoryOp::new_write(1, MemoryAddress(0), word_with_byte(0xff, 31, 0), Word::MAX)
+        );
+        assert_eq!((size_before, size_after), (32, 32));
+        assert_eq!(gas_cost, GasCost::FASTEST.0);
+    }
+
+    #[test]
+    fn mstore8_with_memory_expansion() {
+        let prefix = bytecode! {
+            PUSH32(Word::MAX)
+            PUSH1(0)
+            MSTORE
+        };
+        let (op, gas_cost, size_before, size_after) = mstore8_at(prefix, 0x45, 0x1234);
+        assert_eq!(
+            op,
+            MemoryOp::new_write(
+                1,
+                MemoryAddress(0x40),
+                word_with_byte(0, 5, 0x34),
+                Word::zero()
+            )
+        );
+        // expanded to `offset + 1` bytes, rounded up to 3 words
+        assert_eq!((size_before, size_after), (32, 96));
+        assert_eq!(
+            gas_cost,
+            GasCost::FASTEST.0 + memory_expansion_gas_cost(1, 3)
+        );
+    }
 }
@@ -30,7 +30,12 @@ impl Opcode for Origin {
 
         // Stack write of the origin address value
         #[cfg(feature = "enable-stack")]
-        assert_eq!(value, geth_steps[1].stack.last()?);
+        if value != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, value)?;
 
         Ok(vec![exec_step])
@@ -92,7 +92,10 @@ pub fn gen_ops(
             }),
         ),
         _ => {
-            log::warn!("precompile {:?} unsupported in circuits", precompile);
+            state.diagnostics.warn(
+                state.tx_ctx.id(),
+                format!("precompile {precompile:?} unsupported in circuits"),
+            );
             (
                 None,
                 Some(PrecompileAuxData::Base {
@@ -21,3 +21,67 @@ impl Opcode for Push0 {
         Ok(vec![exec_step])
     }
 }
+
+#[cfg(test)]
+mod push0_tests {
+    #[cfg(feature = "shanghai")]
+    #[test]
+    fn push0_pushes_zero() {
+        use crate::{
+            circuit_input_builder::ExecState,
+            evm::opcodes::error_test::code_block,
+            mock::BlockData,
+            operation::{StackOp, RW},
+        };
+        use eth_types::{
+            bytecode,
+            evm_types::{GasCost, OpcodeId, StackAddress},
+            Word,
+        };
+
+        let block = code_block(bytecode! {
+            PUSH0
+            STOP
+        });
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::PUSH0))
+            .unwrap();
+        assert_eq!(step.gas_cost, GasCost::QUICK);
+        assert_eq!(step.bus_mapping_instance.len(), 1);
+        let op = &builder.block.container.stack[step.bus_mapping_instance[0].as_usize()];
+        assert_eq!(
+            (op.rw(), op.op()),
+            (
+                RW::WRITE,
+                &StackOp::new(1, StackAddress(1023), Word::zero())
+            )
+        );
+    }
+
+    #[cfg(not(any(feature = "shanghai", feature = "scroll")))]
+    #[test]
+    fn push0_is_invalid_before_shanghai() {
+        use crate::{
+            circuit_input_builder::ExecState, error::ExecError,
+            evm::opcodes::error_test::run_code_expecting_error,
+        };
+        use eth_types::{bytecode, evm_types::OpcodeId};
+
+        let step = run_code_expecting_error(
+            bytecode! {
+                PUSH0
+                STOP
+            },
+            ExecError::InvalidOpcode,
+        );
+        // 0x5f is undefined before shanghai
+        assert_eq!(step.exec_state, ExecState::Op(OpcodeId::INVALID(0x5f)));
+    }
+}
@@ -481,4 +481,92 @@ mod return_tests {
             .handle_block(&block.eth_block, &block.geth_traces)
             .unwrap();
     }
+
+    #[test]
+    fn test_revert_reason_copied_by_caller() {
+        use crate::circuit_input_builder::NumberOrHash;
+        use eth_types::{ToWord, Word};
+        use mock::{
+            test_ctx::helpers::{caller_and_callee, tx_from_2_to_0},
+            MOCK_CALLEE,
+        };
+
+        let reason = b"revert reason";
+        // write storage, then revert with the reason at memory[19..32]
+        let callee_code = bytecode! {
+            PUSH1(1)
+            PUSH1(0)
+            SSTORE
+            PUSH13(Word::from_big_endian(reason))
+            PUSH1(0)
+            MSTORE
+            PUSH1(reason.len())
+            PUSH1(32 - reason.len())
+            REVERT
+        };
+        // call without a return buffer, then copy the reason from the return data
+        let caller_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH2(0xFFFF)
+            CALL
+            POP
+            PUSH1(reason.len())
+            PUSH1(0)
+            PUSH1(0)
+            RETURNDATACOPY
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, callee_code),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        // the callee's state is reverted
+        let tx = &builder.block.txs()[0];
+        let (caller_call, callee_call) = (&tx.calls()[0], &tx.calls()[1]);
+        assert!(!callee_call.is_success);
+        assert!(!callee_call.is_persistent);
+        let storage_values: Vec<_> = builder
+            .block
+            .container
+            .storage
+            .iter()
+            .filter(|op| op.rw().is_write())
+            .map(|op| op.op().value)
+            .collect();
+        assert_eq!(storage_values, vec![Word::one(), Word::zero()]);
+
+        // while the reason is still copied to the caller by RETURNDATACOPY
+        let copy_event = builder
+            .block
+            .copy_events
+            .iter()
+            .find(|event| {
+                event.src_id == NumberOrHash::Number(callee_call.call_id)
+                    && event.dst_id == NumberOrHash::Number(caller_call.call_id)
+            })
+            .unwrap();
+        let copied: Vec<u8> = copy_event
+            .copy_bytes
+            .bytes
+            .iter()
+            .filter(|(_, _, mask)| !mask)
+            .map(|(byte, _, _)| *byte)
+            .collect();
+        assert_eq!(copied, reason);
+    }
 }
@@ -31,13 +31,18 @@ impl Opcode for Returndatasize {
         {
             let real_size = geth_steps[1].stack.last()?.as_usize();
             if real_size != size {
-                log::error!(
-                    "return_data.len() != RETURNDATASIZE value, {} != {}, step: {:?}",
-                    size,
-                    real_size,
-                    geth_step
+                let tx_id = state.tx_ctx.id();
+                state.diagnostics.error(
+                    tx_id,
+                    format!(
+                        "return_data.len() != RETURNDATASIZE value, {} != {}, step: {:?}",
+                        size, real_size, geth_step
+                    ),
                 );
-                assert_eq!(real_size, size);
+                return Err(Error::InvalidGethExecStep(
+                    "result differs from the one pushed by geth",
+                    Box::new(geth_steps[1].clone()),
+                ));
             }
         }
         state.stack_push(&mut exec_step, size.into())?;
@@ -151,4 +156,65 @@ mod returndatasize_tests {
             )
         );
     }
+
+    #[test]
+    fn test_after_sha256() {
+        let code = bytecode! {
+            PUSH1(0x20) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(0x20) // argsLength
+            PUSH1(0) // argsOffset
+            PUSH1(0) // value
+            PUSH1(0x02) // address of SHA256
+            PUSH2(0xFFFF) // gas
+            CALL
+
+            RETURNDATASIZE
+
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::RETURNDATASIZE))
+            .unwrap();
+
+        // the output of SHA256 is a single word
+        let call_id = builder.block.txs()[0].calls()[0].call_id;
+        let operation =
+            &builder.block.container.call_context[step.bus_mapping_instance[0].as_usize()];
+        assert_eq!(
+            (operation.rw(), operation.op()),
+            (
+                RW::READ,
+                &CallContextOp {
+                    call_id,
+                    field: CallContextField::LastCalleeReturnDataLength,
+                    value: Word::from(0x20),
+                }
+            )
+        );
+        let operation = &builder.block.container.stack[step.bus_mapping_instance[1].as_usize()];
+        assert_eq!(
+            (operation.rw(), operation.op()),
+            (
+                RW::WRITE,
+                &StackOp::new(call_id, StackAddress::from(1022), Word::from(0x20))
+            )
+        );
+    }
 }
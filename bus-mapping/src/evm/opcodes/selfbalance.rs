@@ -19,7 +19,12 @@ impl Opcode for Selfbalance {
         let callee_address = state.call()?.address;
         let self_balance = state.sdb.get_balance(&callee_address);
         #[cfg(feature = "enable-stack")]
-        assert_eq!(self_balance, geth_steps[1].stack.last()?);
+        if self_balance != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
 
         // CallContext read of the callee_address
         state.call_context_read(
@@ -49,15 +54,20 @@ mod selfbalance_tests {
     use super::*;
     use crate::{
         circuit_input_builder::ExecState,
-        mock::BlockData,
+        mock::{stack_push_of, step_of, BlockData},
         operation::{AccountOp, CallContextField, CallContextOp, StackOp, RW},
     };
     use eth_types::{
         bytecode,
         evm_types::{OpcodeId, StackAddress},
         geth_types::GethData,
+        Word,
+    };
+    use mock::{
+        eth,
+        test_ctx::{helpers::*, TestContext},
+        MOCK_CALLEE,
     };
-    use mock::test_ctx::{helpers::*, TestContext};
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -135,4 +145,48 @@ mod selfbalance_tests {
             )
         );
     }
+
+    #[test]
+    fn selfbalance_after_value_call() {
+        let callee = *MOCK_CALLEE;
+        let (callee_balance, value) = (Word::from(1000), Word::from(234));
+        let caller_code = bytecode! {
+            PUSH1(0) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(0) // argsLength
+            PUSH1(0) // argsOffset
+            PUSH32(value)
+            PUSH20(callee.to_word())
+            PUSH2(10000) // gas
+            CALL
+            STOP
+        };
+        let callee_code = bytecode! {
+            SELFBALANCE
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee_with_balances(caller_code, eth(1), callee_code, callee_balance),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let step = step_of(&builder, OpcodeId::SELFBALANCE);
+        let balance_read =
+            &builder.block.container.account[step.bus_mapping_instance[1].as_usize()];
+        assert_eq!(balance_read.op().address, callee);
+        assert_eq!(balance_read.op().value, callee_balance + value);
+        assert_eq!(
+            stack_push_of(&builder, OpcodeId::SELFBALANCE),
+            callee_balance + value
+        );
+    }
 }
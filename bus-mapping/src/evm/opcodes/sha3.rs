@@ -51,7 +51,12 @@ impl Opcode for Sha3 {
         let sha3 = keccak256(&sha3_input);
         let _output = Word::from_big_endian(&sha3);
         #[cfg(feature = "enable-stack")]
-        assert_eq!(_output, geth_steps[1].stack.last()?);
+        if _output != geth_steps[1].stack.last()? {
+            return Err(Error::InvalidGethExecStep(
+                "result differs from the one pushed by geth",
+                Box::new(geth_steps[1].clone()),
+            ));
+        }
         state.stack_push(&mut exec_step, sha3.into())?;
 
         // Memory read operations
@@ -304,4 +309,64 @@ pub mod sha3_tests {
         test_ok(0x222, 0x111, MemoryKind::EqualToSize);
         test_ok(0x20, 0x30, MemoryKind::MoreThanSize);
     }
+
+    // Run SHA3 on a fresh memory, returning the SHA3 step's gas cost, the pushed
+    // hash and the recorded sha3 inputs.
+    fn sha3_of_fresh_memory(offset: usize, size: usize) -> (u64, Word, Vec<Vec<u8>>) {
+        let code = bytecode! {
+            PUSH32(size)
+            PUSH32(offset)
+            SHA3
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _txs| block,
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::SHA3))
+            .unwrap();
+        let hash = builder.block.container.stack[step.bus_mapping_instance[2].as_usize()]
+            .op()
+            .value;
+        (step.gas_cost.0, hash, builder.block.sha3_inputs.clone())
+    }
+
+    #[test]
+    fn sha3_empty_input() {
+        use eth_types::{evm_types::GasCost, word};
+
+        let (gas_cost, hash, sha3_inputs) = sha3_of_fresh_memory(0x40, 0);
+        assert_eq!(gas_cost, GasCost::SHA3.0);
+        assert_eq!(
+            hash,
+            word!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+        assert_eq!(sha3_inputs, vec![Vec::<u8>::new()]);
+    }
+
+    #[test]
+    fn sha3_64_bytes_with_memory_expansion() {
+        use eth_types::evm_types::{gas_utils::memory_expansion_gas_cost, GasCost};
+
+        let (gas_cost, hash, sha3_inputs) = sha3_of_fresh_memory(0, 64);
+        // 2 words hashed, and memory expanded from 0 to 2 words.
+        assert_eq!(
+            gas_cost,
+            GasCost::SHA3.0 + 2 * GasCost::COPY_SHA3.0 + memory_expansion_gas_cost(0, 2)
+        );
+        assert_eq!(hash, Word::from(keccak256([0u8; 64])));
+        assert_eq!(sha3_inputs, vec![vec![0u8; 64]]);
+    }
 }
@@ -66,7 +66,12 @@ impl Opcode for Sload {
 
             // 2. value_from_step == value_from_stack
             #[cfg(feature = "enable-stack")]
-            assert_eq!(value_from_step, geth_steps[1].stack.last()?, "inconsistent sload: step proof {value_from_step:?}, result {:?} in contract {contract_addr:?}, key {key:?}", geth_steps[1].stack.last()?);
+            if value_from_step != geth_steps[1].stack.last()? {
+                return Err(Error::InvalidGethExecStep(
+                    "result differs from the one pushed by geth",
+                    Box::new(geth_steps[1].clone()),
+                ));
+            }
         }
         let value = value_from_statedb;
 
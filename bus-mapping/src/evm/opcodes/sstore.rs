@@ -127,16 +127,20 @@ mod sstore_tests {
     use super::*;
     use crate::{
         circuit_input_builder::ExecState,
-        mock::BlockData,
-        operation::{CallContextOp, StackOp, RW},
+        error::{ExecError, OogError},
+        mock::{step_of, BlockData},
+        operation::{CallContextOp, StackOp, Target, RW},
     };
     use eth_types::{
         bytecode,
-        evm_types::{OpcodeId, StackAddress},
+        evm_types::{GasCost, OpcodeId, StackAddress},
         geth_types::GethData,
         Word,
     };
-    use mock::{test_ctx::helpers::tx_from_1_to_0, TestContext, MOCK_ACCOUNTS};
+    use mock::{
+        test_ctx::helpers::{caller_and_callee, tx_from_1_to_0, tx_from_2_to_0},
+        TestContext, MOCK_ACCOUNTS, MOCK_CALLEE, MOCK_CALLER,
+    };
     use pretty_assertions::assert_eq;
 
     fn test_ok(is_warm: bool) {
@@ -286,4 +290,112 @@ mod sstore_tests {
     fn sstore_opcode_impl_cold() {
         test_ok(false)
     }
+
+    // Call a contract storing zero into the zero slot with `gas`, and return
+    // the SSTORE step.
+    fn sstore_with_gas(gas: u64) -> ExecStep {
+        // 3 + 3 gas before SSTORE
+        let callee_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            SSTORE
+            STOP
+        };
+        let caller_code = bytecode! {
+            PUSH1(0) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(0) // argsLength
+            PUSH1(0) // argsOffset
+            PUSH1(0) // value
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH32(gas)
+            CALL
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, callee_code),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        step_of(&builder, OpcodeId::SSTORE).clone()
+    }
+
+    #[test]
+    fn sstore_sentry() {
+        // EIP-2200: SSTORE fails unless more than 2300 gas is left
+        let step = sstore_with_gas(GasCost::SSTORE_SENTRY.0 + 6);
+        assert_eq!(step.gas_left.0, GasCost::SSTORE_SENTRY.0);
+        assert_eq!(step.error, Some(ExecError::OutOfGas(OogError::SloadSstore)));
+
+        // a no-op store to a cold slot costs less than the sentry
+        let step = sstore_with_gas(GasCost::SSTORE_SENTRY.0 + 7);
+        assert_eq!(step.gas_left.0, GasCost::SSTORE_SENTRY.0 + 1);
+        assert_eq!(step.error, None);
+        assert_eq!(
+            step.gas_cost.0,
+            GasCost::COLD_SLOAD.0 + GasCost::WARM_ACCESS.0
+        );
+    }
+
+    #[test]
+    fn sstore_in_delegatecall() {
+        let (caller, library) = (*MOCK_CALLER, *MOCK_CALLEE);
+        let library_code = bytecode! {
+            PUSH1(0x2a)
+            PUSH1(1)
+            SSTORE
+            STOP
+        };
+        let caller_code = bytecode! {
+            PUSH1(0) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(0) // argsLength
+            PUSH1(0) // argsOffset
+            PUSH20(library.to_word())
+            PUSH2(30000) // gas
+            DELEGATECALL
+            STOP
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, library_code),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let op_ref = step_of(&builder, OpcodeId::SSTORE)
+            .bus_mapping_instance
+            .iter()
+            .find(|op_ref| op_ref.target() == Target::Storage)
+            .unwrap();
+        let op = builder.block.container.storage[op_ref.as_usize()].op();
+        assert_eq!(
+            (op.address, op.key, op.value),
+            (caller, Word::one(), Word::from(0x2a))
+        );
+
+        // the library code writes to the storage of the caller
+        assert_eq!(
+            builder.sdb.get_storage(&caller, &Word::one()).1,
+            &Word::from(0x2a)
+        );
+        assert_eq!(
+            builder.sdb.get_storage(&library, &Word::one()).1,
+            &Word::zero()
+        );
+    }
 }
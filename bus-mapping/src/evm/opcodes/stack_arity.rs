@@ -0,0 +1,172 @@
+use eth_types::evm_types::OpcodeId;
+
+/// Return the number of words `(pops, pushes)` an opcode takes from and puts
+/// on the stack, or `None` for an invalid opcode.
+///
+/// The pops are derived from [`OpcodeId::valid_stack_ptr_range`], whose
+/// highest stack pointer is the one with exactly as many words on the stack as
+/// the opcode pops. Every opcode pushes at most one word, and those which would
+/// overflow a full stack do push one. `DUP*` and `SWAP*` only read the deeper
+/// stack items, so they count as `(0, 1)` and `(0, 0)` respectively.
+pub fn stack_arity(op: OpcodeId) -> Option<(usize, usize)> {
+    if matches!(op, OpcodeId::INVALID(_)) {
+        return None;
+    }
+    if op.is_dup() {
+        return Some((0, 1));
+    }
+    if op.is_swap() {
+        return Some((0, 0));
+    }
+
+    let (min_stack_ptr, max_stack_ptr) = op.valid_stack_ptr_range();
+    let pops = 1024 - max_stack_ptr as usize;
+    let pushes = if min_stack_ptr > 0 {
+        1
+    } else {
+        usize::from(!has_no_output(op))
+    };
+    Some((pops, pushes))
+}
+
+/// Opcodes which pop words without pushing any result.
+fn has_no_output(op: OpcodeId) -> bool {
+    op.is_log()
+        || matches!(
+            op,
+            OpcodeId::STOP
+                | OpcodeId::JUMPDEST
+                | OpcodeId::POP
+                | OpcodeId::JUMP
+                | OpcodeId::JUMPI
+                | OpcodeId::MSTORE
+                | OpcodeId::MSTORE8
+                | OpcodeId::SSTORE
+                | OpcodeId::CALLDATACOPY
+                | OpcodeId::CODECOPY
+                | OpcodeId::EXTCODECOPY
+                | OpcodeId::RETURNDATACOPY
+                | OpcodeId::RETURN
+                | OpcodeId::REVERT
+                | OpcodeId::SELFDESTRUCT
+        )
+}
+
+#[cfg(test)]
+mod stack_arity_tests {
+    use super::*;
+    use crate::{
+        mock::{step_of, BlockData},
+        operation::{Target, RW},
+    };
+    use eth_types::{bytecode::Bytecode, geth_types::GethData, Word};
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
+
+    // Return the stack reads and writes of the step of `op` run on zeroed
+    // operands, i.e. the arity of the handler `fn_gen_associated_ops` picks.
+    fn stack_rws_of(op: OpcodeId) -> (usize, usize) {
+        let (pops, _) = stack_arity(op).unwrap();
+        let mut code = Bytecode::default();
+        if op == OpcodeId::JUMP {
+            // PUSH1 and JUMP take 3 bytes, so the JUMPDEST is at 3
+            code.push(1, 3u64);
+            code.write_op(op);
+            code.op_jumpdest();
+        } else {
+            for _ in 0..pops {
+                code.push(1, Word::zero());
+            }
+            code.write_op(op);
+        }
+        code.write_op(OpcodeId::STOP);
+
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let stack_rws: Vec<_> = step_of(&builder, op)
+            .bus_mapping_instance
+            .iter()
+            .filter(|op_ref| op_ref.target() == Target::Stack)
+            .map(|op_ref| builder.block.container.stack[op_ref.as_usize()].rw())
+            .collect();
+        let reads = stack_rws.iter().filter(|&&rw| rw == RW::READ).count();
+        (reads, stack_rws.len() - reads)
+    }
+
+    #[test]
+    fn stack_arity_matches_dispatch() {
+        // the opcodes dispatched to a handler with the pops as a const param,
+        // i.e. `ArithmeticOpcode`, `StackPopOnlyOpcode` and `CallOpcode`
+        let ops = [
+            OpcodeId::ADD,
+            OpcodeId::MUL,
+            OpcodeId::SUB,
+            OpcodeId::DIV,
+            OpcodeId::SDIV,
+            OpcodeId::MOD,
+            OpcodeId::SMOD,
+            OpcodeId::ADDMOD,
+            OpcodeId::MULMOD,
+            OpcodeId::SIGNEXTEND,
+            OpcodeId::LT,
+            OpcodeId::GT,
+            OpcodeId::SLT,
+            OpcodeId::SGT,
+            OpcodeId::EQ,
+            OpcodeId::ISZERO,
+            OpcodeId::AND,
+            OpcodeId::OR,
+            OpcodeId::XOR,
+            OpcodeId::NOT,
+            OpcodeId::BYTE,
+            OpcodeId::SHL,
+            OpcodeId::SHR,
+            OpcodeId::SAR,
+            OpcodeId::POP,
+            OpcodeId::JUMP,
+            OpcodeId::CALL,
+            OpcodeId::CALLCODE,
+            OpcodeId::DELEGATECALL,
+            OpcodeId::STATICCALL,
+        ];
+        for op in ops {
+            assert_eq!(Some(stack_rws_of(op)), stack_arity(op), "{op:?}");
+        }
+    }
+
+    #[test]
+    fn stack_arity_of_opcodes() {
+        assert_eq!(stack_arity(OpcodeId::ADD), Some((2, 1)));
+        assert_eq!(stack_arity(OpcodeId::DUP3), Some((0, 1)));
+        assert_eq!(stack_arity(OpcodeId::CALL), Some((7, 1)));
+        assert_eq!(stack_arity(OpcodeId::STATICCALL), Some((6, 1)));
+        assert_eq!(stack_arity(OpcodeId::CREATE2), Some((4, 1)));
+        assert_eq!(stack_arity(OpcodeId::LOG2), Some((4, 0)));
+        assert_eq!(stack_arity(OpcodeId::PUSH32), Some((0, 1)));
+        assert_eq!(stack_arity(OpcodeId::SSTORE), Some((2, 0)));
+        assert_eq!(stack_arity(OpcodeId::STOP), Some((0, 0)));
+    }
+
+    #[test]
+    fn stack_arity_of_invalid_opcodes() {
+        for op in OpcodeId::invalid_opcodes() {
+            assert_eq!(stack_arity(op), None, "{op:?}");
+        }
+        for op in OpcodeId::valid_opcodes() {
+            assert!(stack_arity(op).is_some(), "{op:?}");
+        }
+    }
+}
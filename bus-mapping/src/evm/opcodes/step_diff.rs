@@ -0,0 +1,163 @@
+//! Differential check of a single step against the geth trace.
+use super::{gen_associated_ops, FnGenAssociatedOps};
+use crate::{circuit_input_builder::CircuitInputStateRef, Error};
+use eth_types::{
+    evm_types::{Memory, Stack},
+    GethExecStep,
+};
+
+/// Differences between the state produced by a bus-mapping handler for one
+/// step and the state recorded by geth in the following step. Each field is
+/// `Some((bus_mapping, geth))` only when the two values differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepDiff {
+    /// Stack of the current call after the step.
+    pub stack: Option<(Stack, Stack)>,
+    /// Memory of the current call after the step.
+    pub memory: Option<(Memory, Memory)>,
+    /// Gas left after the step.
+    pub gas: Option<(u64, u64)>,
+}
+
+impl StepDiff {
+    /// Return true if no difference was found.
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_none() && self.memory.is_none() && self.gas.is_none()
+    }
+}
+
+/// Generate the step `geth_steps[0]` via [`gen_associated_ops`], and compare
+/// the resulting stack, memory and gas with the next geth step.
+///
+/// The comparison is skipped when the next step is in another call frame, and
+/// stack (memory) is only compared when the `enable-stack` (`enable-memory`)
+/// feature is on.
+pub fn diff_step_against_geth(
+    state: &mut CircuitInputStateRef,
+    geth_steps: &[GethExecStep],
+) -> Result<StepDiff, Error> {
+    diff_step_with(state, geth_steps, |state, geth_steps| {
+        gen_associated_ops(&geth_steps[0].op, state, geth_steps)
+    })
+}
+
+pub(crate) fn diff_step_with(
+    state: &mut CircuitInputStateRef,
+    geth_steps: &[GethExecStep],
+    gen_ops: FnGenAssociatedOps,
+) -> Result<StepDiff, Error> {
+    let geth_step = &geth_steps[0];
+    let exec_steps = gen_ops(state, geth_steps)?;
+
+    let mut diff = StepDiff {
+        stack: None,
+        memory: None,
+        gas: None,
+    };
+    let next_step = match geth_steps.get(1) {
+        Some(next_step) if next_step.depth == geth_step.depth => next_step,
+        _ => return Ok(diff),
+    };
+
+    #[cfg(feature = "enable-stack")]
+    if state.call_ctx()?.stack != next_step.stack {
+        diff.stack = Some((state.call_ctx()?.stack.clone(), next_step.stack.clone()));
+    }
+    #[cfg(feature = "enable-memory")]
+    if !geth_steps.iter().all(|s| s.memory.is_empty())
+        && state.call_ctx()?.memory != next_step.memory
+    {
+        diff.memory = Some((state.call_ctx()?.memory.clone(), next_step.memory.clone()));
+    }
+    // The gas cost of call and create includes the gas passed to the callee, so
+    // it can't be compared with the gas of the next step.
+    if !geth_step.op.is_call_or_create() {
+        let exec_step = &exec_steps[0];
+        let gas_left = exec_step.gas_left.0.saturating_sub(exec_step.gas_cost.0);
+        if gas_left != next_step.gas.0 {
+            diff.gas = Some((gas_left, next_step.gas.0));
+        }
+    }
+    if !diff.is_empty() {
+        let tx_id = state.tx_ctx.id();
+        state.diagnostics.error(
+            tx_id,
+            format!("step {:?} differs from geth: {:?}", geth_step.op, diff),
+        );
+    }
+
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod step_diff_tests {
+    use super::*;
+    use crate::{
+        circuit_input_builder::{ExecState, ExecStep, TransactionContext},
+        evm::{opcodes::gen_associated_steps, OpcodeId},
+        mock::BlockData,
+    };
+    use eth_types::{bytecode, evm_types::GasCost, geth_types::GethData, GethExecTrace};
+    use mock::TestContext;
+
+    // A handler for ADD which charges one more gas than expected.
+    fn wrong_add(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+    ) -> Result<Vec<ExecStep>, Error> {
+        let mut exec_step = state.new_step(&geth_steps[0])?;
+        let a = state.stack_pop(&mut exec_step)?;
+        let b = state.stack_pop(&mut exec_step)?;
+        state.stack_push(&mut exec_step, a.overflowing_add(b).0)?;
+        exec_step.gas_cost = GasCost(exec_step.gas_cost.0 + 1);
+        Ok(vec![exec_step])
+    }
+
+    // Replay the tx of a single tx block up to the first ADD, then diff the ADD
+    // step with the given handler.
+    fn diff_first_add(gen_ops: FnGenAssociatedOps) -> StepDiff {
+        let code = bytecode! {
+            PUSH1(1)
+            PUSH1(2)
+            ADD
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+            .unwrap()
+            .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        let eth_tx = &block.eth_block.transactions[0];
+        let geth_trace: &GethExecTrace = &block.geth_traces[0];
+
+        let mut tx = builder.new_tx(eth_tx, !geth_trace.failed).unwrap();
+        let mut tx_ctx = TransactionContext::new(eth_tx, geth_trace, true).unwrap();
+        let mut state = builder.state_ref(&mut tx, &mut tx_ctx);
+        gen_associated_steps(&mut state, ExecState::BeginTx).unwrap();
+
+        let struct_logs = &geth_trace.struct_logs;
+        let add_idx = struct_logs
+            .iter()
+            .position(|step| step.op == OpcodeId::ADD)
+            .unwrap();
+        for (idx, step) in struct_logs[..add_idx].iter().enumerate() {
+            gen_associated_ops(&step.op, &mut state, &struct_logs[idx..]).unwrap();
+        }
+        diff_step_with(&mut state, &struct_logs[add_idx..], gen_ops).unwrap()
+    }
+
+    #[test]
+    fn correct_handler_has_no_diff() {
+        let diff = diff_first_add(|state, geth_steps| {
+            gen_associated_ops(&geth_steps[0].op, state, geth_steps)
+        });
+        assert!(diff.is_empty(), "{diff:?}");
+    }
+
+    #[test]
+    fn wrong_handler_is_flagged() {
+        let diff = diff_first_add(wrong_add);
+        assert!(!diff.is_empty());
+        let (gas_left, geth_gas_left) = diff.gas.unwrap();
+        assert_eq!(gas_left + 1, geth_gas_left);
+    }
+}
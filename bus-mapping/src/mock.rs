@@ -1,10 +1,13 @@
 //! Mock types and functions to generate mock data useful for tests
 
 use crate::{
-    circuit_input_builder::{AccessSet, Block, BlockHead, CircuitInputBuilder, CircuitsParams},
+    circuit_input_builder::{
+        AccessSet, Block, BlockHead, CircuitInputBuilder, CircuitsParams, ExecState, ExecStep,
+    },
+    operation::{Target, RW},
     state_db::{self, CodeDB, StateDB},
 };
-use eth_types::{geth_types::GethData, ToWord, Word, H256};
+use eth_types::{evm_types::OpcodeId, geth_types::GethData, ToWord, Word, H256};
 use ethers_core::utils::keccak256;
 
 const MOCK_OLD_STATE_ROOT: u64 = 0xcafeu64;
@@ -99,6 +102,28 @@ impl BlockData {
     }
 }
 
+/// Return the first step executing `op` in the first tx of the block handled by
+/// `builder`.
+pub fn step_of(builder: &CircuitInputBuilder, op: OpcodeId) -> &ExecStep {
+    builder.block.txs()[0]
+        .steps()
+        .iter()
+        .find(|step| step.exec_state == ExecState::Op(op))
+        .unwrap_or_else(|| panic!("no {op:?} step"))
+}
+
+/// Return the value pushed on the stack by the step of [`step_of`].
+pub fn stack_push_of(builder: &CircuitInputBuilder, op: OpcodeId) -> Word {
+    step_of(builder, op)
+        .bus_mapping_instance
+        .iter()
+        .filter(|op_ref| op_ref.target() == Target::Stack)
+        .map(|op_ref| &builder.block.container.stack[op_ref.as_usize()])
+        .find(|stack_op| stack_op.rw() == RW::WRITE)
+        .map(|stack_op| *stack_op.op().value())
+        .unwrap_or_else(|| panic!("no stack push in the {op:?} step"))
+}
+
 #[cfg(test)]
 #[ctor::ctor]
 fn init_env_logger() {
@@ -4,7 +4,9 @@ use eth_types::{evm_types::GasCost, Address, ToBigEndian, Word};
 use revm_precompile::{Precompile, PrecompileError, Precompiles};
 use strum_macros::EnumIter;
 
-use crate::circuit_input_builder::{EcMulOp, EcPairingOp, N_BYTES_PER_PAIR, N_PAIRING_PER_OP};
+use crate::circuit_input_builder::{
+    Diagnostics, EcMulOp, EcPairingOp, N_BYTES_PER_PAIR, N_PAIRING_PER_OP,
+};
 
 /// Check if address is a precompiled or not.
 pub fn is_precompiled(address: &Address) -> bool {
@@ -17,6 +19,8 @@ pub(crate) fn execute_precompiled(
     address: &Address,
     input: &[u8],
     gas: u64,
+    diagnostics: &mut Diagnostics,
+    tx_id: usize,
 ) -> (Vec<u8>, u64, bool) {
     let Some(Precompile::Standard(precompile_fn)) =
         Precompiles::berlin().get(address.as_fixed_bytes())
@@ -45,7 +49,16 @@ pub(crate) fn execute_precompiled(
                         }
                     }
                     PrecompileCalls::Modexp => {
-                        let (input_valid, [_, _, modulus_len]) = ModExpAuxData::check_input(input);
+                        let (input_valid, [base_len, exp_len, modulus_len]) =
+                            ModExpAuxData::check_input(input);
+                        if !input_valid {
+                            diagnostics.warn(
+                                tx_id,
+                                format!(
+                                    "modexp input lengths {base_len} {exp_len} {modulus_len} exceed the size limit"
+                                ),
+                            );
+                        }
                         if input_valid {
                             // detect some edge cases like modulus = 0
                             assert_eq!(modulus_len.as_usize(), return_value.len());
@@ -63,7 +76,7 @@ pub(crate) fn execute_precompiled(
         Err(err) => match err {
             PrecompileError::OutOfGas => (vec![], gas, true, false),
             _ => {
-                log::warn!("unknown precompile err {err:?}");
+                diagnostics.warn(tx_id, format!("unknown precompile err {err:?}"));
                 (vec![], gas, false, false)
             }
         },
@@ -278,9 +291,6 @@ impl ModExpAuxData {
 
         let input_valid = base_len <= limit && exp_len <= limit && modulus_len <= limit;
         log::debug!("modexp base_len {base_len} exp_len {exp_len} modulus_len {modulus_len}");
-        if !input_valid {
-            log::warn!("modexp input input_valid {input_valid}");
-        }
         (input_valid, [base_len, exp_len, modulus_len])
     }
 
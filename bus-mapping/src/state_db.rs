@@ -121,6 +121,17 @@ impl Account {
     }
 }
 
+/// Source of the accounts and storage slots that are missing from the
+/// [`StateDB`], e.g. a remote node queried on demand instead of cloning all the
+/// touched accounts up front.
+pub trait StateProvider: std::fmt::Debug + Send + Sync {
+    /// Get the [`Account`] at `addr`, or `None` if the account doesn't exist.
+    fn get_account(&self, addr: &Address) -> Option<Account>;
+    /// Get the storage value of the [`Account`] at `addr`, at `key`, or `None`
+    /// if the account or `key` doesn't exist.
+    fn get_storage(&self, addr: &Address, key: &Word) -> Option<Word>;
+}
+
 /// In-memory key-value database that represents the Ethereum State Trie.
 #[derive(Debug, Clone, Default)]
 pub struct StateDB {
@@ -165,6 +176,31 @@ impl StateDB {
         }
     }
 
+    /// Make sure the [`Account`] at `addr` is in the StateDB, fetching it from
+    /// `provider` if it's missing.
+    pub fn load_account(&mut self, provider: &dyn StateProvider, addr: &Address) {
+        if self.state.contains_key(addr) {
+            return;
+        }
+        if let Some(account) = provider.get_account(addr) {
+            self.set_account(addr, account);
+        }
+    }
+
+    /// Make sure the storage value at `addr` and `key` is in the StateDB,
+    /// fetching it from `provider` if it's missing. The storage of a self
+    /// destructed account is never fetched, since it has been cleared.
+    pub fn load_storage(&mut self, provider: &dyn StateProvider, addr: &Address, key: &Word) {
+        if self.get_storage(addr, key).0 || self.destructed_account.contains(addr) {
+            return;
+        }
+        if let Some(value) = provider.get_storage(addr, key) {
+            self.load_account(provider, addr);
+            // the fetched value is committed state
+            *self.get_storage_mut(addr, key).1 = value;
+        }
+    }
+
     /// List all account addresses in current state db
     pub fn list_accounts(&self) {
         let addrs: BTreeSet<_> = self.state.keys().collect();
@@ -343,6 +379,17 @@ impl StateDB {
     }
 }
 
+/// The in-memory [`StateDB`] provides its committed state.
+impl StateProvider for StateDB {
+    fn get_account(&self, addr: &Address) -> Option<Account> {
+        self.state.get(addr).cloned()
+    }
+
+    fn get_storage(&self, addr: &Address, key: &Word) -> Option<Word> {
+        self.state.get(addr)?.storage.get(key).copied()
+    }
+}
+
 #[cfg(test)]
 mod statedb_tests {
     use super::*;
@@ -71,6 +71,13 @@ pub const MAX_EXPANDED_MEMORY_ADDRESS: u64 = 0x1FFFFFFFE0;
 pub const MAX_REFUND_QUOTIENT_OF_GAS_USED: usize = 5;
 /// Gas stipend when CALL or CALLCODE is attached with value.
 pub const GAS_STIPEND_CALL_WITH_VALUE: u64 = 2300;
+/// Maximum depth of nested calls, a call or create at a deeper depth fails
+/// with `ErrDepth`. The root call has depth 1.
+///
+/// This is the protocol limit, which geth enforces when generating the traces
+/// and the EVM circuit constrains the depth against. The circuit input builder
+/// takes it as a default that can be changed for simulations.
+pub const MAX_CALL_DEPTH: usize = 1024;
 
 #[cfg(feature = "shanghai")]
 mod gas_create {
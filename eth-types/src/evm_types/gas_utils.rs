@@ -1,6 +1,6 @@
 //! Utility functions to help calculate gas
 
-use super::GasCost;
+use super::{GasCost, OpcodeId};
 use crate::{AccessList, Word};
 
 /// Calculate memory expansion gas cost by current and next memory word size.
@@ -66,3 +66,93 @@ pub fn tx_data_gas_cost(data: &[u8]) -> u64 {
     data.iter()
         .fold(0, |acc, byte| acc + if *byte == 0 { 4 } else { 16 })
 }
+
+/// Static gas cost of `op`, i.e. the part of its cost which doesn't depend on
+/// its operands, the memory or the access list, under the EIP-2929 gas
+/// schedule when `berlin` is set and the EIP-1884 one before it.
+///
+/// Unlike [`OpcodeId::constant_gas_cost`], which leaves out what the circuits
+/// charge dynamically, SLOAD and SSTORE cost at least a warm access and LOGn
+/// includes the cost of its `n` topics. Before Berlin there are no cold
+/// accesses, so the storage and account accesses have a single flat cost. The
+/// opcodes which don't exist in the enabled fork, e.g. PUSH0 without
+/// `shanghai` or BASEFEE and SELFDESTRUCT with `scroll`, are invalid ones and
+/// cost nothing.
+pub fn base_gas_cost(op: OpcodeId, berlin: bool) -> u64 {
+    // EIP-1884 costs of SLOAD and of the opcodes accessing an account
+    const ISTANBUL_SLOAD: u64 = 800;
+    const ISTANBUL_ACCOUNT_ACCESS: u64 = 700;
+
+    match op {
+        #[cfg(not(feature = "shanghai"))]
+        OpcodeId::PUSH0 => 0,
+        #[cfg(feature = "scroll")]
+        OpcodeId::BASEFEE | OpcodeId::SELFDESTRUCT => 0,
+        OpcodeId::SLOAD | OpcodeId::SSTORE if !berlin => ISTANBUL_SLOAD,
+        OpcodeId::SLOAD | OpcodeId::SSTORE => GasCost::WARM_ACCESS.0,
+        OpcodeId::BALANCE
+        | OpcodeId::EXTCODESIZE
+        | OpcodeId::EXTCODECOPY
+        | OpcodeId::EXTCODEHASH
+        | OpcodeId::CALL
+        | OpcodeId::CALLCODE
+        | OpcodeId::DELEGATECALL
+        | OpcodeId::STATICCALL
+            if !berlin =>
+        {
+            ISTANBUL_ACCOUNT_ACCESS
+        }
+        _ if op.is_log() => {
+            let topics = (op.as_u8() - OpcodeId::LOG0.as_u8()) as u64;
+            GasCost::LOG.0 * (1 + topics)
+        }
+        _ => op.constant_gas_cost().0,
+    }
+}
+
+#[cfg(test)]
+mod gas_utils_tests {
+    use super::*;
+
+    #[test]
+    fn base_gas_costs() {
+        for berlin in [true, false] {
+            assert_eq!(base_gas_cost(OpcodeId::ADD, berlin), 3);
+            assert_eq!(base_gas_cost(OpcodeId::LOG2, berlin), 3 * 375);
+        }
+        // warm accesses; a cold access costs the difference on top
+        assert_eq!(base_gas_cost(OpcodeId::SLOAD, true), 100);
+        assert_eq!(
+            base_gas_cost(OpcodeId::SLOAD, true) + GasCost::COLD_SLOAD.0 - GasCost::WARM_ACCESS.0,
+            2100
+        );
+        assert_eq!(base_gas_cost(OpcodeId::BALANCE, true), 100);
+        assert_eq!(
+            base_gas_cost(OpcodeId::BALANCE, true) + GasCost::COLD_ACCOUNT_ACCESS.0
+                - GasCost::WARM_ACCESS.0,
+            2600
+        );
+        // no cold accesses before Berlin
+        assert_eq!(base_gas_cost(OpcodeId::SLOAD, false), 800);
+        assert_eq!(base_gas_cost(OpcodeId::BALANCE, false), 700);
+    }
+
+    #[test]
+    fn base_gas_costs_of_forks() {
+        #[cfg(feature = "shanghai")]
+        assert_eq!(base_gas_cost(OpcodeId::PUSH0, true), 2);
+        #[cfg(not(feature = "shanghai"))]
+        assert_eq!(base_gas_cost(OpcodeId::PUSH0, true), 0);
+
+        #[cfg(feature = "scroll")]
+        {
+            assert_eq!(base_gas_cost(OpcodeId::BASEFEE, true), 0);
+            assert_eq!(base_gas_cost(OpcodeId::SELFDESTRUCT, true), 0);
+        }
+        #[cfg(not(feature = "scroll"))]
+        {
+            assert_eq!(base_gas_cost(OpcodeId::BASEFEE, true), 2);
+            assert_eq!(base_gas_cost(OpcodeId::SELFDESTRUCT, true), 5000);
+        }
+    }
+}
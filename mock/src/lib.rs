@@ -46,6 +46,18 @@ pub static MOCK_ACCOUNTS: LazyLock<Vec<Address>> = LazyLock::new(|| {
         address!("0x000000000000000000000000000000000cafe555"),
     ]
 });
+/// Mock contract calling [`static@MOCK_CALLEE`] in the tests of calls between
+/// contracts.
+pub static MOCK_CALLER: LazyLock<Address> =
+    LazyLock::new(|| address!("0x0000000000000000000000000000000000000010"));
+/// Mock contract called by [`static@MOCK_CALLER`] in the tests of calls between
+/// contracts.
+pub static MOCK_CALLEE: LazyLock<Address> =
+    LazyLock::new(|| address!("0x0000000000000000000000000000000000000020"));
+/// Mock account sending the tx to [`static@MOCK_CALLER`] in the tests of calls
+/// between contracts.
+pub static MOCK_SENDER: LazyLock<Address> =
+    LazyLock::new(|| address!("0x0000000000000000000000000000000000cafe01"));
 /// Mock EVM codes to use for test cases.
 pub static MOCK_CODES: LazyLock<Vec<Bytes>> = LazyLock::new(|| {
     vec![
@@ -300,7 +300,7 @@ pub fn gen_trace_config(
 /// builder pattern used to construct [`TestContext`]s.
 pub mod helpers {
     use super::*;
-    use crate::{MOCK_ACCOUNTS, MOCK_WALLETS};
+    use crate::{MOCK_ACCOUNTS, MOCK_CALLEE, MOCK_CALLER, MOCK_SENDER, MOCK_WALLETS};
     use ethers_signers::Signer;
 
     /// Generate a simple setup which adds balance to two default accounts from
@@ -340,4 +340,42 @@ pub mod helpers {
     pub fn tx_from_1_to_0(mut txs: Vec<&mut MockTransaction>, accs: [MockAccount; 2]) {
         txs[0].from(accs[1].address).to(accs[0].address);
     }
+
+    /// Generate a setup of three accounts to test the calls between contracts:
+    /// - [`static@MOCK_CALLER`] with 1 ETH and the `caller_code`
+    /// - [`static@MOCK_CALLEE`] without balance and with the `callee_code`
+    /// - [`static@MOCK_SENDER`] with 10 ETH, sending the tx to the caller with [`tx_from_2_to_0`].
+    pub fn caller_and_callee(
+        caller_code: Bytecode,
+        callee_code: Bytecode,
+    ) -> impl FnOnce([&mut MockAccount; 3]) {
+        caller_and_callee_with_balances(caller_code, eth(1), callee_code, Word::zero())
+    }
+
+    /// Same as [`caller_and_callee`], with the given balances of the caller and
+    /// the callee.
+    pub fn caller_and_callee_with_balances(
+        caller_code: Bytecode,
+        caller_balance: Word,
+        callee_code: Bytecode,
+        callee_balance: Word,
+    ) -> impl FnOnce([&mut MockAccount; 3]) {
+        move |accs| {
+            accs[0]
+                .address(*MOCK_CALLER)
+                .balance(caller_balance)
+                .code(caller_code);
+            accs[1]
+                .address(*MOCK_CALLEE)
+                .balance(callee_balance)
+                .code(callee_code);
+            accs[2].address(*MOCK_SENDER).balance(eth(10));
+        }
+    }
+
+    /// Generate a single transaction from the third account of the list to the
+    /// first one.
+    pub fn tx_from_2_to_0(mut txs: Vec<&mut MockTransaction>, accs: [MockAccount; 3]) {
+        txs[0].from(accs[2].address).to(accs[0].address);
+    }
 }
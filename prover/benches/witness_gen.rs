@@ -0,0 +1,76 @@
+//! Witness generation benchmarks.
+//!
+//! Measure `block_traces_to_witness_block` on the checked-in trace at
+//! `FIXTURE_PATH`, and on a block traced from the mock state whose txs hash,
+//! store and load words in a contract.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use eth_types::{bytecode, l2_types::BlockTrace};
+use mock::{eth, TestContext, MOCK_ACCOUNTS};
+use prover::{
+    utils::{get_block_trace_from_file, read_env_var},
+    zkevm::circuit::block_traces_to_witness_block,
+};
+
+const FIXTURE_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/benches/traces/empty_block.json"
+);
+
+fn mock_block_trace() -> BlockTrace {
+    let chain_id = read_env_var("CHAIN_ID", 53077);
+    let code = bytecode! {
+        PUSH1(0x20)
+        PUSH1(0x00)
+        SHA3
+        DUP1
+        PUSH1(0x00)
+        SSTORE
+        PUSH1(0x00)
+        SLOAD
+        EQ
+        PUSH1(0x00)
+        MSTORE
+        STOP
+    };
+
+    TestContext::<2, 4>::new(
+        None,
+        |accs| {
+            accs[0].address(MOCK_ACCOUNTS[0]).code(code);
+            accs[1].address(MOCK_ACCOUNTS[1]).balance(eth(10));
+        },
+        |txs, accs| {
+            for tx in txs {
+                tx.from(accs[1].address)
+                    .to(accs[0].address)
+                    .chain_id(chain_id);
+            }
+        },
+        |block, _tx| block.number(0xcafeu64).chain_id(chain_id),
+    )
+    .unwrap()
+    .l2_trace()
+    .clone()
+}
+
+fn bench_witness_gen(c: &mut Criterion) {
+    let fixture_trace = get_block_trace_from_file(FIXTURE_PATH);
+    let mock_trace = mock_block_trace();
+
+    let mut group = c.benchmark_group("block_traces_to_witness_block");
+    group.bench_function("fixture", |b| {
+        b.iter(|| block_traces_to_witness_block(vec![fixture_trace.clone()]).unwrap())
+    });
+    group.bench_function("mock_block", |b| {
+        b.iter(|| block_traces_to_witness_block(vec![mock_trace.clone()]).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_witness_gen
+}
+criterion_main!(benches);
@@ -3,7 +3,7 @@ use crate::{
     config::INNER_DEGREE,
     io::serialize_vk,
     utils::{chunk_trace_to_witness_block, gen_rng},
-    zkevm::circuit::TargetCircuit,
+    zkevm::circuit::{CircuitCapacity, TargetCircuit},
     Proof,
 };
 use anyhow::Result;
@@ -47,7 +47,8 @@ impl<C: TargetCircuit> Prover<C> {
                 assert!(!block_traces.is_empty());
 
                 let rng = gen_rng();
-                let witness_block = chunk_trace_to_witness_block(block_traces)?;
+                let witness_block =
+                    chunk_trace_to_witness_block(block_traces, &CircuitCapacity::default())?;
                 let result = self
                     .inner
                     .gen_inner_snark::<C>(id, rng, &witness_block)
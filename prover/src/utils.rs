@@ -1,14 +1,21 @@
 use crate::{
     types::BlockTraceJsonRpcResult,
-    zkevm::circuit::{block_traces_to_witness_block, check_batch_capacity},
+    zkevm::circuit::{
+        block_traces_to_witness_block_with_progress, check_batch_capacity, CircuitCapacity,
+    },
 };
 use anyhow::{bail, Result};
+use bus_mapping::circuit_input_builder::CircuitInputBuilder;
 use chrono::Utc;
 use eth_types::{l2_types::BlockTrace, Address};
+use ethers_providers::{Http, JsonRpcClient};
 use git_version::git_version;
 use halo2_proofs::{
-    halo2curves::bn256::{Bn256, Fr},
-    poly::kzg::commitment::ParamsKZG,
+    halo2curves::{
+        bn256::{Bn256, Fr},
+        pairing::Engine,
+    },
+    poly::{commitment::ParamsProver, kzg::commitment::ParamsKZG},
     SerdeFormat,
 };
 use log::LevelFilter;
@@ -22,11 +29,14 @@ use log4rs::{
 use rand::{Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
 use std::{
+    fmt,
     fs::{self, metadata, File},
     io::{BufReader, Read},
     path::{Path, PathBuf},
     str::FromStr,
     sync::Once,
+    thread,
+    time::Duration,
 };
 use zkevm_circuits::evm_circuit::witness::Block;
 
@@ -38,6 +48,9 @@ pub const GIT_VERSION: &str = git_version!(args = ["--abbrev=7", "--always"]);
 pub const PARAMS_G2_SECRET_POWER: &str = "(Fq2 { c0: 0x17944351223333f260ddc3b4af45191b856689eda9eab5cbcddbbe570ce860d2, c1: 0x186282957db913abd99f91db59fe69922e95040603ef44c0bd7aa3adeef8f5ac }, Fq2 { c0: 0x297772d34bc9aa8ae56162486363ffe417b02dc7e8c207fc2cc20203e67a02ad, c1: 0x298adc7396bd3865cbf6d6df91bae406694e6d2215baa893bdeadb63052895f4 })";
 
 /// Load setup params from a file.
+///
+/// The `s_g2` of the params (debug formatted) must be `PARAMS_G2_SECRET_POWER`,
+/// or the `PARAMS_G2_SECRET` env var when it's set.
 pub fn load_params(
     params_dir: &str,
     degree: u32,
@@ -77,14 +90,139 @@ pub fn load_params(
     }
 
     let p = ParamsKZG::<Bn256>::read_custom::<_>(&mut BufReader::new(f), serde_fmt)?;
-    if format!("{:?}", p.s_g2()) != PARAMS_G2_SECRET_POWER {
+    let expected_s_g2 = read_env_var("PARAMS_G2_SECRET", PARAMS_G2_SECRET_POWER.to_string());
+    if format!("{:?}", p.s_g2()) != expected_s_g2 {
         bail!("Wrong params file of degree {}", degree);
     }
+    if read_env_var("CHECK_PARAMS_CONSISTENCY", false) {
+        verify_params_consistency(&p)?;
+    }
 
     log::info!("load params successfully!");
     Ok(p)
 }
 
+/// Retry policy of a fallible load, e.g. [`get_block_trace_from_rpc`]. Local
+/// loads like [`load_params`] don't retry.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled before each following one.
+    pub backoff: Duration,
+    /// Upper bound of the delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Error of a load whose attempts all failed, in the order of the attempts.
+#[derive(Debug)]
+pub struct RetryError {
+    pub attempts: Vec<anyhow::Error>,
+}
+
+impl fmt::Display for RetryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed after {} attempts", self.attempts.len())?;
+        for (idx, err) in self.attempts.iter().enumerate() {
+            write!(f, "; attempt {}: {err}", idx + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RetryError {}
+
+impl RetryPolicy {
+    /// Call `load` until it succeeds, at most `max_attempts` times.
+    pub fn run<T>(&self, mut load: impl FnMut() -> Result<T>) -> Result<T, RetryError> {
+        let mut attempts = vec![];
+        let mut backoff = self.backoff;
+        while attempts.len() < self.max_attempts.max(1) as usize {
+            if !attempts.is_empty() {
+                thread::sleep(backoff);
+                backoff = backoff.saturating_mul(2).min(self.max_backoff);
+            }
+            match load() {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    log::warn!("attempt {} failed: {err}", attempts.len() + 1);
+                    attempts.push(err);
+                }
+            }
+        }
+        Err(RetryError { attempts })
+    }
+}
+
+/// Fetch the trace of block `number` from the l2geth RPC endpoint at `url`,
+/// retrying the failed requests according to `policy`.
+pub fn get_block_trace_from_rpc(
+    url: &str,
+    number: u64,
+    policy: &RetryPolicy,
+) -> Result<BlockTrace> {
+    let client = Http::from_str(url)?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let number = format!("{number:#x}");
+    let block_trace = policy.run(|| -> Result<BlockTrace> {
+        let block_trace =
+            runtime.block_on(client.request("scroll_getBlockTraceByNumberOrHash", [&number]))?;
+        Ok(block_trace)
+    })?;
+    Ok(block_trace)
+}
+
+/// Generate unsafe setup params of each degree in `degrees` and write them to
+/// `out_dir` in the layout and format read by `load_params`, for tests which
+/// can't download the real params. Their secret differs from the real one, so
+/// `load_params` only accepts them with `PARAMS_G2_SECRET` set to the secret
+/// of `ParamsKZG::setup(degree, gen_rng())`.
+pub fn gen_test_params(degrees: &[u32], out_dir: &str) -> Result<()> {
+    fs::create_dir_all(out_dir)?;
+    for &degree in degrees {
+        let params = ParamsKZG::<Bn256>::setup(degree, gen_rng());
+        let mut f = File::create(param_path_for_degree(out_dir, degree))?;
+        params.write_custom(&mut f, DEFAULT_SERDE_FORMAT)?;
+    }
+    Ok(())
+}
+
+/// Check that the g1 points of the params are successive powers of the secret,
+/// i.e. `e([s^(i+1)]G1, [1]G2) == e([s^i]G1, [s]G2)`, on a few indices.
+pub fn verify_params_consistency(params: &ParamsKZG<Bn256>) -> Result<()> {
+    let g = params.get_g();
+    let n = g.len();
+    if n < 2 {
+        bail!("params with {n} g1 points are too small to check");
+    }
+
+    let mut indices = vec![0, 1, n / 2, n - 2];
+    indices.dedup();
+    for i in indices.into_iter().filter(|i| i + 1 < n) {
+        if Bn256::pairing(&g[i + 1], &params.g2()) != Bn256::pairing(&g[i], &params.s_g2()) {
+            bail!(
+                "inconsistent params: g1 points {} and {} are not successive powers",
+                i,
+                i + 1
+            );
+        }
+    }
+
+    Ok(())
+}
+
 /// get a block-result from file
 pub fn get_block_trace_from_file<P: AsRef<Path>>(path: P) -> BlockTrace {
     let mut buffer = Vec::new();
@@ -109,28 +247,34 @@ pub fn get_block_trace_from_file<P: AsRef<Path>>(path: P) -> BlockTrace {
         Address::from_str("0x5300000000000000000000000000000000000000").unwrap(),
         Address::from_str("0x5300000000000000000000000000000000000002").unwrap(),
     ];
+    apply_intrinsic_storage_proofs(&mut trace, &addrs);
+
+    trace
+}
+
+/// Copy the account and storage proofs of `addrs` from the storage trace of the
+/// block into the storage trace of each of its txs, as done when loading a
+/// block trace from file. The proofs missing from the block are skipped.
+pub fn apply_intrinsic_storage_proofs(trace: &mut BlockTrace, addrs: &[Address]) {
     for tx_storage_trace in &mut trace.tx_storage_trace {
-        if let Some(proof) = tx_storage_trace.proofs.as_mut() {
-            for addr in &addrs {
-                proof.insert(
-                    *addr,
-                    trace
-                        .storage_trace
-                        .proofs
-                        .as_ref()
-                        .map(|p| p[addr].clone())
-                        .unwrap(),
-                );
+        for addr in addrs {
+            if let (Some(proof), Some(block_proof)) = (
+                tx_storage_trace.proofs.as_mut(),
+                trace
+                    .storage_trace
+                    .proofs
+                    .as_ref()
+                    .and_then(|p| p.get(addr)),
+            ) {
+                proof.insert(*addr, block_proof.clone());
+            }
+            if let Some(storage_proof) = trace.storage_trace.storage_proofs.get(addr) {
+                tx_storage_trace
+                    .storage_proofs
+                    .insert(*addr, storage_proof.clone());
             }
-        }
-        for addr in &addrs {
-            tx_storage_trace
-                .storage_proofs
-                .insert(*addr, trace.storage_trace.storage_proofs[addr].clone());
         }
     }
-
-    trace
 }
 
 pub fn read_env_var<T: Clone + FromStr>(var_name: &'static str, default: T) -> T {
@@ -139,6 +283,24 @@ pub fn read_env_var<T: Clone + FromStr>(var_name: &'static str, default: T) -> T
         .unwrap_or(default)
 }
 
+/// Read the boolean flag `var_name`, which is false when the var isn't set.
+///
+/// # Panics
+///
+/// If the var is set to anything else than `1`, `true`, `yes`, `0`, `false`,
+/// `no` or an empty string (case-insensitive).
+pub fn read_env_flag(var_name: &'static str) -> bool {
+    std::env::var(var_name).is_ok_and(|value| parse_env_flag(var_name, &value))
+}
+
+fn parse_env_flag(var_name: &str, value: &str) -> bool {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" => true,
+        "" | "0" | "false" | "no" => false,
+        _ => panic!("invalid value {value:?} of {var_name}, expected 1/true/yes or 0/false/no"),
+    }
+}
+
 #[derive(Debug)]
 pub struct BatchMetric {
     pub num_block: usize,
@@ -154,47 +316,61 @@ pub fn metric_of_witness_block(block: &Block<Fr>) -> BatchMetric {
     }
 }
 
-pub fn chunk_trace_to_witness_block(mut chunk_trace: Vec<BlockTrace>) -> Result<Block<Fr>> {
+fn metric_of_builder(builder: &CircuitInputBuilder) -> BatchMetric {
+    BatchMetric {
+        num_block: builder.block.headers.len(),
+        num_tx: builder.block.txs.len(),
+        num_step: builder
+            .block
+            .txs
+            .iter()
+            .map(|tx| tx.steps().len())
+            .sum::<usize>(),
+    }
+}
+
+/// Build the witness block of a chunk, after truncating the blocks which don't
+/// fit `capacity`.
+pub fn chunk_trace_to_witness_block(
+    chunk_trace: Vec<BlockTrace>,
+    capacity: &CircuitCapacity,
+) -> Result<Block<Fr>> {
+    chunk_trace_to_witness_block_with_progress(chunk_trace, capacity, |_, _| {})
+}
+
+/// Same as [`chunk_trace_to_witness_block`], but `progress` is called with the
+/// block index and the running metric after each block is processed.
+pub fn chunk_trace_to_witness_block_with_progress(
+    mut chunk_trace: Vec<BlockTrace>,
+    capacity: &CircuitCapacity,
+    mut progress: impl FnMut(usize, &BatchMetric),
+) -> Result<Block<Fr>> {
     if chunk_trace.is_empty() {
         bail!("Empty chunk trace");
     }
 
     // Check if the trace exceeds the circuit capacity.
-    check_batch_capacity(&mut chunk_trace)?;
+    check_batch_capacity(&mut chunk_trace, capacity)?;
 
-    block_traces_to_witness_block(chunk_trace)
+    block_traces_to_witness_block_with_progress(chunk_trace, |idx, builder| {
+        progress(idx, &metric_of_builder(builder))
+    })
 }
 
 // Return the output dir.
 pub fn init_env_and_log(id: &str) -> String {
+    init_env_and_log_with_dir(id, None)
+}
+
+/// Same as [`init_env_and_log`], but use `dir` as the output dir when it's set,
+/// instead of the one derived from `MODE`, `OUTPUT_DIR` and the current time
+/// (unless `DETERMINISTIC_OUTPUT` is set).
+pub fn init_env_and_log_with_dir(id: &str, dir: Option<PathBuf>) -> String {
     dotenvy::dotenv().ok();
-    let output_dir = create_output_dir(id);
+    let output_dir = resolve_output_dir(id, dir);
 
     LOGGER.call_once(|| {
-        // TODO: cannot support complicated `RUST_LOG` for now.
-        let log_level = read_env_var("RUST_LOG", "INFO".to_string());
-        let log_level = LevelFilter::from_str(&log_level).unwrap_or(LevelFilter::Info);
-
-        let mut log_file_path = PathBuf::from(output_dir.clone());
-        log_file_path.push("log.txt");
-        let log_file = FileAppender::builder().build(log_file_path).unwrap();
-
-        let stderr = ConsoleAppender::builder().target(Target::Stderr).build();
-
-        let config = Config::builder()
-            .appenders([
-                Appender::builder().build("log-file", Box::new(log_file)),
-                Appender::builder().build("stderr", Box::new(stderr)),
-            ])
-            .build(
-                Root::builder()
-                    .appender("log-file")
-                    .appender("stderr")
-                    .build(log_level),
-            )
-            .unwrap();
-
-        log4rs::init_config(config).unwrap();
+        log4rs::init_config(log_config(&output_dir)).unwrap();
 
         log::info!("git version {}", GIT_VERSION);
         log::info!("short git version {}", short_git_version());
@@ -203,17 +379,48 @@ pub fn init_env_and_log(id: &str) -> String {
     output_dir
 }
 
+// Return `dir` when it's set, otherwise the one derived from the env vars,
+// after creating it.
+fn resolve_output_dir(id: &str, dir: Option<PathBuf>) -> String {
+    match dir {
+        Some(dir) => {
+            fs::create_dir_all(&dir).unwrap();
+            dir.to_string_lossy().into_owned()
+        }
+        None => create_output_dir(id),
+    }
+}
+
+// Log to stderr and to `log.txt` in `output_dir`.
+fn log_config(output_dir: &str) -> Config {
+    // TODO: cannot support complicated `RUST_LOG` for now.
+    let log_level = read_env_var("RUST_LOG", "INFO".to_string());
+    let log_level = LevelFilter::from_str(&log_level).unwrap_or(LevelFilter::Info);
+
+    let mut log_file_path = PathBuf::from(output_dir);
+    log_file_path.push("log.txt");
+    let log_file = FileAppender::builder().build(log_file_path).unwrap();
+
+    let stderr = ConsoleAppender::builder().target(Target::Stderr).build();
+
+    Config::builder()
+        .appenders([
+            Appender::builder().build("log-file", Box::new(log_file)),
+            Appender::builder().build("stderr", Box::new(stderr)),
+        ])
+        .build(
+            Root::builder()
+                .appender("log-file")
+                .appender("stderr")
+                .build(log_level),
+        )
+        .unwrap()
+}
+
 fn create_output_dir(id: &str) -> String {
     let mode = read_env_var("MODE", "multi".to_string());
-    let output = read_env_var(
-        "OUTPUT_DIR",
-        format!(
-            "{}_output_{}_{}",
-            id,
-            mode,
-            Utc::now().format("%Y%m%d_%H%M%S")
-        ),
-    );
+    let deterministic = read_env_flag("DETERMINISTIC_OUTPUT");
+    let output = read_env_var("OUTPUT_DIR", output_dir_name(id, &mode, deterministic));
 
     let output_dir = PathBuf::from_str(&output).unwrap();
     fs::create_dir_all(output_dir).unwrap();
@@ -221,6 +428,21 @@ fn create_output_dir(id: &str) -> String {
     output
 }
 
+// Default name of the output dir, which is reproducible across runs when
+// `deterministic` is set, i.e. without the current time.
+fn output_dir_name(id: &str, mode: &str, deterministic: bool) -> String {
+    if deterministic {
+        format!("{id}_{mode}")
+    } else {
+        format!(
+            "{}_output_{}_{}",
+            id,
+            mode,
+            Utc::now().format("%Y%m%d_%H%M%S")
+        )
+    }
+}
+
 pub fn param_path_for_degree(params_dir: &str, degree: u32) -> String {
     format!("{params_dir}/params{degree}")
 }
@@ -240,3 +462,270 @@ pub fn short_git_version() -> String {
         commit_version[1..8].to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth_types::{l2_types::StorageTrace, Bytes, Word};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_verify_params_consistency() {
+        let params = ParamsKZG::<Bn256>::setup(4, gen_rng());
+        verify_params_consistency(&params).unwrap();
+
+        // swap the g1 points [s]G1 and [s^2]G1
+        let mut buf = vec![];
+        params
+            .write_custom(&mut buf, SerdeFormat::RawBytesUnchecked)
+            .unwrap();
+        let (s1, s2) = (4 + 64, 4 + 2 * 64);
+        let point = buf[s1..s2].to_vec();
+        buf.copy_within(s2..s2 + 64, s1);
+        buf[s2..s2 + 64].copy_from_slice(&point);
+        let corrupted =
+            ParamsKZG::<Bn256>::read_custom(&mut &buf[..], SerdeFormat::RawBytesUnchecked).unwrap();
+        assert!(verify_params_consistency(&corrupted).is_err());
+    }
+
+    #[test]
+    fn test_gen_test_params() {
+        let dir = std::env::temp_dir().join(format!("prover_params_{}", std::process::id()));
+        let params_dir = dir.to_str().unwrap();
+        gen_test_params(&[5], params_dir).unwrap();
+
+        // the real secret check rejects them
+        assert!(load_params(params_dir, 5, None).is_err());
+
+        let secret = format!("{:?}", ParamsKZG::<Bn256>::setup(5, gen_rng()).s_g2());
+        std::env::set_var("PARAMS_G2_SECRET", secret);
+        let params = load_params(params_dir, 5, None).unwrap();
+        assert_eq!(params.get_g().len(), 1 << 5);
+        // no params of other degrees
+        assert!(load_params(params_dir, 6, None).is_err());
+        std::env::remove_var("PARAMS_G2_SECRET");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_retry_policy_with_flaky_server() {
+        use std::{
+            io::{BufRead, Write},
+            net::TcpListener,
+        };
+
+        // The server closes the first two connections without any response,
+        // and answers the third request with a block trace.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            for attempt in 1..=3 {
+                let (stream, _) = listener.accept().unwrap();
+                if attempt < 3 {
+                    continue;
+                }
+
+                let mut reader = BufReader::new(stream);
+                let mut content_len = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(len) = line.strip_prefix("content-length:") {
+                        content_len = len.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_len];
+                reader.read_exact(&mut body).unwrap();
+                let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                assert_eq!(request["method"], "scroll_getBlockTraceByNumberOrHash");
+                assert_eq!(request["params"], serde_json::json!(["0xcafe"]));
+
+                let response = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": BlockTrace {
+                        chain_id: 53077,
+                        ..Default::default()
+                    },
+                })
+                .to_string();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                     content-length: {}\r\nconnection: close\r\n\r\n{response}",
+                    response.len()
+                )
+                .unwrap();
+            }
+        });
+
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        };
+        let block_trace = get_block_trace_from_rpc(&url, 0xcafe, &policy).unwrap();
+        assert_eq!(block_trace.chain_id, 53077);
+        server.join().unwrap();
+
+        let err = RetryPolicy {
+            max_attempts: 2,
+            ..policy
+        }
+        .run(|| -> Result<()> { bail!("unreachable") })
+        .unwrap_err();
+        assert_eq!(err.attempts.len(), 2);
+        assert_eq!(
+            err.to_string(),
+            "failed after 2 attempts; attempt 1: unreachable; attempt 2: unreachable"
+        );
+    }
+
+    #[test]
+    fn test_init_env_and_log_with_dir() {
+        let dir = std::env::temp_dir().join(format!("prover_log_{}", std::process::id()));
+        let output_dir = resolve_output_dir("test", Some(dir.clone()));
+        assert_eq!(PathBuf::from(&output_dir), dir);
+        assert!(dir.is_dir());
+
+        // the log file is created in the dir, whether or not the global
+        // logger was already initialized by another test
+        log_config(&output_dir);
+        assert!(dir.join("log.txt").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_env_flag() {
+        for value in ["1", "true", "TRUE", "yes", "Yes"] {
+            assert!(parse_env_flag("DETERMINISTIC_OUTPUT", value), "{value}");
+        }
+        for value in ["", "0", "false", "False", "no"] {
+            assert!(!parse_env_flag("DETERMINISTIC_OUTPUT", value), "{value}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value \"on\" of DETERMINISTIC_OUTPUT")]
+    fn test_parse_env_flag_of_invalid_value() {
+        parse_env_flag("DETERMINISTIC_OUTPUT", "on");
+    }
+
+    #[test]
+    fn test_deterministic_output_dir_name() {
+        assert_eq!(output_dir_name("test", "multi", true), "test_multi");
+
+        let name = output_dir_name("test", "multi", false);
+        let timestamp = name.strip_prefix("test_output_multi_").unwrap();
+        assert_eq!(timestamp.len(), "YYYYmmdd_HHMMSS".len());
+    }
+
+    #[test]
+    fn test_apply_intrinsic_storage_proofs() {
+        let addr = Address::from_str("0x5300000000000000000000000000000000000000").unwrap();
+        let account_proof = vec![Bytes::from(vec![0x01, 0x02])];
+        let storage_proof = HashMap::from([(Word::one(), vec![Bytes::from(vec![0x03])])]);
+
+        let mut trace = BlockTrace::default();
+        trace.storage_trace.proofs = Some(HashMap::from([(addr, account_proof.clone())]));
+        trace
+            .storage_trace
+            .storage_proofs
+            .insert(addr, storage_proof.clone());
+        trace.tx_storage_trace = vec![
+            StorageTrace {
+                proofs: Some(HashMap::new()),
+                ..Default::default()
+            },
+            // a tx without account proofs only gets the storage proofs
+            StorageTrace::default(),
+        ];
+
+        apply_intrinsic_storage_proofs(&mut trace, &[addr]);
+        let [with_proofs, without_proofs] = &trace.tx_storage_trace[..] else {
+            panic!("unexpected number of tx storage traces");
+        };
+        assert_eq!(with_proofs.proofs.as_ref().unwrap()[&addr], account_proof);
+        assert_eq!(with_proofs.storage_proofs[&addr], storage_proof);
+        assert!(without_proofs.proofs.is_none());
+        assert_eq!(without_proofs.storage_proofs[&addr], storage_proof);
+    }
+
+    #[test]
+    fn test_apply_intrinsic_storage_proofs_of_absent_address() {
+        let addr = Address::from_str("0x5300000000000000000000000000000000000000").unwrap();
+        let mut trace = BlockTrace::default();
+        trace.storage_trace.proofs = Some(HashMap::new());
+        trace.tx_storage_trace = vec![StorageTrace {
+            proofs: Some(HashMap::new()),
+            ..Default::default()
+        }];
+
+        apply_intrinsic_storage_proofs(&mut trace, &[addr]);
+        let tx_storage_trace = &trace.tx_storage_trace[0];
+        assert!(tx_storage_trace.proofs.as_ref().unwrap().is_empty());
+        assert!(tx_storage_trace.storage_proofs.is_empty());
+    }
+
+    // Trace a chunk of one transfer per block. The blocks are traced from the
+    // same genesis, and each one only touches its own sender and receiver
+    // besides the coinbase, so their state follows from the previous blocks.
+    #[cfg(feature = "scroll")]
+    fn chunk_of_transfers() -> Vec<BlockTrace> {
+        use crate::zkevm::circuit::CHAIN_ID;
+        use mock::{eth, TestContext, MOCK_ACCOUNTS};
+
+        let receiver = |i: usize| Address::from_low_u64_be(0x1000 + i as u64);
+        (0..3)
+            .map(|i| {
+                TestContext::<6, 1>::new(
+                    None,
+                    |accs| {
+                        for (j, sender) in MOCK_ACCOUNTS.iter().take(3).enumerate() {
+                            accs[2 * j].address(*sender).balance(eth(10));
+                            accs[2 * j + 1].address(receiver(j)).balance(eth(1));
+                        }
+                    },
+                    |mut txs, _accs| {
+                        txs[0]
+                            .from(MOCK_ACCOUNTS[i])
+                            .to(receiver(i))
+                            .value(eth(1))
+                            .chain_id(*CHAIN_ID);
+                    },
+                    |block, _tx| block.number(0xcafe + i as u64).chain_id(*CHAIN_ID),
+                )
+                .unwrap()
+                .l2_trace()
+                .clone()
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "scroll")]
+    #[test]
+    fn test_chunk_trace_to_witness_block_with_progress() {
+        let chunk_trace = chunk_of_transfers();
+        let num_block = chunk_trace.len();
+
+        let mut reports = vec![];
+        let witness_block = chunk_trace_to_witness_block_with_progress(
+            chunk_trace,
+            &CircuitCapacity::default(),
+            |idx, metric| reports.push((idx, metric.num_block, metric.num_step)),
+        )
+        .unwrap();
+
+        assert_eq!(reports.len(), num_block);
+        for (i, (idx, num_block, _)) in reports.iter().enumerate() {
+            assert_eq!((*idx, *num_block), (i, i + 1));
+        }
+        assert!(reports.windows(2).all(|pair| pair[0].2 < pair[1].2));
+        let last_num_step = reports.last().unwrap().2;
+        assert!(last_num_step <= metric_of_witness_block(&witness_block).num_step);
+    }
+}
@@ -1,3 +1,4 @@
+use bus_mapping::circuit_input_builder::CircuitsParams;
 use eth_types::l2_types::BlockTrace;
 use halo2_proofs::halo2curves::bn256::Fr;
 use snark_verifier_sdk::CircuitExt;
@@ -7,6 +8,8 @@ use zkevm_circuits::witness;
 mod l2_builder;
 #[cfg(feature = "scroll")]
 use l2_builder as builder;
+#[cfg(all(test, feature = "scroll"))]
+pub(crate) use l2_builder::CHAIN_ID;
 #[cfg(not(feature = "scroll"))]
 mod l1_builder;
 #[cfg(not(feature = "scroll"))]
@@ -14,9 +17,10 @@ use l1_builder as builder;
 mod super_circuit;
 pub use self::builder::{
     block_trace_to_witness_block, block_traces_to_witness_block,
-    block_traces_to_witness_block_with_updated_state, calculate_row_usage_of_trace,
-    calculate_row_usage_of_witness_block, check_batch_capacity, get_super_circuit_params,
-    validite_block_traces,
+    block_traces_to_witness_block_with_progress, block_traces_to_witness_block_with_updated_state,
+    calculate_row_usage_of_trace, calculate_row_usage_of_witness_block, check_batch_capacity,
+    check_batch_capacity_with_reserved_rows, get_super_circuit_params, min_degree_for_block,
+    partition_traces_by_capacity, validite_block_traces,
 };
 pub use super_circuit::SuperCircuit;
 
@@ -32,10 +36,69 @@ pub const MAX_KECCAK_ROWS: usize = 1_000_000;
 pub const MAX_POSEIDON_ROWS: usize = 1_000_000;
 pub const MAX_VERTICAL_ROWS: usize = 1_000_000;
 pub const MAX_RWS: usize = 1_000_000;
+pub const MAX_EVM_ROWS: usize = MAX_RWS;
+pub const MAX_COPY_ROWS: usize = MAX_RWS;
 pub const MAX_PRECOMPILE_EC_ADD: usize = 50;
 pub const MAX_PRECOMPILE_EC_MUL: usize = 50;
 pub const MAX_PRECOMPILE_EC_PAIRING: usize = 2;
 
+/// Per subcircuit limits checked by `check_batch_capacity`, so that the same code can
+/// target proving keys of different degrees. Each limit is the number of rows its
+/// subcircuit is sized with by the `CircuitsParams` of the super circuit, beyond
+/// which the witness can't be assigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitCapacity {
+    /// Rows of the state circuit, `max_rws`.
+    pub max_rws: usize,
+    /// Rows of the evm circuit, `max_evm_rows`.
+    pub max_steps: usize,
+    /// Rows of the keccak circuit, `max_keccak_rows`.
+    pub max_keccak_rows: usize,
+    /// Rows of the copy circuit, `max_copy_rows`.
+    pub max_copy_rows: usize,
+    /// Rows of the bytecode circuit, i.e. bytes of the bytecodes plus their headers,
+    /// `max_bytecode`.
+    pub max_bytecode_bytes: usize,
+}
+
+impl Default for CircuitCapacity {
+    /// The capacity of the super circuit of degree 20.
+    fn default() -> Self {
+        Self {
+            max_rws: MAX_RWS,
+            max_steps: MAX_EVM_ROWS,
+            max_keccak_rows: MAX_KECCAK_ROWS,
+            max_copy_rows: MAX_COPY_ROWS,
+            max_bytecode_bytes: MAX_BYTECODE,
+        }
+    }
+}
+
+impl CircuitCapacity {
+    /// Capacity of a super circuit built with `params`.
+    pub fn from_params(params: &CircuitsParams) -> Self {
+        Self {
+            max_rws: params.max_rws,
+            max_steps: params.max_evm_rows,
+            max_keccak_rows: params.max_keccak_rows,
+            max_copy_rows: params.max_copy_rows,
+            max_bytecode_bytes: params.max_bytecode,
+        }
+    }
+
+    /// Limit of the subcircuit named `name` in the row usage, if there's any.
+    pub fn limit_of(&self, name: &str) -> Option<usize> {
+        match name {
+            "evm" => Some(self.max_steps),
+            "state" => Some(self.max_rws),
+            "bytecode" => Some(self.max_bytecode_bytes),
+            "copy" => Some(self.max_copy_rows),
+            "keccak" => Some(self.max_keccak_rows),
+            _ => None,
+        }
+    }
+}
+
 /// A target circuit trait is a wrapper of inner circuit, with convenient APIs for building
 /// circuits from traces.
 pub trait TargetCircuit {
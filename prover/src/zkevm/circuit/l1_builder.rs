@@ -1,3 +1,4 @@
+use super::CircuitCapacity;
 use anyhow::Result;
 use bus_mapping::circuit_input_builder::{CircuitInputBuilder, CircuitsParams};
 use eth_types::l2_types::BlockTrace;
@@ -24,8 +25,34 @@ pub fn calculate_row_usage_of_witness_block(
     unimplemented!("Must build with feature scroll")
 }
 
+pub fn min_degree_for_block(
+    _witness_block: &Block<Fr>,
+    _capacity: &CircuitCapacity,
+) -> Result<u32> {
+    unimplemented!("Must build with feature scroll")
+}
+
 #[allow(clippy::ptr_arg)]
-pub fn check_batch_capacity(_block_traces: &mut Vec<BlockTrace>) -> Result<()> {
+pub fn check_batch_capacity(
+    _block_traces: &mut Vec<BlockTrace>,
+    _capacity: &CircuitCapacity,
+) -> Result<()> {
+    unimplemented!("Must build with feature scroll")
+}
+
+#[allow(clippy::ptr_arg)]
+pub fn check_batch_capacity_with_reserved_rows(
+    _block_traces: &mut Vec<BlockTrace>,
+    _capacity: &CircuitCapacity,
+    _reserved_rows: usize,
+) -> Result<()> {
+    unimplemented!("Must build with feature scroll")
+}
+
+pub fn partition_traces_by_capacity(
+    _block_traces: Vec<BlockTrace>,
+    _capacity: &CircuitCapacity,
+) -> Result<Vec<Vec<BlockTrace>>> {
     unimplemented!("Must build with feature scroll")
 }
 
@@ -37,6 +64,13 @@ pub fn block_traces_to_witness_block(_block_traces: Vec<BlockTrace>) -> Result<B
     unimplemented!("Must build with feature scroll")
 }
 
+pub fn block_traces_to_witness_block_with_progress(
+    _block_traces: Vec<BlockTrace>,
+    _progress: impl FnMut(usize, &CircuitInputBuilder),
+) -> Result<Block<Fr>> {
+    unimplemented!("Must build with feature scroll")
+}
+
 pub fn block_traces_to_witness_block_with_updated_state(
     _block_traces: Vec<BlockTrace>,
     _builder: &mut CircuitInputBuilder,
@@ -1,4 +1,4 @@
-use super::TargetCircuit;
+use super::{CircuitCapacity, TargetCircuit};
 use crate::{config::INNER_DEGREE, utils::read_env_var};
 use anyhow::{bail, Result};
 use bus_mapping::{
@@ -16,7 +16,7 @@ use zkevm_circuits::{
     witness::block_convert,
 };
 
-static CHAIN_ID: LazyLock<u64> = LazyLock::new(|| read_env_var("CHAIN_ID", 53077));
+pub(crate) static CHAIN_ID: LazyLock<u64> = LazyLock::new(|| read_env_var("CHAIN_ID", 53077));
 static AUTO_TRUNCATE: LazyLock<bool> = LazyLock::new(|| read_env_var("AUTO_TRUNCATE", false));
 
 ////// params for degree = 20 ////////////
@@ -31,6 +31,8 @@ pub const MAX_KECCAK_ROWS: usize = 1_000_000;
 pub const MAX_POSEIDON_ROWS: usize = 1_000_000;
 pub const MAX_VERTICAL_ROWS: usize = 1_000_000;
 pub const MAX_RWS: usize = 1_000_000;
+pub const MAX_EVM_ROWS: usize = MAX_RWS;
+pub const MAX_COPY_ROWS: usize = MAX_RWS;
 pub const MAX_PRECOMPILE_EC_ADD: usize = 50;
 pub const MAX_PRECOMPILE_EC_MUL: usize = 50;
 pub const MAX_PRECOMPILE_EC_PAIRING: usize = 2;
@@ -38,9 +40,9 @@ pub const MAX_PRECOMPILE_EC_PAIRING: usize = 2;
 /// default params for super circuit
 pub fn get_super_circuit_params() -> CircuitsParams {
     CircuitsParams {
-        max_evm_rows: MAX_RWS,
+        max_evm_rows: MAX_EVM_ROWS,
         max_rws: MAX_RWS,
-        max_copy_rows: MAX_RWS,
+        max_copy_rows: MAX_COPY_ROWS,
         max_txs: MAX_TXS,
         max_calldata: MAX_CALLDATA,
         max_bytecode: MAX_BYTECODE,
@@ -103,9 +105,82 @@ pub fn calculate_row_usage_of_witness_block(
     Ok(rows)
 }
 
+/// Rows kept free at the end of the inner circuit for the blinding factors and
+/// other unusable rows.
+pub const DEFAULT_RESERVED_ROWS: usize = 256;
+
+// Whether `rows` fits the inner circuit once `reserved_rows` are kept free.
+// Fails if more rows are reserved than the inner circuit has.
+fn is_within_row_limit(rows: usize, reserved_rows: usize) -> Result<bool> {
+    let Some(limit) = (1usize << *INNER_DEGREE).checked_sub(reserved_rows) else {
+        bail!(
+            "reserved rows {} exceed the {} rows of the inner circuit",
+            reserved_rows,
+            1usize << *INNER_DEGREE
+        );
+    };
+    Ok(rows <= limit)
+}
+
+// The first subcircuit in `usage` whose rows exceed its limit in `capacity`.
+fn exceeded_subcircuit<'a>(
+    usage: &'a [crate::zkevm::SubCircuitRowUsage],
+    capacity: &CircuitCapacity,
+) -> Option<&'a crate::zkevm::SubCircuitRowUsage> {
+    usage.iter().find(|x| {
+        capacity
+            .limit_of(&x.name)
+            .map_or(false, |limit| x.row_number > limit)
+    })
+}
+
+/// Smallest degree of the inner circuit fitting the row usage of `witness_block`, once
+/// `DEFAULT_RESERVED_ROWS` are kept free. Fails if a subcircuit exceeds its limit in
+/// `capacity`.
+pub fn min_degree_for_block(witness_block: &Block<Fr>, capacity: &CircuitCapacity) -> Result<u32> {
+    let usage = calculate_row_usage_of_witness_block(witness_block)?
+        .into_iter()
+        .map(|x| crate::zkevm::SubCircuitRowUsage {
+            name: x.name,
+            row_number: x.row_num_real,
+        })
+        .collect_vec();
+    min_degree_for_row_usage(&usage, capacity, DEFAULT_RESERVED_ROWS)
+}
+
+// Smallest `k` such that every subcircuit in `usage` fits `2^k - reserved_rows` rows.
+fn min_degree_for_row_usage(
+    usage: &[crate::zkevm::SubCircuitRowUsage],
+    capacity: &CircuitCapacity,
+    reserved_rows: usize,
+) -> Result<u32> {
+    if let Some(exceeded) = exceeded_subcircuit(usage, capacity) {
+        bail!(
+            "subcircuit {} exceeds its capacity, rows {}",
+            exceeded.name,
+            exceeded.row_number
+        );
+    }
+    let rows = itertools::max(usage.iter().map(|x| x.row_number)).unwrap_or_default();
+    Ok((rows + reserved_rows).next_power_of_two().trailing_zeros())
+}
+
 // FIXME: we need better API name for this.
 // This function also mutates the block trace.
-pub fn check_batch_capacity(block_traces: &mut Vec<BlockTrace>) -> Result<()> {
+pub fn check_batch_capacity(
+    block_traces: &mut Vec<BlockTrace>,
+    capacity: &CircuitCapacity,
+) -> Result<()> {
+    check_batch_capacity_with_reserved_rows(block_traces, capacity, DEFAULT_RESERVED_ROWS)
+}
+
+/// Same as `check_batch_capacity`, but with `reserved_rows` subtracted from the
+/// available capacity instead of `DEFAULT_RESERVED_ROWS`.
+pub fn check_batch_capacity_with_reserved_rows(
+    block_traces: &mut Vec<BlockTrace>,
+    capacity: &CircuitCapacity,
+    reserved_rows: usize,
+) -> Result<()> {
     let block_traces_len = block_traces.len();
     let total_tx_count = block_traces
         .iter()
@@ -159,13 +234,15 @@ pub fn check_batch_capacity(block_traces: &mut Vec<BlockTrace>) -> Result<()> {
             usage
         );
         n_txs += block.transactions.len();
-        if rows > (1 << *INNER_DEGREE) - 256 || n_txs > MAX_TXS {
+        let exceeded = exceeded_subcircuit(&acc, capacity);
+        if !is_within_row_limit(rows, reserved_rows)? || n_txs > MAX_TXS || exceeded.is_some() {
             log::warn!(
-                "truncate blocks [{}..{}), n_txs {}, rows {}",
+                "truncate blocks [{}..{}), n_txs {}, rows {}, exceeded subcircuit {:?}",
                 idx,
                 block_traces_len,
                 n_txs,
-                rows
+                rows,
+                exceeded.map(|x| &x.name)
             );
             truncate_idx = idx;
             break;
@@ -184,6 +261,100 @@ pub fn check_batch_capacity(block_traces: &mut Vec<BlockTrace>) -> Result<()> {
     Ok(())
 }
 
+/// Greedily group block traces into chunks so that each chunk fits the circuit capacity,
+/// i.e. the limits of `capacity` and the rows of the inner circuit. Unlike
+/// `check_batch_capacity`, no block is dropped: once a block would overflow the current
+/// chunk, a new chunk is started with it.
+pub fn partition_traces_by_capacity(
+    block_traces: Vec<BlockTrace>,
+    capacity: &CircuitCapacity,
+) -> Result<Vec<Vec<BlockTrace>>> {
+    let t = Instant::now();
+    let usages = block_traces
+        .iter()
+        .map(|block| {
+            let usage = calculate_row_usage_of_trace(block.clone())?
+                .into_iter()
+                .map(|x| crate::zkevm::SubCircuitRowUsage {
+                    name: x.name,
+                    row_number: x.row_num_real,
+                })
+                .collect_vec();
+            Ok((usage, block.transactions.len()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let chunk_lens = partition_by_row_usage(
+        &usages,
+        (1 << *INNER_DEGREE) - DEFAULT_RESERVED_ROWS,
+        capacity,
+    )?;
+    log::debug!(
+        "partition_traces_by_capacity takes {:?}, {} blocks into {} chunks",
+        t.elapsed(),
+        block_traces.len(),
+        chunk_lens.len()
+    );
+
+    let mut traces = block_traces.into_iter();
+    Ok(chunk_lens
+        .into_iter()
+        .map(|len| traces.by_ref().take(len).collect())
+        .collect())
+}
+
+// Returns the number of blocks in each chunk, given the per-subcircuit row usage and
+// the tx count of every block.
+fn partition_by_row_usage(
+    usages: &[(Vec<crate::zkevm::SubCircuitRowUsage>, usize)],
+    row_limit: usize,
+    capacity: &CircuitCapacity,
+) -> Result<Vec<usize>> {
+    let fits = |usage: &[crate::zkevm::SubCircuitRowUsage], n_txs: usize| {
+        itertools::max(usage.iter().map(|x| x.row_number)).unwrap_or_default() <= row_limit
+            && exceeded_subcircuit(usage, capacity).is_none()
+            && n_txs <= MAX_TXS
+    };
+
+    let mut chunk_lens = vec![];
+    let mut acc: Vec<crate::zkevm::SubCircuitRowUsage> = Vec::new();
+    let mut n_txs = 0;
+    let mut n_blocks = 0;
+    for (idx, (usage, block_n_txs)) in usages.iter().enumerate() {
+        if !fits(usage, *block_n_txs) {
+            bail!(
+                "block {idx} alone exceeds circuit capacity, n_txs {block_n_txs}, rows {:?}",
+                itertools::max(usage.iter().map(|x| x.row_number))
+            );
+        }
+        let merged = if acc.is_empty() {
+            usage.clone()
+        } else {
+            acc.iter()
+                .zip_eq(usage.iter())
+                .map(|(a, b)| crate::zkevm::SubCircuitRowUsage {
+                    name: a.name.clone(),
+                    row_number: a.row_number + b.row_number,
+                })
+                .collect()
+        };
+        if n_blocks < MAX_INNER_BLOCKS && fits(&merged, n_txs + block_n_txs) {
+            acc = merged;
+            n_txs += block_n_txs;
+            n_blocks += 1;
+        } else {
+            log::debug!("start new chunk at block {idx}, previous chunk has {n_blocks} blocks");
+            chunk_lens.push(n_blocks);
+            acc = usage.clone();
+            n_txs = *block_n_txs;
+            n_blocks = 1;
+        }
+    }
+    if n_blocks > 0 {
+        chunk_lens.push(n_blocks);
+    }
+    Ok(chunk_lens)
+}
+
 // prepare an empty builder which can updated by more trace
 // from the default settings
 // only require the prev state root being provided
@@ -254,6 +425,15 @@ pub fn block_trace_to_witness_block(block_trace: BlockTrace) -> Result<Block<Fr>
 }
 
 pub fn block_traces_to_witness_block(block_traces: Vec<BlockTrace>) -> Result<Block<Fr>> {
+    block_traces_to_witness_block_with_progress(block_traces, |_, _| {})
+}
+
+/// Same as [`block_traces_to_witness_block`], but `progress` is called with the
+/// block index and the builder after each block is added.
+pub fn block_traces_to_witness_block_with_progress(
+    block_traces: Vec<BlockTrace>,
+    mut progress: impl FnMut(usize, &CircuitInputBuilder),
+) -> Result<Block<Fr>> {
     validite_block_traces(&block_traces)?;
     let block_num = block_traces.len();
     let total_tx_num = block_traces
@@ -291,9 +471,11 @@ pub fn block_traces_to_witness_block(block_traces: Vec<BlockTrace>) -> Result<Bl
             block_traces_len > 1,
             false,
         )?;
-        let witness = block_traces_to_witness_block_with_updated_state(
+        progress(0, &builder);
+        let witness = add_traces_and_finalize(
             traces.collect(), // this is a cold path
             &mut builder,
+            |idx, builder| progress(idx + 1, builder),
         );
         // send to other thread to drop
         std::thread::spawn(move || drop(builder.block));
@@ -308,6 +490,14 @@ pub fn block_traces_to_witness_block(block_traces: Vec<BlockTrace>) -> Result<Bl
 pub fn block_traces_to_witness_block_with_updated_state(
     block_traces: Vec<BlockTrace>,
     builder: &mut CircuitInputBuilder,
+) -> Result<Block<Fr>> {
+    add_traces_and_finalize(block_traces, builder, |_, _| {})
+}
+
+fn add_traces_and_finalize(
+    block_traces: Vec<BlockTrace>,
+    builder: &mut CircuitInputBuilder,
+    mut on_block: impl FnMut(usize, &CircuitInputBuilder),
 ) -> Result<Block<Fr>> {
     let metric = |builder: &CircuitInputBuilder, idx: usize| -> Result<(), bus_mapping::Error> {
         let t = Instant::now();
@@ -350,6 +540,7 @@ pub fn block_traces_to_witness_block_with_updated_state(
             block_trace.header.number
         );
         builder.add_more_l2_trace(block_trace, !is_last)?;
+        on_block(idx, builder);
         if per_block_metric {
             metric(builder, idx + initial_blk_index)?;
         }
@@ -386,3 +577,209 @@ pub fn block_traces_to_witness_block_with_updated_state(
 
     Ok(witness_block)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The row usage of the evm and state circuits, with the tx count of the block.
+    fn evm_and_state_usage(
+        (rows, n_txs): ([usize; 2], usize),
+    ) -> (Vec<crate::zkevm::SubCircuitRowUsage>, usize) {
+        let usage = ["evm", "state"]
+            .into_iter()
+            .zip(rows)
+            .map(|(name, row_number)| crate::zkevm::SubCircuitRowUsage {
+                name: name.to_string(),
+                row_number,
+            })
+            .collect();
+        (usage, n_txs)
+    }
+
+    #[test]
+    fn test_partition_by_row_usage() {
+        let capacity = CircuitCapacity::default();
+        let usages = [
+            ([400, 100], 10),
+            ([300, 500], 20),
+            ([200, 300], MAX_TXS - 29),
+            ([900, 100], 1),
+            ([100, 800], 2),
+        ]
+        .map(evm_and_state_usage);
+        let chunk_lens = partition_by_row_usage(&usages, 1000, &capacity).unwrap();
+        assert_eq!(chunk_lens, vec![2, 1, 2]);
+        assert_eq!(chunk_lens.iter().sum::<usize>(), usages.len());
+
+        let too_large = [([100, 100], 1), ([1001, 0], 1)].map(evm_and_state_usage);
+        let err = partition_by_row_usage(&too_large, 1000, &capacity).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("block 1 alone exceeds circuit capacity"));
+    }
+
+    #[test]
+    fn test_partition_by_row_usage_with_custom_capacity() {
+        let usages = [([400, 100], 1), ([300, 500], 1), ([200, 300], 1)].map(evm_and_state_usage);
+        // under the row limit, but over the state circuit limit
+        let capacity = CircuitCapacity {
+            max_rws: 700,
+            ..Default::default()
+        };
+        let chunk_lens = partition_by_row_usage(&usages, 1000, &capacity).unwrap();
+        assert_eq!(chunk_lens, vec![2, 1]);
+
+        let capacity = CircuitCapacity {
+            max_rws: 400,
+            ..Default::default()
+        };
+        assert!(partition_by_row_usage(&usages, 1000, &capacity).is_err());
+    }
+
+    #[test]
+    fn test_default_capacity_is_the_super_circuit_one() {
+        assert_eq!(
+            CircuitCapacity::default(),
+            CircuitCapacity::from_params(&get_super_circuit_params())
+        );
+    }
+
+    #[test]
+    fn test_custom_capacity() {
+        let usage = [
+            ("evm", 300_000),
+            ("state", 500_000),
+            ("bytecode", 10_000),
+            ("copy", 20_000),
+            ("keccak", 40_000),
+            // no limit in the capacity
+            ("poseidon", 2_000_000),
+        ]
+        .map(|(name, row_number)| crate::zkevm::SubCircuitRowUsage {
+            name: name.to_string(),
+            row_number,
+        });
+        assert!(exceeded_subcircuit(&usage, &CircuitCapacity::default()).is_none());
+
+        // e.g. a proving key of a smaller degree
+        let small = CircuitCapacity {
+            max_rws: 1 << 18,
+            max_steps: 1 << 18,
+            ..Default::default()
+        };
+        let exceeded = exceeded_subcircuit(&usage, &small).unwrap();
+        assert_eq!(
+            (exceeded.name.as_str(), exceeded.row_number),
+            ("evm", 300_000)
+        );
+    }
+
+    #[test]
+    fn test_min_degree_for_row_usage() {
+        let usage_of = |rows: [usize; 3]| {
+            ["evm", "state", "poseidon"]
+                .into_iter()
+                .zip(rows)
+                .map(|(name, row_number)| crate::zkevm::SubCircuitRowUsage {
+                    name: name.to_string(),
+                    row_number,
+                })
+                .collect_vec()
+        };
+        let capacity = CircuitCapacity::default();
+
+        // a small block
+        let small = usage_of([1000, 3000, 500]);
+        assert_eq!(
+            min_degree_for_row_usage(&small, &capacity, 256).unwrap(),
+            12
+        );
+        // a larger one, bounded by the subcircuit with the most rows
+        let large = usage_of([200_000, 100_000, 600_000]);
+        assert_eq!(
+            min_degree_for_row_usage(&large, &capacity, 256).unwrap(),
+            20
+        );
+
+        // the reserved rows are taken into account
+        let full = usage_of([(1 << 12) - 256, 0, 0]);
+        assert_eq!(min_degree_for_row_usage(&full, &capacity, 256).unwrap(), 12);
+        assert_eq!(min_degree_for_row_usage(&full, &capacity, 257).unwrap(), 13);
+
+        let over_capacity = usage_of([MAX_RWS + 1, 0, 0]);
+        assert!(min_degree_for_row_usage(&over_capacity, &capacity, 256).is_err());
+    }
+
+    // The witness block of a block whose only tx calls a contract running `code`.
+    fn witness_block_running(code: eth_types::Bytecode) -> Block<Fr> {
+        use mock::{eth, TestContext, MOCK_ACCOUNTS};
+
+        let block_trace = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0].address(MOCK_ACCOUNTS[0]).code(code);
+                accs[1].address(MOCK_ACCOUNTS[1]).balance(eth(10));
+            },
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .chain_id(*CHAIN_ID);
+            },
+            |block, _tx| block.number(0xcafeu64).chain_id(*CHAIN_ID),
+        )
+        .unwrap()
+        .l2_trace()
+        .clone();
+        block_trace_to_witness_block(block_trace).unwrap()
+    }
+
+    #[test]
+    fn test_min_degree_for_block() {
+        let small = witness_block_running(eth_types::bytecode! { STOP });
+        // count down from 5000 in a loop
+        let large = witness_block_running(eth_types::bytecode! {
+            PUSH2(5000)
+            JUMPDEST
+            PUSH1(1)
+            SWAP1
+            SUB
+            DUP1
+            PUSH1(3)
+            JUMPI
+            STOP
+        });
+
+        let capacity = CircuitCapacity::default();
+        let small_degree = min_degree_for_block(&small, &capacity).unwrap();
+        let large_degree = min_degree_for_block(&large, &capacity).unwrap();
+        assert!(small_degree < large_degree);
+
+        // the rows of the block fit the degree but not the one below
+        for (block, degree) in [(&small, small_degree), (&large, large_degree)] {
+            let rows = calculate_row_usage_of_witness_block(block)
+                .unwrap()
+                .into_iter()
+                .map(|x| x.row_num_real)
+                .max()
+                .unwrap()
+                + DEFAULT_RESERVED_ROWS;
+            assert!(rows <= 1 << degree);
+            assert!(rows > 1 << (degree - 1));
+        }
+    }
+
+    #[test]
+    fn test_reserved_rows() {
+        let raw_limit = 1 << *INNER_DEGREE;
+        assert!(
+            is_within_row_limit(raw_limit - DEFAULT_RESERVED_ROWS, DEFAULT_RESERVED_ROWS).unwrap()
+        );
+        // just under the raw limit, but over the reserved one
+        assert!(is_within_row_limit(raw_limit - 1, 0).unwrap());
+        assert!(!is_within_row_limit(raw_limit - 1, DEFAULT_RESERVED_ROWS).unwrap());
+        // more rows reserved than the circuit has
+        assert!(is_within_row_limit(0, raw_limit + 1).is_err());
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     consts::CHUNK_VK_FILENAME,
     io::try_to_read,
     utils::chunk_trace_to_witness_block,
+    zkevm::circuit::CircuitCapacity,
     ChunkProof,
 };
 use aggregator::ChunkHash;
@@ -48,7 +49,7 @@ impl Prover {
     ) -> Result<ChunkProof> {
         assert!(!chunk_trace.is_empty());
 
-        let witness_block = chunk_trace_to_witness_block(chunk_trace)?;
+        let witness_block = chunk_trace_to_witness_block(chunk_trace, &CircuitCapacity::default())?;
         log::info!("Got witness block");
 
         let name = name.map_or_else(
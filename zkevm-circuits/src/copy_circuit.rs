@@ -1106,13 +1106,7 @@ impl<F: Field> SubCircuit<F> for CopyCircuit<F> {
 
     /// Return the minimum number of rows required to prove the block
     fn min_num_rows_block(block: &witness::Block<F>) -> (usize, usize) {
-        let row_num = block
-            .copy_events
-            .iter()
-            .map(|c| c.full_length() as usize * 2)
-            .sum::<usize>()
-            + UNUSED_ROWS
-            + DISABLED_ROWS;
+        let row_num = copy_circuit_rows(block);
         (row_num, row_num.max(block.circuits_params.max_copy_rows))
     }
 
@@ -1127,6 +1121,19 @@ impl<F: Field> SubCircuit<F> for CopyCircuit<F> {
     }
 }
 
+/// Number of rows of the copy circuit used by the copy events of `block`, i.e.
+/// two rows (a read and a write) per byte of each event, plus the rows kept
+/// unused at the end of the circuit.
+pub fn copy_circuit_rows<F: Field>(block: &witness::Block<F>) -> usize {
+    block
+        .copy_events
+        .iter()
+        .map(|c| c.full_length() as usize * 2)
+        .sum::<usize>()
+        + UNUSED_ROWS
+        + DISABLED_ROWS
+}
+
 fn unwrap_value<F: Field>(value: Value<F>) -> F {
     let mut f = F::zero();
     value.map(|v| f = v);
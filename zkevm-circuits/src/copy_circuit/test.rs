@@ -534,6 +534,44 @@ fn copy_circuit_precompile_call() {
     assert_eq!(test_copy_circuit_from_block(block), Ok(()));
 }
 
+#[test]
+fn copy_circuit_rows_of_calldatacopy() {
+    let rows_of_calldatacopy = |length: usize| {
+        let code = bytecode! {
+            PUSH32(Word::from(length))
+            PUSH32(Word::from(0x00))
+            PUSH32(Word::from(0x00))
+            CALLDATACOPY
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .input(rand_bytes(length).into());
+            },
+            |block, _txs| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+        assert_eq!(block.copy_events.len(), 1);
+        copy_circuit_rows(&block)
+    };
+
+    // two rows per copied byte, the copy being word aligned
+    let (short, long) = (rows_of_calldatacopy(0x100), rows_of_calldatacopy(0x1000));
+    assert!(short > 2 * 0x100);
+    assert_eq!(long - short, 2 * (0x1000 - 0x100));
+}
+
 #[test]
 fn variadic_size_check() {
     let builder = gen_tx_log_data();
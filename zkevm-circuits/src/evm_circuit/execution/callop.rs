@@ -29,7 +29,7 @@ use bus_mapping::{
     precompile::{is_precompiled, PrecompileCalls},
 };
 use eth_types::{
-    evm_types::{memory::MemoryWordRange, GAS_STIPEND_CALL_WITH_VALUE},
+    evm_types::{memory::MemoryWordRange, GAS_STIPEND_CALL_WITH_VALUE, MAX_CALL_DEPTH},
     Field, ToAddress, ToBigEndian, ToLittleEndian, ToScalar, U256,
 };
 use halo2_proofs::{circuit::Value, plonk::Error};
@@ -197,8 +197,8 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
         // callee_reversion_info.rw_delta()
         let is_insufficient_balance =
             LtWordGadget::construct(cb, &caller_balance_word, &call_gadget.value);
-        // depth < 1025
-        let is_depth_ok = LtGadget::construct(cb, depth.expr(), 1025.expr());
+        // depth <= MAX_CALL_DEPTH
+        let is_depth_ok = LtGadget::construct(cb, depth.expr(), (MAX_CALL_DEPTH + 1).expr());
 
         let is_precheck_ok = and::expr([
             is_depth_ok.expr(),
@@ -771,8 +771,12 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
         let depth = rws.next().call_context_value();
         let current_callee_address = rws.next().call_context_value();
 
-        self.is_depth_ok
-            .assign(region, offset, F::from(depth.low_u64()), F::from(1025))?;
+        self.is_depth_ok.assign(
+            region,
+            offset,
+            F::from(depth.low_u64()),
+            F::from(MAX_CALL_DEPTH as u64 + 1),
+        )?;
 
         // This offset is used to change the index offset of `step.rw_indices`.
         // Since both CALL and CALLCODE have an extra stack pop `value`, and
@@ -808,8 +812,8 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
         self.is_insufficient_balance
             .assign(region, offset, caller_balance, value)?;
 
-        let is_precheck_ok =
-            depth.low_u64() < 1025 && (!(is_call || is_callcode) || caller_balance >= value);
+        let is_precheck_ok = depth.low_u64() <= MAX_CALL_DEPTH as u64
+            && (!(is_call || is_callcode) || caller_balance >= value);
 
         // only call opcode do transfer in sucessful case.
         if is_call && is_precheck_ok && !value.is_zero() {
@@ -27,7 +27,10 @@ use crate::{
 };
 use bus_mapping::{circuit_input_builder::CopyDataType, evm::OpcodeId, state_db::CodeDB};
 use eth_types::{
-    evm_types::{GasCost, CREATE2_GAS_PER_CODE_WORD, CREATE_GAS_PER_CODE_WORD, MAX_INIT_CODE_SIZE},
+    evm_types::{
+        GasCost, CREATE2_GAS_PER_CODE_WORD, CREATE_GAS_PER_CODE_WORD, MAX_CALL_DEPTH,
+        MAX_INIT_CODE_SIZE,
+    },
     Field, ToBigEndian, ToLittleEndian, ToScalar, ToWord, H256, KECCAK_CODE_HASH_EMPTY, U256,
 };
 use ethers_core::utils::keccak256;
@@ -201,7 +204,7 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
         );
 
         // Pre-check: call depth, user's nonce and user's balance
-        let is_depth_in_range = LtGadget::construct(cb, depth.expr(), 1025.expr());
+        let is_depth_in_range = LtGadget::construct(cb, depth.expr(), (MAX_CALL_DEPTH + 1).expr());
         let is_insufficient_balance = LtWordGadget::construct(cb, &caller_balance, &value);
         let is_nonce_in_range = LtGadget::construct(cb, caller_nonce.expr(), u64::MAX.expr());
 
@@ -613,7 +616,7 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
         let caller_balance = rws.next().account_balance_pair().1;
         let caller_nonce = rws.next().account_nonce_pair().1.low_u64();
         let is_precheck_ok =
-            call.depth < 1025 && caller_balance >= value && caller_nonce < u64::MAX;
+            call.depth <= MAX_CALL_DEPTH && caller_balance >= value && caller_nonce < u64::MAX;
 
         self.caller_balance
             .assign(region, offset, Some(caller_balance.to_le_bytes()))?;
@@ -820,8 +823,12 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
 
         self.is_insufficient_balance
             .assign(region, offset, caller_balance, value)?;
-        self.is_depth_in_range
-            .assign(region, offset, F::from(call.depth as u64), F::from(1025))?;
+        self.is_depth_in_range.assign(
+            region,
+            offset,
+            F::from(call.depth as u64),
+            F::from(MAX_CALL_DEPTH as u64 + 1),
+        )?;
         self.is_nonce_in_range
             .assign(region, offset, F::from(caller_nonce), F::from(u64::MAX))?;
 
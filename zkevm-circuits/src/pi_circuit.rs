@@ -137,7 +137,7 @@ impl PublicData {
     }
 
     /// Compute the bytes for dataHash from the verifier's perspective.
-    fn data_bytes(&self) -> Vec<u8> {
+    pub(crate) fn data_bytes(&self) -> Vec<u8> {
         log::debug!(
             "pi circuit data_bytes, inner block num {}",
             self.block_ctxs.ctxs.len()
@@ -194,7 +194,7 @@ impl PublicData {
         H256(keccak256(self.data_bytes()))
     }
 
-    fn pi_bytes(&self, data_hash: H256) -> Vec<u8> {
+    pub(crate) fn pi_bytes(&self, data_hash: H256) -> Vec<u8> {
         iter::empty()
             .chain(self.chain_id.to_be_bytes())
             // state roots
@@ -245,10 +245,52 @@ pub(crate) fn block_2tx() -> GethData {
     block_2tx_ctx().into()
 }
 
+#[cfg(feature = "scroll")]
+fn block_0tx_trace() -> BlockTrace {
+    TestContext::<0, 0>::new(
+        Some(vec![Word::zero()]),
+        |_| {},
+        |_, _| {},
+        |block, _tx| block.number(0xcafeu64),
+    )
+    .unwrap()
+    .l2_trace()
+    .clone()
+}
+
 const TEST_MOCK_RANDOMNESS: u64 = 0x100;
 
 // High memory usage test.  Run in serial with:
 // `cargo test [...] serial_ -- --ignored --test-threads 1`
+#[ignore]
+#[cfg(feature = "scroll")]
+#[test]
+fn serial_test_super_circuit_0tx_1max_tx() {
+    let block = block_0tx_trace();
+    const MAX_TXS: usize = 1;
+    const MAX_CALLDATA: usize = 256;
+    const MAX_INNER_BLOCKS: usize = 1;
+    let circuits_params = CircuitsParams {
+        max_txs: MAX_TXS,
+        max_calldata: MAX_CALLDATA,
+        max_rws: 256,
+        max_copy_rows: 256,
+        max_exp_steps: 256,
+        max_bytecode: 512,
+        max_mpt_rows: 2049,
+        max_poseidon_rows: 512,
+        max_evm_rows: 0,
+        max_keccak_rows: 0,
+        max_inner_blocks: MAX_INNER_BLOCKS,
+        max_rlp_rows: 500,
+        ..Default::default()
+    };
+    test_super_circuit::<MAX_TXS, MAX_CALLDATA, MAX_INNER_BLOCKS, TEST_MOCK_RANDOMNESS>(
+        block,
+        circuits_params,
+    );
+}
+
 #[ignore]
 #[cfg(feature = "scroll")]
 #[test]
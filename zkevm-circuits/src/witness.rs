@@ -4,8 +4,11 @@
 
 mod block;
 pub use block::{
-    block_apply_mpt_state, block_convert, block_convert_with_l1_queue_index,
-    block_mocking_apply_mpt, Block, BlockContext, BlockContexts,
+    applied_refund, block_apply_mpt_state, block_convert, block_convert_with_l1_queue_index,
+    block_gas_used, block_logs, block_mocking_apply_mpt, deployed_contracts, diff_witness_blocks,
+    merge_witness_blocks, opcode_histogram, state_transition_witness, tx_logs_bloom, tx_receipt,
+    validate_block_context, Block, BlockContext, BlockContexts, LogEntry, StateTransitionWitness,
+    WitnessDiff,
 };
 
 mod bytecode;
@@ -15,7 +18,7 @@ mod call;
 pub use call::Call;
 
 mod mpt;
-pub use mpt::{MptUpdate, MptUpdateRow, MptUpdates, WithdrawProof};
+pub use mpt::{MptUpdate, MptUpdateRow, MptUpdates, TrieUpdate, TrieUpdateKey, WithdrawProof};
 
 mod receipt;
 pub use receipt::Receipt;
@@ -1,28 +1,41 @@
-use ethers_core::types::Signature;
+use ethers_core::{
+    types::{Bloom, Log, Signature},
+    utils::keccak256,
+};
 use std::collections::{BTreeMap, HashMap};
 
 #[cfg(any(feature = "test", test))]
 use crate::evm_circuit::{detect_fixed_table_tags, EvmCircuit};
 
 use crate::{
-    evm_circuit::util::rlc,
-    table::{BlockContextFieldTag, RwTableTag},
+    copy_circuit::copy_circuit_rows,
+    evm_circuit::{step::ExecutionState, util::rlc},
+    pi_circuit::PublicData,
+    table::{
+        BlockContextFieldTag, CallContextFieldTag, RwTableTag, TxLogFieldTag, TxReceiptFieldTag,
+    },
     util::SubCircuit,
 };
 use bus_mapping::{
     circuit_input_builder::{
-        self, BigModExp, CircuitsParams, CopyEvent, EcAddOp, EcMulOp, EcPairingOp, ExpEvent,
-        PrecompileEvents, SHA256,
+        self, BigModExp, CircuitsParams, CopyDataType, CopyEvent, EcAddOp, EcMulOp, EcPairingOp,
+        ExpEvent, NumberOrHash, PrecompileEvents, SHA256,
     },
+    evm::OpcodeId,
+    operation::RWCounter,
     Error,
 };
-use eth_types::{sign_types::SignData, Address, Field, ToLittleEndian, ToScalar, Word, U256};
+use eth_types::{
+    evm_types::MAX_REFUND_QUOTIENT_OF_GAS_USED, sign_types::SignData, Address, Bytes, Field,
+    ToAddress, ToBigEndian, ToLittleEndian, ToScalar, Word, H256, U256,
+};
 use halo2_proofs::circuit::Value;
 use itertools::Itertools;
+use strum::IntoEnumIterator;
 
 use super::{
     mpt::ZktrieState as MptState, step::step_convert, tx::tx_convert, Bytecode, ExecStep,
-    MptUpdates, RwMap, Transaction,
+    MptUpdates, Receipt, Rw, RwMap, Transaction, TrieUpdate,
 };
 use crate::util::Challenges;
 
@@ -86,6 +99,22 @@ pub struct BlockContexts {
 }
 
 impl<F: Field> Block<F> {
+    /// Remove the rws of the EndBlock step, which are the last ones of their
+    /// tags.
+    fn drop_end_block_rws(&mut self) -> Result<(), Error> {
+        for (tag, idx) in std::mem::take(&mut self.end_block_last.rw_indices)
+            .into_iter()
+            .rev()
+        {
+            let rws = self.rws.0.entry(tag).or_default();
+            if idx + 1 != rws.len() {
+                return Err(Error::InternalError("EndBlock rws are not the last ones"));
+            }
+            rws.pop();
+        }
+        Ok(())
+    }
+
     /// For each tx, for each step, print the rwc at the beginning of the step,
     /// and all the rw operations of the step.
     pub(crate) fn debug_print_txs_steps_rw_ops(&self) {
@@ -307,6 +336,417 @@ impl<F: Field> Block<F> {
     }
 }
 
+/// Total gas used by the block, i.e. the cumulative gas used recorded in the
+/// receipt of the last tx. Returns 0 for a block without txs.
+pub fn block_gas_used<F: Field>(block: &Block<F>) -> u64 {
+    block
+        .rws
+        .0
+        .get(&RwTableTag::TxReceipt)
+        .into_iter()
+        .flatten()
+        .filter_map(|rw| match rw {
+            Rw::TxReceipt {
+                is_write: true,
+                tx_id,
+                field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+                value,
+                ..
+            } => Some((*tx_id, *value)),
+            _ => None,
+        })
+        .max_by_key(|(tx_id, _)| *tx_id)
+        .map_or(0, |(_, value)| value)
+}
+
+/// Refund applied to the caller of the tx at `tx_index` at the end of the tx,
+/// i.e. the accumulated refund capped at the gas used divided by
+/// `MAX_REFUND_QUOTIENT_OF_GAS_USED`. Returns 0 for an L1 message, whose
+/// caller isn't refunded.
+pub fn applied_refund<F: Field>(block: &Block<F>, tx_index: usize) -> u64 {
+    let tx = &block.txs[tx_index];
+    if tx.tx_type.is_l1_msg() {
+        return 0;
+    }
+    let Some(end_tx) = tx
+        .steps
+        .iter()
+        .find(|step| step.execution_state == ExecutionState::EndTx)
+    else {
+        return 0;
+    };
+    let refund = end_tx
+        .rw_indices
+        .iter()
+        .find(|(tag, _)| *tag == RwTableTag::TxRefund)
+        .map_or(0, |idx| block.rws[*idx].tx_refund_value_pair().0);
+    refund.min((tx.gas - end_tx.gas_left) / MAX_REFUND_QUOTIENT_OF_GAS_USED as u64)
+}
+
+/// A log emitted in a block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogEntry {
+    /// Id of the tx emitting the log, starting at 1.
+    pub tx_id: usize,
+    /// Id of the log in the tx, starting at 1.
+    pub log_id: u64,
+    /// Address of the contract emitting the log.
+    pub address: Address,
+    /// Topics of the log.
+    pub topics: Vec<Word>,
+    /// Data of the log.
+    pub data: Vec<u8>,
+}
+
+/// Logs emitted in the block, ordered by tx and log id, assembled from the
+/// `TxLog` rws and the copy events of the log data. Logs of reverted calls are
+/// never written, so they are not included.
+pub fn block_logs<F: Field>(block: &Block<F>) -> Vec<LogEntry> {
+    let mut logs: BTreeMap<(usize, u64), LogEntry> = BTreeMap::new();
+    let mut data_words: BTreeMap<(usize, u64), BTreeMap<usize, Word>> = BTreeMap::new();
+    let mut topics: BTreeMap<(usize, u64), BTreeMap<usize, Word>> = BTreeMap::new();
+
+    for rw in block.rws.0.get(&RwTableTag::TxLog).into_iter().flatten() {
+        let Rw::TxLog {
+            tx_id,
+            log_id,
+            field_tag,
+            index,
+            value,
+            ..
+        } = *rw
+        else {
+            continue;
+        };
+        let key = (tx_id, log_id);
+        logs.entry(key).or_insert_with(|| LogEntry {
+            tx_id,
+            log_id,
+            ..Default::default()
+        });
+        match field_tag {
+            TxLogFieldTag::Address => logs.get_mut(&key).unwrap().address = value.to_address(),
+            TxLogFieldTag::Topic => {
+                topics.entry(key).or_default().insert(index, value);
+            }
+            TxLogFieldTag::Data => {
+                data_words.entry(key).or_default().insert(index, value);
+            }
+        }
+    }
+
+    // The data is written in words, so cut it to the length of the copy.
+    let data_lens: HashMap<(usize, u64), usize> = block
+        .copy_events
+        .iter()
+        .filter(|event| event.dst_type == CopyDataType::TxLog)
+        .filter_map(|event| match (event.dst_id.clone(), event.log_id) {
+            (NumberOrHash::Number(tx_id), Some(log_id)) => Some((
+                (tx_id, log_id),
+                (event.src_addr_end - event.src_addr) as usize,
+            )),
+            _ => None,
+        })
+        .collect();
+
+    for (key, log) in logs.iter_mut() {
+        log.topics = topics
+            .remove(key)
+            .unwrap_or_default()
+            .into_values()
+            .collect();
+        log.data = data_words
+            .remove(key)
+            .unwrap_or_default()
+            .into_values()
+            .flat_map(|word| word.to_be_bytes())
+            .collect();
+        log.data
+            .truncate(data_lens.get(key).copied().unwrap_or_default());
+    }
+
+    logs.into_values().collect()
+}
+
+/// Address and runtime code of the contracts deployed in the block, in the
+/// order of their creation. The code is taken from the copy event of the
+/// `RETURN` in the creation frame, and creations which are eventually reverted
+/// are skipped.
+pub fn deployed_contracts<F: Field>(block: &Block<F>) -> Vec<(Address, Bytes)> {
+    let codes: HashMap<usize, Vec<u8>> = block
+        .copy_events
+        .iter()
+        .filter(|event| {
+            event.src_type == CopyDataType::Memory && event.dst_type == CopyDataType::Bytecode
+        })
+        .filter_map(|event| match event.src_id {
+            NumberOrHash::Number(call_id) => Some((
+                call_id,
+                event
+                    .copy_bytes
+                    .bytes
+                    .iter()
+                    .filter(|(_, _, is_mask)| !is_mask)
+                    .map(|(byte, _, _)| *byte)
+                    .collect(),
+            )),
+            NumberOrHash::Hash(_) => None,
+        })
+        .collect();
+
+    block
+        .txs
+        .iter()
+        .flat_map(|tx| tx.calls.iter())
+        .filter(|call| call.is_create && call.is_persistent)
+        .map(|call| {
+            (
+                call.callee_address,
+                codes.get(&call.id).cloned().unwrap_or_default().into(),
+            )
+        })
+        .collect()
+}
+
+/// Logs bloom of the tx at `tx_index` in `block.txs`, i.e. the bloom filter of
+/// the addresses and topics of the logs in [`block_logs`] emitted by the tx.
+pub fn tx_logs_bloom<F: Field>(block: &Block<F>, tx_index: usize) -> [u8; 256] {
+    logs_bloom(&tx_logs(block, tx_index))
+}
+
+// Logs in `block_logs` emitted by the tx at `tx_index` in `block.txs`.
+fn tx_logs<F: Field>(block: &Block<F>, tx_index: usize) -> Vec<LogEntry> {
+    let tx_id = block.txs[tx_index].id;
+    block_logs(block)
+        .into_iter()
+        .filter(|log| log.tx_id == tx_id)
+        .collect()
+}
+
+// Bloom filter of the addresses and topics of `logs`.
+fn logs_bloom(logs: &[LogEntry]) -> [u8; 256] {
+    let mut bloom = [0u8; 256];
+    for log in logs {
+        accrue_bloom(&mut bloom, log.address.as_bytes());
+        for topic in log.topics.iter() {
+            accrue_bloom(&mut bloom, &topic.to_be_bytes());
+        }
+    }
+    bloom
+}
+
+/// Receipt of the tx at `tx_index` in `block.txs`, assembled from its
+/// `TxReceipt` rws, its logs in [`block_logs`] and [`tx_logs_bloom`].
+pub fn tx_receipt<F: Field>(block: &Block<F>, tx_index: usize) -> Receipt {
+    let tx_id = block.txs[tx_index].id;
+    let logs = tx_logs(block, tx_index);
+    let mut receipt = Receipt {
+        id: tx_id,
+        bloom: Bloom(logs_bloom(&logs)),
+        ..Default::default()
+    };
+    for rw in block
+        .rws
+        .0
+        .get(&RwTableTag::TxReceipt)
+        .into_iter()
+        .flatten()
+    {
+        match *rw {
+            Rw::TxReceipt {
+                tx_id: rw_tx_id,
+                field_tag: TxReceiptFieldTag::PostStateOrStatus,
+                value,
+                ..
+            } if rw_tx_id == tx_id => receipt.status = value as u8,
+            Rw::TxReceipt {
+                tx_id: rw_tx_id,
+                field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+                value,
+                ..
+            } if rw_tx_id == tx_id => receipt.cumulative_gas_used = value,
+            _ => {}
+        }
+    }
+    receipt.logs = logs
+        .into_iter()
+        .map(|log| Log {
+            address: log.address,
+            topics: log
+                .topics
+                .iter()
+                .map(|topic| H256::from(topic.to_be_bytes()))
+                .collect(),
+            data: log.data.into(),
+            ..Default::default()
+        })
+        .collect();
+    receipt
+}
+
+// Set the 3 bits selected by the low 11 bits of the first 3 byte pairs of the
+// keccak hash of `input`.
+fn accrue_bloom(bloom: &mut [u8; 256], input: &[u8]) {
+    let hash = keccak256(input);
+    for i in [0, 2, 4] {
+        let bit = (u16::from_be_bytes([hash[i], hash[i + 1]]) & 0x7ff) as usize;
+        bloom[255 - bit / 8] |= 1 << (bit % 8);
+    }
+}
+
+/// Check the fields of each block context (timestamp, number, coinbase, base
+/// fee and gas limit) against its header in `headers`, which are obtained
+/// independently of the witness, e.g. from the RPC. The contexts must also have
+/// consecutive numbers and non-decreasing timestamps. Every mismatch is logged.
+pub fn validate_block_context<F: Field>(
+    block: &Block<F>,
+    headers: &[eth_types::Block<eth_types::Transaction>],
+) -> Result<(), Error> {
+    let headers: HashMap<u64, _> = headers
+        .iter()
+        .filter_map(|header| header.number.map(|number| (number.as_u64(), header)))
+        .collect();
+    let mut valid = true;
+    let mut prev_ctx: Option<&BlockContext> = None;
+    for (&block_num, ctx) in block.context.ctxs.iter() {
+        let Some(header) = headers.get(&block_num) else {
+            log::error!("block {block_num}: no header for the block context");
+            valid = false;
+            continue;
+        };
+        let mut mismatches = vec![
+            ("timestamp", ctx.timestamp == header.timestamp),
+            ("number", ctx.number == block_num.into()),
+            ("coinbase", Some(ctx.coinbase) == header.author),
+            (
+                "base fee",
+                ctx.base_fee == header.base_fee_per_gas.unwrap_or_default(),
+            ),
+            ("gas limit", ctx.gas_limit == header.gas_limit.low_u64()),
+        ];
+        if let Some(prev_ctx) = prev_ctx {
+            mismatches.extend([
+                (
+                    "number after the previous block",
+                    ctx.number == prev_ctx.number + 1,
+                ),
+                (
+                    "timestamp after the previous block",
+                    ctx.timestamp >= prev_ctx.timestamp,
+                ),
+            ]);
+        }
+        for (field, _) in mismatches.into_iter().filter(|(_, matches)| !matches) {
+            log::error!("block {block_num}: {field} of the block context mismatches");
+            valid = false;
+        }
+        prev_ctx = Some(ctx);
+    }
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InternalError("block context mismatches header"))
+    }
+}
+
+/// Number of steps of each opcode in the block. Steps without an opcode, e.g.
+/// `BeginTx`, `EndTx` and precompile steps, are not counted.
+pub fn opcode_histogram<F: Field>(block: &Block<F>) -> HashMap<OpcodeId, usize> {
+    let mut histogram = HashMap::new();
+    for opcode in block
+        .txs
+        .iter()
+        .flat_map(|tx| tx.steps.iter())
+        .filter_map(|step| step.opcode)
+    {
+        *histogram.entry(opcode).or_default() += 1;
+    }
+    histogram
+}
+
+/// Differences between two witness blocks, see [`diff_witness_blocks`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WitnessDiff {
+    /// `(tx_index, steps in a, steps in b)` for each tx whose step count
+    /// differs. A tx missing in one of the blocks has no steps there.
+    pub step_counts: Vec<(usize, usize, usize)>,
+    /// `(tag, rws in a, rws in b)` for each rw table tag whose count differs.
+    pub rw_counts: Vec<(RwTableTag, usize, usize)>,
+    /// `(tx_index, step_index, step in a, step in b)` of the first differing
+    /// exec step, where a step missing in one of the blocks is `None`.
+    pub first_step_diff: Option<(usize, usize, Option<ExecStep>, Option<ExecStep>)>,
+}
+
+impl WitnessDiff {
+    /// Return true if no difference was found.
+    pub fn is_empty(&self) -> bool {
+        self.step_counts.is_empty() && self.rw_counts.is_empty() && self.first_step_diff.is_none()
+    }
+}
+
+/// Compare the step counts per tx, the rw counts per tag and the exec steps of
+/// the witness blocks `a` and `b`, e.g. generated by two versions of the
+/// witness generation from the same traces.
+pub fn diff_witness_blocks<F: Field>(a: &Block<F>, b: &Block<F>) -> WitnessDiff {
+    let num_txs = a.txs.len().max(b.txs.len());
+    let steps_of = |block: &Block<F>, tx_index: usize| -> Vec<ExecStep> {
+        block
+            .txs
+            .get(tx_index)
+            .map(|tx| tx.steps.clone())
+            .unwrap_or_default()
+    };
+
+    let mut diff = WitnessDiff::default();
+    for tx_index in 0..num_txs {
+        let (steps_a, steps_b) = (steps_of(a, tx_index), steps_of(b, tx_index));
+        if steps_a.len() != steps_b.len() {
+            diff.step_counts
+                .push((tx_index, steps_a.len(), steps_b.len()));
+        }
+        if diff.first_step_diff.is_none() {
+            diff.first_step_diff = (0..steps_a.len().max(steps_b.len()))
+                .map(|step_index| {
+                    (
+                        step_index,
+                        steps_a.get(step_index).cloned(),
+                        steps_b.get(step_index).cloned(),
+                    )
+                })
+                .find(|(_, step_a, step_b)| step_a != step_b)
+                .map(|(step_index, step_a, step_b)| (tx_index, step_index, step_a, step_b));
+        }
+    }
+    diff.rw_counts = RwTableTag::iter()
+        .map(|tag| (tag, a.rws.rw_num(tag), b.rws.rw_num(tag)))
+        .filter(|(_, num_a, num_b)| num_a != num_b)
+        .collect();
+
+    diff
+}
+
+/// State roots before and after a block, with the trie updates between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateTransitionWitness {
+    /// State root before the block
+    pub prev_state_root: Word,
+    /// State root after the block, if the MPT state has been applied
+    pub state_root: Option<Word>,
+    /// Trie updates of the block, in order
+    pub updates: Vec<TrieUpdate>,
+}
+
+/// Collect the trie updates needed to compute the state root after the block
+/// from its account and storage writes. The roots of the updates are the real
+/// trie roots only once [`block_apply_mpt_state`] has been called.
+pub fn state_transition_witness<F: Field>(block: &Block<F>) -> StateTransitionWitness {
+    StateTransitionWitness {
+        prev_state_root: block.prev_state_root,
+        state_root: block.state_root,
+        updates: block.mpt_updates.trie_updates(),
+    }
+}
+
 /// Block context for execution
 #[derive(Debug, Clone)]
 pub struct BlockContext {
@@ -605,3 +1045,1062 @@ pub fn block_mocking_apply_mpt<F: Field>(block: &mut Block<F>) {
     block.state_root = Some(block.mpt_updates.new_root());
     block.prev_state_root = block.mpt_updates.old_root();
 }
+
+/// Merge the witness blocks of consecutive blocks, each built on the state left
+/// by the previous one, into the witness block of a single batch, as if they
+/// had been handled by one `CircuitInputBuilder`. The rw counters, call ids, tx
+/// ids and cumulative gas of each block are rebased on the blocks before it,
+/// and the capacity of the first block's `circuits_params` must hold them all.
+/// As in `block_convert`, the mpt updates of the result have mock roots.
+pub fn merge_witness_blocks<F: Field>(blocks: Vec<Block<F>>) -> Result<Block<F>, Error> {
+    let num_blocks = blocks.len();
+    let mut blocks = blocks.into_iter();
+    let mut merged = blocks
+        .next()
+        .ok_or(Error::InternalError("no witness block to merge"))?;
+    if num_blocks == 1 {
+        return Ok(merged);
+    }
+    let old_root = merged.mpt_updates.old_root();
+    let mut new_root = merged.mpt_updates.new_root();
+    let mut keccak_inputs = keccak_inputs_without_pi(&merged);
+    let mut next_rwc = merged.end_block_last.rw_counter;
+    merged.drop_end_block_rws()?;
+
+    for (i, mut block) in blocks.enumerate() {
+        let is_last = i + 2 == num_blocks;
+        if block.chain_id != merged.chain_id {
+            return Err(Error::InternalError("witness blocks of different chains"));
+        }
+        match (
+            merged.context.ctxs.keys().next_back(),
+            block.context.ctxs.keys().next(),
+        ) {
+            (Some(prev), Some(next)) if prev >= next => {
+                return Err(Error::InternalError(
+                    "witness blocks to merge are not in order",
+                ));
+            }
+            _ => {}
+        }
+        keccak_inputs.extend(keccak_inputs_without_pi(&block));
+        let block_next_rwc = block.end_block_last.rw_counter;
+        if !is_last {
+            block.drop_end_block_rws()?;
+        }
+
+        let tx_offset = merged.txs.len();
+        let mut rebase = Rebase {
+            rwc_offset: next_rwc - 1,
+            inserted_rwcs: vec![],
+            tx_offset,
+            gas_offset: merged
+                .rws
+                .0
+                .get(&RwTableTag::TxReceipt)
+                .into_iter()
+                .flatten()
+                .filter_map(|rw| match rw {
+                    Rw::TxReceipt {
+                        is_write: true,
+                        tx_id,
+                        field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+                        value,
+                        ..
+                    } if *tx_id == tx_offset => Some(*value),
+                    _ => None,
+                })
+                .next_back()
+                .unwrap_or_default(),
+        };
+        // The EndTx step of the first tx of the batch is the only one which
+        // doesn't read the cumulative gas of the previous tx.
+        let gas_read = match block.txs.first() {
+            Some(tx) if tx_offset > 0 => {
+                let (step_idx, step) = tx
+                    .steps
+                    .iter()
+                    .enumerate()
+                    .find(|(_, step)| step.execution_state == ExecutionState::EndTx)
+                    .ok_or(Error::InternalError("tx without EndTx step"))?;
+                let pos = step
+                    .rw_indices
+                    .iter()
+                    .position(|&rw_idx| {
+                        matches!(
+                            block.rws[rw_idx],
+                            Rw::TxReceipt {
+                                is_write: true,
+                                field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+                                ..
+                            }
+                        )
+                    })
+                    .ok_or(Error::InternalError(
+                        "EndTx step without cumulative gas write",
+                    ))?;
+                let rwc = block.rws[step.rw_indices[pos]].rw_counter();
+                rebase.inserted_rwcs.push(rwc);
+                Some((step_idx, pos, rwc))
+            }
+            _ => None,
+        };
+        // The EndBlock step reads the tx id of the last call, which an empty
+        // last block doesn't do.
+        let tx_id_read = is_last && tx_offset > 0 && block.txs.is_empty();
+        if tx_id_read {
+            rebase.inserted_rwcs.push(block_next_rwc);
+        }
+
+        if let Some((_, _, rwc)) = gas_read {
+            merged
+                .rws
+                .0
+                .entry(RwTableTag::TxReceipt)
+                .or_default()
+                .push(Rw::TxReceipt {
+                    rw_counter: rebase.inserted_rwc(rwc),
+                    is_write: false,
+                    tx_id: tx_offset,
+                    field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+                    value: rebase.gas_offset,
+                });
+        }
+        let idx_offsets: HashMap<_, _> = merged
+            .rws
+            .0
+            .iter()
+            .map(|(tag, rws)| (*tag, rws.len()))
+            .collect();
+
+        for tx in block.txs.iter_mut() {
+            tx.id = rebase.tx_id(tx.id);
+            for call in tx.calls.iter_mut() {
+                call.id = rebase.rwc(call.id);
+                call.caller_id = rebase.rwc(call.caller_id);
+                call.last_callee_id = rebase.rwc(call.last_callee_id);
+                call.rw_counter_end_of_reversion = rebase.rwc(call.rw_counter_end_of_reversion);
+            }
+            for step in tx.steps.iter_mut() {
+                rebase.step(step, &idx_offsets);
+            }
+        }
+        if let Some((step_idx, pos, _)) = gas_read {
+            let read_idx = idx_offsets[&RwTableTag::TxReceipt] - 1;
+            block.txs[0].steps[step_idx]
+                .rw_indices
+                .insert(pos, (RwTableTag::TxReceipt, read_idx));
+        }
+        for (tag, mut rws) in block.rws.0 {
+            rws.iter_mut().for_each(|rw| rebase.rw(rw));
+            merged.rws.0.entry(tag).or_default().extend(rws);
+        }
+        for event in block.copy_events.iter_mut() {
+            rebase.copy_event(event);
+        }
+
+        if is_last {
+            merged.end_block_not_last = block.end_block_not_last;
+            merged.end_block_last = block.end_block_last;
+            rebase.step(&mut merged.end_block_not_last, &idx_offsets);
+            rebase.step(&mut merged.end_block_last, &idx_offsets);
+            if tx_id_read {
+                let rw_counter = rebase.inserted_rwc(block_next_rwc);
+                let last_call_id = merged.txs[tx_offset - 1].calls[0].id;
+                let call_context = merged.rws.0.entry(RwTableTag::CallContext).or_default();
+                call_context.push(Rw::CallContext {
+                    rw_counter,
+                    is_write: false,
+                    call_id: last_call_id,
+                    field_tag: CallContextFieldTag::TxId,
+                    value: tx_offset.into(),
+                });
+                let read_idx = call_context.len() - 1;
+                merged
+                    .end_block_last
+                    .rw_indices
+                    .insert(0, (RwTableTag::CallContext, read_idx));
+                merged.end_block_not_last.rw_counter = rw_counter;
+                merged.end_block_last.rw_counter = rw_counter;
+            }
+        }
+        next_rwc = rebase.rwc(block_next_rwc);
+
+        merged.txs.extend(block.txs);
+        merged.sigs.extend(block.sigs);
+        merged.context.ctxs.extend(block.context.ctxs);
+        merged.bytecodes.extend(block.bytecodes);
+        merged.copy_events.extend(block.copy_events);
+        merged.exp_events.extend(block.exp_events);
+        merged.sha3_inputs.extend(block.sha3_inputs);
+        merged
+            .precompile_events
+            .events
+            .extend(block.precompile_events.events);
+        merged.withdraw_root = block.withdraw_root;
+        new_root = block.mpt_updates.new_root();
+    }
+
+    // Each tx ends with an EndInnerBlock step for each block from its own one
+    // to the one of the next tx.
+    let last_block_num = merged
+        .context
+        .ctxs
+        .keys()
+        .next_back()
+        .copied()
+        .unwrap_or_default();
+    let next_block_nums = merged
+        .txs
+        .iter()
+        .skip(1)
+        .map(|tx| tx.block_number)
+        .chain(std::iter::once(last_block_num + 1))
+        .collect::<Vec<_>>();
+    for (tx, next_block_num) in merged.txs.iter_mut().zip(next_block_nums) {
+        tx.steps
+            .retain(|step| step.execution_state != ExecutionState::EndInnerBlock);
+        let last_step = tx
+            .steps
+            .last()
+            .ok_or(Error::InternalError("tx without steps"))?;
+        let rw_counter = last_step.rw_counter + last_step.rw_indices.len();
+        let call_index = last_step.call_index;
+        let block_number = tx.block_number;
+        tx.steps
+            .extend((block_number..next_block_num).map(|block_num| ExecStep {
+                rw_counter,
+                execution_state: ExecutionState::EndInnerBlock,
+                block_num,
+                call_index,
+                ..Default::default()
+            }));
+    }
+
+    let params = merged.circuits_params;
+    let total_rws = merged.end_block_last.rw_counter
+        + merged
+            .end_block_last
+            .rw_indices
+            .iter()
+            .filter(|(tag, _)| *tag != RwTableTag::Start)
+            .count()
+        - 1;
+    if merged.txs.len() > params.max_txs {
+        return Err(Error::InternalError("merged block exceeds max_txs"));
+    }
+    if merged.context.ctxs.len() > params.max_inner_blocks {
+        return Err(Error::InternalError(
+            "merged block exceeds max_inner_blocks",
+        ));
+    }
+    if merged
+        .txs
+        .iter()
+        .map(|tx| tx.call_data_length)
+        .sum::<usize>()
+        > params.max_calldata
+    {
+        return Err(Error::InternalError("merged block exceeds max_calldata"));
+    }
+    // We need at least 1 extra Start row
+    if total_rws + 1 > params.max_rws {
+        return Err(Error::InternalError("merged block exceeds max_rws"));
+    }
+    if params.max_copy_rows != 0 && copy_circuit_rows(&merged) > params.max_copy_rows {
+        return Err(Error::InternalError("merged block exceeds max_copy_rows"));
+    }
+    merged.rws.0.insert(
+        RwTableTag::Start,
+        vec![
+            Rw::Start { rw_counter: 1 },
+            Rw::Start {
+                rw_counter: params.max_rws - total_rws,
+            },
+        ],
+    );
+    merged.rws.check_rw_counter_sanity();
+    merged.rws.check_value()?;
+
+    merged.mpt_updates = MptUpdates::from_unsorted_rws_with_mock_state_roots(
+        &merged.rws.table_assignments_unsorted(),
+        old_root,
+        new_root,
+    );
+    merged.state_root = None;
+    keccak_inputs.extend(keccak_inputs_pi(&merged));
+    merged.keccak_inputs = keccak_inputs.into_iter().unique().collect();
+    Ok(merged)
+}
+
+/// How the rw counters, call ids, tx ids and cumulative gas of a witness block
+/// are shifted when it's appended to the blocks before it.
+struct Rebase {
+    rwc_offset: usize,
+    /// The rw counters (before rebasing) before which a rw is inserted.
+    inserted_rwcs: Vec<usize>,
+    tx_offset: usize,
+    gas_offset: u64,
+}
+
+impl Rebase {
+    /// Rebase a rw counter, or a call id, which is the rw counter at the
+    /// beginning of the call. 0 stands for no call and stays 0.
+    fn rwc(&self, rwc: usize) -> usize {
+        if rwc == 0 {
+            return 0;
+        }
+        rwc + self.rwc_offset + self.inserted_rwcs.iter().filter(|&&r| rwc >= r).count()
+    }
+
+    /// The rw counter of the rw inserted before `rwc`.
+    fn inserted_rwc(&self, rwc: usize) -> usize {
+        rwc + self.rwc_offset + self.inserted_rwcs.iter().filter(|&&r| rwc > r).count()
+    }
+
+    fn tx_id(&self, tx_id: usize) -> usize {
+        tx_id + self.tx_offset
+    }
+
+    fn rw(&self, rw: &mut Rw) {
+        match rw {
+            // Start rws are rebuilt for the merged block.
+            Rw::Start { .. } => {}
+            Rw::Account { rw_counter, .. } => *rw_counter = self.rwc(*rw_counter),
+            Rw::TxAccessListAccount {
+                rw_counter, tx_id, ..
+            }
+            | Rw::TxAccessListAccountStorage {
+                rw_counter, tx_id, ..
+            }
+            | Rw::TxRefund {
+                rw_counter, tx_id, ..
+            }
+            | Rw::AccountStorage {
+                rw_counter, tx_id, ..
+            }
+            | Rw::TxLog {
+                rw_counter, tx_id, ..
+            } => {
+                *rw_counter = self.rwc(*rw_counter);
+                *tx_id = self.tx_id(*tx_id);
+            }
+            Rw::Stack {
+                rw_counter,
+                call_id,
+                ..
+            }
+            | Rw::Memory {
+                rw_counter,
+                call_id,
+                ..
+            } => {
+                *rw_counter = self.rwc(*rw_counter);
+                *call_id = self.rwc(*call_id);
+            }
+            Rw::CallContext {
+                rw_counter,
+                call_id,
+                field_tag,
+                value,
+                ..
+            } => {
+                *rw_counter = self.rwc(*rw_counter);
+                *call_id = self.rwc(*call_id);
+                match field_tag {
+                    CallContextFieldTag::RwCounterEndOfReversion
+                    | CallContextFieldTag::CallerId
+                    | CallContextFieldTag::LastCalleeId => {
+                        *value = self.rwc(value.as_usize()).into();
+                    }
+                    CallContextFieldTag::TxId => *value = self.tx_id(value.as_usize()).into(),
+                    _ => {}
+                }
+            }
+            Rw::TxReceipt {
+                rw_counter,
+                tx_id,
+                field_tag,
+                value,
+                ..
+            } => {
+                *rw_counter = self.rwc(*rw_counter);
+                *tx_id = self.tx_id(*tx_id);
+                if *field_tag == TxReceiptFieldTag::CumulativeGasUsed {
+                    *value += self.gas_offset;
+                }
+            }
+        }
+    }
+
+    fn step(&self, step: &mut ExecStep, idx_offsets: &HashMap<RwTableTag, usize>) {
+        step.rw_counter = self.rwc(step.rw_counter);
+        for (tag, idx) in step.rw_indices.iter_mut() {
+            *idx += idx_offsets.get(tag).copied().unwrap_or_default();
+        }
+    }
+
+    fn copy_event(&self, event: &mut CopyEvent) {
+        event.rw_counter_start = RWCounter(self.rwc(event.rw_counter_start.0));
+        self.copy_id(event.src_type, &mut event.src_id);
+        self.copy_id(event.dst_type, &mut event.dst_id);
+    }
+
+    fn copy_id(&self, copy_type: CopyDataType, id: &mut NumberOrHash) {
+        if let NumberOrHash::Number(id) = id {
+            match copy_type {
+                CopyDataType::Memory | CopyDataType::RlcAcc => *id = self.rwc(*id),
+                CopyDataType::TxCalldata
+                | CopyDataType::TxLog
+                | CopyDataType::AccessListAddresses
+                | CopyDataType::AccessListStorageKeys => *id = self.tx_id(*id),
+                CopyDataType::Padding | CopyDataType::Bytecode => {}
+            }
+        }
+    }
+}
+
+/// The keccak inputs of the PI circuit of `block`, as `keccak_inputs` of the
+/// bus-mapping adds them.
+fn keccak_inputs_pi<F: Field>(block: &Block<F>) -> Vec<Vec<u8>> {
+    let public_data = PublicData {
+        chain_id: block.chain_id,
+        start_l1_queue_index: block.start_l1_queue_index,
+        transactions: block.txs.clone(),
+        // the coinbase and difficulty constants are checked by the PI circuit
+        block_ctxs: BlockContexts {
+            relax_mode: true,
+            ..block.context.clone()
+        },
+        prev_state_root: H256(block.prev_state_root.to_be_bytes()),
+        next_state_root: block
+            .context
+            .ctxs
+            .last_key_value()
+            .map(|(_, ctx)| ctx.eth_block.state_root)
+            .unwrap_or(H256(block.prev_state_root.to_be_bytes())),
+        withdraw_trie_root: H256(block.withdraw_root.to_be_bytes()),
+        max_txs: block.circuits_params.max_txs,
+        max_calldata: block.circuits_params.max_calldata,
+        max_inner_blocks: block.circuits_params.max_inner_blocks,
+    };
+    let data_bytes = public_data.data_bytes();
+    let pi_bytes = public_data.pi_bytes(H256(keccak256(&data_bytes)));
+    vec![data_bytes, pi_bytes]
+}
+
+/// The keccak inputs of `block` but the ones of its PI circuit, which cover the
+/// whole batch.
+fn keccak_inputs_without_pi<F: Field>(block: &Block<F>) -> Vec<Vec<u8>> {
+    let pi_inputs = keccak_inputs_pi(block);
+    block
+        .keccak_inputs
+        .iter()
+        .filter(|input| !pi_inputs.contains(input))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bus_mapping::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData};
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use mock::{eth, test_ctx::helpers::account_0_code_account_1_no_code, TestContext};
+
+    #[test]
+    fn test_empty_block() {
+        let block: GethData = TestContext::<0, 0>::new(
+            None,
+            |_| {},
+            |_, _| {},
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+
+        assert!(block.txs.is_empty());
+        assert_eq!(
+            block.context.ctxs.keys().copied().collect::<Vec<_>>(),
+            vec![0xcafe]
+        );
+        assert_eq!(
+            block.end_block_last.execution_state,
+            ExecutionState::EndBlock
+        );
+        // no tx means no tx receipt nor call context
+        assert_eq!(block.rws.rw_num(RwTableTag::TxReceipt), 0);
+        assert_eq!(block.rws.rw_num(RwTableTag::CallContext), 0);
+    }
+
+    #[test]
+    fn test_applied_refund_is_capped() {
+        // clear 2 slots: a refund of 2 * 4800 gas, more than a fifth of the
+        // gas used
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(Address::repeat_byte(0x10))
+                    .balance(eth(10))
+                    .code(bytecode! {
+                        PUSH1(0)
+                        PUSH1(0)
+                        SSTORE
+                        PUSH1(0)
+                        PUSH1(1)
+                        SSTORE
+                        STOP
+                    })
+                    .storage(
+                        vec![(Word::zero(), Word::one()), (Word::one(), Word::one())].into_iter(),
+                    );
+                accs[1].address(Address::repeat_byte(0x20)).balance(eth(10));
+            },
+            |mut txs, accs| {
+                txs[0].to(accs[0].address).from(accs[1].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+
+        let tx = &block.txs[0];
+        let end_tx = tx
+            .steps
+            .iter()
+            .find(|step| step.execution_state == ExecutionState::EndTx)
+            .unwrap();
+        let gas_used = tx.gas - end_tx.gas_left;
+        let refund = applied_refund(&block, 0);
+        assert!(refund < 2 * 4800);
+        assert_eq!(refund, gas_used / MAX_REFUND_QUOTIENT_OF_GAS_USED as u64);
+    }
+
+    #[test]
+    fn test_block_gas_used() {
+        let block: GethData = TestContext::<2, 2>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! {
+                PUSH1(0)
+                SLOAD
+                STOP
+            }),
+            |mut txs, accs| {
+                txs[0]
+                    .to(accs[0].address)
+                    .from(accs[1].address)
+                    .value(eth(1));
+                txs[1]
+                    .to(accs[0].address)
+                    .from(accs[1].address)
+                    .input(vec![1, 2, 3].into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+
+        let tx_gas_used = block
+            .txs
+            .iter()
+            .map(|tx| {
+                let end_tx = tx
+                    .steps
+                    .iter()
+                    .find(|step| step.execution_state == ExecutionState::EndTx)
+                    .unwrap();
+                tx.gas - end_tx.gas_left
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tx_gas_used.len(), 2);
+        assert_eq!(block_gas_used(&block), tx_gas_used.iter().sum::<u64>());
+    }
+
+    #[test]
+    fn test_block_logs() {
+        use eth_types::ToWord;
+        use mock::{
+            test_ctx::helpers::{caller_and_callee, tx_from_2_to_0},
+            MOCK_CALLEE, MOCK_CALLER,
+        };
+
+        // log 4 bytes with topic 0xaa, then call the callee
+        let caller_code = bytecode! {
+            PUSH4(0xdeadbeefu64)
+            PUSH1(0)
+            MSTORE
+            PUSH1(0xaa)
+            PUSH1(4)
+            PUSH1(28)
+            LOG1
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(MOCK_CALLEE.to_word())
+            PUSH2(0xFFFF)
+            CALL
+            POP
+            STOP
+        };
+        // log with topic 0xbb, then revert
+        let callee_code = bytecode! {
+            PUSH1(0xbb)
+            PUSH1(0)
+            PUSH1(0)
+            LOG1
+            PUSH1(0)
+            PUSH1(0)
+            REVERT
+        };
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            caller_and_callee(caller_code, callee_code),
+            tx_from_2_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+
+        assert_eq!(
+            block_logs(&block),
+            vec![LogEntry {
+                tx_id: 1,
+                log_id: 1,
+                address: *MOCK_CALLER,
+                topics: vec![Word::from(0xaa)],
+                data: vec![0xde, 0xad, 0xbe, 0xef],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_tx_logs_bloom() {
+        use eth_types::address;
+        use ethers_core::types::{Bloom, BloomInput};
+
+        let contract = address!("0x0000000000000000000000000000000000000010");
+        let topics = [Word::from(0xaa), Word::from(0xbb), Word::from(0xcc)];
+        let code = bytecode! {
+            PUSH1(topics[2])
+            PUSH1(topics[1])
+            PUSH1(topics[0])
+            PUSH1(0)
+            PUSH1(0)
+            LOG3
+            STOP
+        };
+        let block: GethData = TestContext::<2, 2>::new(
+            None,
+            |accs| {
+                accs[0].address(contract).code(code);
+                accs[1]
+                    .address(address!("0x0000000000000000000000000000000000cafe01"))
+                    .balance(eth(1));
+            },
+            |mut txs, accs| {
+                txs[0].to(accs[1].address).from(accs[1].address);
+                txs[1].to(accs[0].address).from(accs[1].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+
+        let mut expected = Bloom::default();
+        expected.accrue(BloomInput::Raw(contract.as_bytes()));
+        for topic in topics {
+            expected.accrue(BloomInput::Raw(&topic.to_be_bytes()));
+        }
+        assert_eq!(tx_logs_bloom(&block, 1), expected.0);
+        // the first tx emits no log
+        assert_eq!(tx_logs_bloom(&block, 0), [0u8; 256]);
+    }
+
+    #[test]
+    fn test_tx_receipt() {
+        use eth_types::address;
+
+        let contract = address!("0x0000000000000000000000000000000000000010");
+        let code = bytecode! {
+            PUSH4(0xdeadbeefu64)
+            PUSH1(0)
+            MSTORE
+            PUSH1(0xaa)
+            PUSH1(4)
+            PUSH1(28)
+            LOG1
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0].address(contract).code(code);
+                accs[1]
+                    .address(address!("0x0000000000000000000000000000000000cafe01"))
+                    .balance(eth(1));
+            },
+            |mut txs, accs| {
+                txs[0].to(accs[0].address).from(accs[1].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+
+        let receipt = tx_receipt(&block, 0);
+        assert_eq!(receipt.id, 1);
+        assert_eq!(receipt.status, 1);
+        assert_eq!(receipt.cumulative_gas_used, block_gas_used(&block));
+        assert_eq!(receipt.bloom.0, tx_logs_bloom(&block, 0));
+        assert_eq!(receipt.logs.len(), 1);
+        assert_eq!(receipt.logs[0].address, contract);
+        assert_eq!(receipt.logs[0].topics, vec![H256::from_low_u64_be(0xaa)]);
+        assert_eq!(receipt.logs[0].data.to_vec(), vec![0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[test]
+    fn test_deployed_contracts() {
+        use eth_types::{address, Bytecode};
+        use ethers_core::utils::get_contract_address;
+
+        let runtime_code = bytecode! {
+            PUSH1(0x2a)
+            PUSH1(0)
+            MSTORE
+            STOP
+        }
+        .code();
+        let mut init_code = bytecode! {
+            PUSH6(Word::from_big_endian(&runtime_code))
+            PUSH1(0)
+            MSTORE
+        };
+        init_code.op_return(32 - runtime_code.len(), runtime_code.len());
+        let init_code = init_code.code();
+        let reverting_init_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            REVERT
+        }
+        .code();
+
+        // deploy the contract, then try to deploy another one which reverts
+        let mut code = Bytecode::default();
+        for init_code in [&init_code, &reverting_init_code] {
+            code.append(&bytecode! {
+                PUSH32(Word::from_big_endian(init_code))
+                PUSH1(0)
+                MSTORE
+                PUSH1(init_code.len())
+                PUSH1(32 - init_code.len())
+                PUSH1(0)
+                CREATE
+                POP
+            });
+        }
+        code.op_stop();
+
+        let creator = address!("0x0000000000000000000000000000000000000010");
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(creator)
+                    .nonce(Word::one())
+                    .balance(eth(1))
+                    .code(code);
+                accs[1]
+                    .address(address!("0x0000000000000000000000000000000000cafe01"))
+                    .balance(eth(1));
+            },
+            |mut txs, accs| {
+                txs[0].to(accs[0].address).from(accs[1].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+
+        assert_eq!(
+            deployed_contracts(&block),
+            vec![(
+                get_contract_address(creator, Word::one()),
+                runtime_code.into()
+            )]
+        );
+        // the reverted creation is a create call, but isn't listed
+        let create_calls = block.txs[0].calls.iter().filter(|call| call.is_create);
+        assert_eq!(create_calls.count(), 2);
+    }
+
+    #[test]
+    fn test_validate_block_context() {
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode! { STOP })
+            .unwrap()
+            .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let witness_block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+        let header = block.eth_block;
+        assert!(validate_block_context(&witness_block, &[header.clone()]).is_ok());
+
+        // the header lacks or has another base fee
+        assert!(validate_block_context(&witness_block, &[]).is_err());
+        let mut tampered_header = header.clone();
+        tampered_header.base_fee_per_gas = Some(header.base_fee_per_gas.unwrap_or_default() + 1);
+        assert!(matches!(
+            validate_block_context(&witness_block, &[tampered_header]),
+            Err(Error::InternalError(_))
+        ));
+
+        // a following block context, with a consecutive number or not
+        let block_num = header.number.unwrap().as_u64();
+        for (next_num, valid) in [(block_num + 1, true), (block_num + 2, false)] {
+            let mut witness_block = witness_block.clone();
+            let mut next_ctx = witness_block.context.ctxs[&block_num].clone();
+            next_ctx.number = next_num.into();
+            witness_block.context.ctxs.insert(next_num, next_ctx);
+            let next_header = eth_types::Block {
+                number: Some(next_num.into()),
+                ..header.clone()
+            };
+            assert_eq!(
+                validate_block_context(&witness_block, &[header.clone(), next_header]).is_ok(),
+                valid
+            );
+        }
+    }
+
+    #[test]
+    fn test_diff_witness_blocks() {
+        let witness_block = |code: eth_types::Bytecode| {
+            let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+                .unwrap()
+                .into();
+            let mut builder =
+                BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+            builder
+                .handle_block(&block.eth_block, &block.geth_traces)
+                .unwrap();
+            block_convert::<Fr>(&builder.block, &builder.code_db).unwrap()
+        };
+        let code = bytecode! {
+            PUSH1(1)
+            PUSH1(0)
+            SSTORE
+            STOP
+        };
+        let code_extra_sstore = bytecode! {
+            PUSH1(1)
+            PUSH1(0)
+            SSTORE
+            PUSH1(2)
+            PUSH1(1)
+            SSTORE
+            STOP
+        };
+        let a = witness_block(code.clone());
+        let b = witness_block(code_extra_sstore);
+
+        assert!(diff_witness_blocks(&a, &witness_block(code)).is_empty());
+
+        let diff = diff_witness_blocks(&a, &b);
+        let num_steps = a.txs[0].steps.len();
+        assert_eq!(diff.step_counts, vec![(0, num_steps, num_steps + 3)]);
+        assert!(diff.rw_counts.contains(&(RwTableTag::AccountStorage, 1, 2)));
+        // the STOP of `a` is replaced by the PUSH1 of the extra SSTORE
+        let (tx_index, step_index, step_a, step_b) = diff.first_step_diff.unwrap();
+        assert_eq!((tx_index, step_index), (0, num_steps - 2));
+        assert_eq!(step_a.unwrap().opcode, Some(OpcodeId::STOP));
+        assert_eq!(step_b.unwrap().opcode, Some(OpcodeId::PUSH1));
+    }
+
+    #[test]
+    fn test_opcode_histogram() {
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode! {
+            PUSH1(1)
+            PUSH1(2)
+            ADD
+            PUSH1(0)
+            SSTORE
+            PUSH1(3)
+            PUSH1(1)
+            SSTORE
+            STOP
+        })
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+
+        let histogram = opcode_histogram(&block);
+        assert_eq!(histogram[&OpcodeId::PUSH1], 5);
+        assert_eq!(histogram[&OpcodeId::ADD], 1);
+        assert_eq!(histogram[&OpcodeId::SSTORE], 2);
+        assert_eq!(histogram[&OpcodeId::STOP], 1);
+        // BeginTx and EndTx are not counted
+        assert_eq!(
+            histogram.values().sum::<usize>(),
+            block.txs[0].steps.len() - 2
+        );
+    }
+
+    #[test]
+    fn test_state_transition_witness() {
+        use crate::witness::TrieUpdateKey;
+        use mock::MOCK_ACCOUNTS;
+
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode! {
+            PUSH1(1)
+            PUSH1(0)
+            SSTORE
+            STOP
+        })
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let mut block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+        block_mocking_apply_mpt(&mut block);
+
+        let witness = state_transition_witness(&block);
+        let storage_updates = witness
+            .updates
+            .iter()
+            .filter(|update| matches!(update.key, TrieUpdateKey::Storage { .. }))
+            .collect::<Vec<_>>();
+        assert_eq!(storage_updates.len(), 1);
+        assert_eq!(
+            storage_updates[0].key,
+            TrieUpdateKey::Storage {
+                address: MOCK_ACCOUNTS[0],
+                storage_key: Word::zero(),
+            }
+        );
+        assert_eq!(
+            (storage_updates[0].old_value, storage_updates[0].new_value),
+            (Word::zero(), Word::one())
+        );
+        // the updates chain the roots from the previous to the new state root
+        for pair in witness.updates.windows(2) {
+            assert_eq!(pair[0].new_root, pair[1].old_root);
+        }
+        assert_eq!(
+            witness.state_root,
+            witness.updates.last().map(|u| u.new_root)
+        );
+    }
+
+    fn transfer_block(number: u64, from: Address, to: Address) -> Block<Fr> {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0].address(from).balance(eth(10));
+                accs[1].address(to).balance(eth(1));
+            },
+            |mut txs, accs| {
+                // no fee, so that both blocks leave the coinbase untouched
+                txs[0]
+                    .from(accs[0].address)
+                    .to(accs[1].address)
+                    .value(eth(1))
+                    .gas_price(Word::zero());
+            },
+            |block, _tx| block.number(number),
+        )
+        .unwrap()
+        .into();
+        let params = CircuitsParams {
+            max_txs: 2,
+            ..Default::default()
+        };
+        let mut builder = BlockData::new_from_geth_data_with_params(block.clone(), params)
+            .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        block_convert(&builder.block, &builder.code_db).unwrap()
+    }
+
+    #[test]
+    fn test_merge_witness_blocks() {
+        use crate::test_util::CircuitTestBuilder;
+        use mock::MOCK_ACCOUNTS;
+
+        let first = transfer_block(0xcafe, MOCK_ACCOUNTS[0], MOCK_ACCOUNTS[1]);
+        let second = transfer_block(0xcaff, MOCK_ACCOUNTS[2], MOCK_ACCOUNTS[3]);
+        let gas_used = block_gas_used(&first) + block_gas_used(&second);
+        let merged = merge_witness_blocks(vec![first, second]).unwrap();
+
+        assert_eq!(
+            merged.txs.iter().map(|tx| tx.id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            merged.context.ctxs.keys().copied().collect::<Vec<_>>(),
+            vec![0xcafe, 0xcaff]
+        );
+        assert_eq!(block_gas_used(&merged), gas_used);
+        // the EndTx step of the second tx reads the cumulative gas of the first
+        assert_eq!(merged.rws.rw_num(RwTableTag::TxReceipt), 3 + 4);
+
+        CircuitTestBuilder::<4, 2>::new_from_block(merged).run();
+    }
+
+    #[test]
+    fn test_merge_witness_blocks_beyond_capacity() {
+        use mock::MOCK_ACCOUNTS;
+
+        let mut first = transfer_block(0xcafe, MOCK_ACCOUNTS[0], MOCK_ACCOUNTS[1]);
+        first.circuits_params.max_txs = 1;
+        let second = transfer_block(0xcaff, MOCK_ACCOUNTS[2], MOCK_ACCOUNTS[3]);
+
+        assert!(merge_witness_blocks(vec![first, second]).is_err());
+    }
+}
@@ -102,6 +102,40 @@ pub struct MptUpdates {
     pub(crate) proof_types: Vec<MPTProofType>,
 }
 
+/// Key of a [`TrieUpdate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrieUpdateKey {
+    /// A field of an account
+    Account {
+        /// Address of the account
+        address: Address,
+        /// The updated field
+        field_tag: AccountFieldTag,
+    },
+    /// A storage slot of an account
+    Storage {
+        /// Address of the account
+        address: Address,
+        /// The updated slot
+        storage_key: Word,
+    },
+}
+
+/// A change of the state trie, i.e. an [`MptUpdate`] whose value changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrieUpdate {
+    /// Updated account field or storage slot
+    pub key: TrieUpdateKey,
+    /// Value before the update
+    pub old_value: Word,
+    /// Value after the update
+    pub new_value: Word,
+    /// State root before the update
+    pub old_root: Word,
+    /// State root after the update
+    pub new_root: Word,
+}
+
 /// The field element encoding of an MPT update, which is used by the MptTable
 #[derive(Debug, Clone, Copy)]
 pub struct MptUpdateRow<F>(pub(crate) [F; 7]);
@@ -336,6 +370,34 @@ impl MptUpdates {
             .collect()
     }
 
+    /// The updates changing the state, in the order in which they are applied
+    /// to the trie to compute the new state root.
+    pub fn trie_updates(&self) -> Vec<TrieUpdate> {
+        self.updates
+            .values()
+            .filter(|update| update.old_value != update.new_value)
+            .map(|update| TrieUpdate {
+                key: match update.key {
+                    Key::Account { address, field_tag } => {
+                        TrieUpdateKey::Account { address, field_tag }
+                    }
+                    Key::AccountStorage {
+                        address,
+                        storage_key,
+                        ..
+                    } => TrieUpdateKey::Storage {
+                        address,
+                        storage_key,
+                    },
+                },
+                old_value: update.old_value,
+                new_value: update.new_value,
+                old_root: update.old_root,
+                new_root: update.new_root,
+            })
+            .collect()
+    }
+
     fn insert(&mut self, update: MptUpdate) {
         self.updates.insert(update.key, update);
     }